@@ -1,5 +1,5 @@
-use clap::{Parser, Subcommand};
-use futures::{SinkExt, StreamExt};
+use clap::{Args, Parser, Subcommand};
+use futures::StreamExt;
 use reqwest::Client;
 
 #[derive(Parser)]
@@ -24,37 +24,168 @@ enum Commands {
         #[arg(short, long)]
         level: Option<String>,
 
+        /// Only levels at least this severe, e.g. warn
+        #[arg(long)]
+        min_level: Option<String>,
+
         /// Filter by trace ID
         #[arg(short, long)]
         trace: Option<String>,
+
+        /// Query language filter, e.g. 'level:>=warn -meta.path:/health'
+        #[arg(short, long)]
+        query: Option<String>,
     },
 
     /// Search logs
     #[command(name = "search")]
     Search {
-        /// Full-text search query
+        /// Query, e.g. 'project:api level:>=warn "db timeout"'
         query: Option<String>,
 
         /// Filter by project
         #[arg(short, long)]
         project: Option<String>,
 
-        /// Filter by level
+        /// Filter by level (comma-separated)
         #[arg(short, long)]
         level: Option<String>,
 
+        /// Only levels at least this severe, e.g. warn
+        #[arg(long)]
+        min_level: Option<String>,
+
         /// Filter by trace ID
         #[arg(short, long)]
         trace: Option<String>,
 
-        /// Time range (e.g. 5m, 1h, 2d)
-        #[arg(short, long)]
-        since: Option<String>,
+        #[command(flatten)]
+        time: TimeArgs,
 
         /// Max results
-        #[arg(short, long, default_value = "20")]
+        #[arg(short = 'n', long, default_value = "20")]
         limit: usize,
 
+        /// Sort by client `timestamp` or server `received` time
+        #[arg(long)]
+        sort: Option<String>,
+
+        /// Continue from a cursor printed by a previous search
+        #[arg(short, long)]
+        cursor: Option<String>,
+
+        /// Filter on a promoted meta key, e.g. `status=>=500`, `userId=42`, `region=exists`
+        #[arg(short, long = "meta", value_name = "KEY=VALUE")]
+        meta: Vec<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Export every matching log to a file or stdout
+    Export {
+        /// Query, e.g. 'project:api level:>=warn "db timeout"'
+        query: Option<String>,
+
+        /// Filter by project
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Filter by level (comma-separated)
+        #[arg(short, long)]
+        level: Option<String>,
+
+        /// Only levels at least this severe, e.g. warn
+        #[arg(long)]
+        min_level: Option<String>,
+
+        /// Filter by trace ID
+        #[arg(short, long)]
+        trace: Option<String>,
+
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// ndjson, csv or json
+        #[arg(short, long, default_value = "ndjson")]
+        format: String,
+
+        /// CSV columns (comma-separated), e.g. timestamp,level,meta.http.status
+        #[arg(long)]
+        fields: Option<String>,
+
+        /// Stop after this many logs
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Chart log volume over time
+    Histogram {
+        /// Query, e.g. 'project:api level:>=warn "db timeout"'
+        query: Option<String>,
+
+        /// Filter by project
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Filter by level (comma-separated)
+        #[arg(short, long)]
+        level: Option<String>,
+
+        /// Only levels at least this severe, e.g. warn
+        #[arg(long)]
+        min_level: Option<String>,
+
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Bucket width, e.g. 1m (picked automatically by default)
+        #[arg(short, long)]
+        interval: Option<String>,
+
+        /// Split counts by level or project
+        #[arg(short, long)]
+        group_by: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare two time windows: what changed between them
+    Diff {
+        /// Query, e.g. 'project:api level:>=warn'; '@name' runs a saved search
+        query: Option<String>,
+
+        /// Filter by project
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Filter by level (comma-separated)
+        #[arg(short, long)]
+        level: Option<String>,
+
+        /// Only levels at least this severe, e.g. warn
+        #[arg(long)]
+        min_level: Option<String>,
+
+        /// Window under investigation, e.g. now-1h..now (the default)
+        #[arg(short, long)]
+        target: Option<String>,
+
+        /// Window to compare against (default: the same length just before target)
+        #[arg(short, long)]
+        baseline: Option<String>,
+
+        /// Entries per list
+        #[arg(long)]
+        limit: Option<usize>,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -66,18 +197,198 @@ enum Commands {
     /// Show full trace timeline
     Trace {
         trace_id: String,
+
+        /// Show the span hierarchy as a waterfall
+        #[arg(long)]
+        tree: bool,
+    },
+
+    /// Show log entries by id
+    Get {
+        /// One or more log entry ids
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+
+    /// Show the logs around one entry
+    Context {
+        /// Log entry id
+        id: String,
+
+        /// Entries before the entry
+        #[arg(short = 'B', long, default_value = "50")]
+        before: usize,
+
+        /// Entries after the entry
+        #[arg(short = 'A', long, default_value = "50")]
+        after: usize,
+
+        /// Neighbors from the same project, request, trace or source
+        #[arg(long, default_value = "project")]
+        scope: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show error summary
     Errors {
-        /// Time range
-        #[arg(short, long, default_value = "1h")]
-        since: String,
+        /// Time range; defaults to the last hour
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Filter by project
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Include levels at least this severe (default: error)
+        #[arg(long)]
+        min_level: Option<String>,
+    },
+
+    /// List error issues (errors grouped by fingerprint)
+    Issues {
+        /// Only issues seen within this time range
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Filter by project
+        #[arg(short, long)]
+        project: Option<String>,
+
+        /// Filter by status: unresolved, resolved or ignored
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Sort by lastSeen, firstSeen or count
+        #[arg(long)]
+        sort: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List, show, save or delete saved searches (run them with `search @name`)
+    Saved {
+        #[command(subcommand)]
+        action: SavedAction,
+    },
+
+    /// Change an issue's status
+    Issue {
+        #[command(subcommand)]
+        action: IssueAction,
+    },
+}
+
+/// Time range flags shared by commands that query stored logs.
+#[derive(Args)]
+struct TimeArgs {
+    /// Time range ending now (e.g. 5m, 1h30m, 2d)
+    #[arg(short, long)]
+    since: Option<String>,
+
+    /// Range start: RFC 3339, epoch millis, now-2h, yesterday
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Range end, same syntax as --from
+    #[arg(long)]
+    to: Option<String>,
+}
+
+impl TimeArgs {
+    fn push_params<'a>(&'a self, params: &mut Vec<(&'a str, &'a str)>) {
+        for (key, value) in [
+            ("since", &self.since),
+            ("from", &self.from),
+            ("to", &self.to),
+        ] {
+            if let Some(v) = value {
+                params.push((key, v.as_str()));
+            }
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum IssueAction {
+    /// Mark an issue resolved; a new occurrence is flagged as a regression
+    Resolve {
+        fingerprint: String,
+
+        /// Only occurrences from this release onward count as regressions
+        #[arg(long)]
+        version: Option<String>,
+    },
+
+    /// Ignore an issue, optionally for a while or until a release
+    Ignore {
+        fingerprint: String,
+
+        /// Ignore duration (e.g. 24h)
+        #[arg(long = "for")]
+        duration: Option<String>,
+
+        /// Reopen once this release reports the issue
+        #[arg(long)]
+        version: Option<String>,
+    },
+
+    /// Reopen a resolved or ignored issue
+    Reopen { fingerprint: String },
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum SavedAction {
+    /// List saved searches
+    List {
+        /// Only searches saved by this owner
+        #[arg(long)]
+        owner: Option<String>,
+    },
+
+    /// Show one saved search
+    Show { name: String },
+
+    /// Create or replace a saved search
+    Save {
+        /// Letters, digits, '-' and '_'
+        name: String,
+
+        /// Query, e.g. 'project:api level:>=warn "db timeout"'
+        query: Option<String>,
 
         /// Filter by project
         #[arg(short, long)]
         project: Option<String>,
+
+        /// Filter by level (comma-separated)
+        #[arg(short, long)]
+        level: Option<String>,
+
+        /// Only levels at least this severe, e.g. warn
+        #[arg(long)]
+        min_level: Option<String>,
+
+        /// Filter by trace ID
+        #[arg(short, long)]
+        trace: Option<String>,
+
+        /// Default time range
+        #[command(flatten)]
+        time: TimeArgs,
+
+        /// Who the search belongs to
+        #[arg(long)]
+        owner: Option<String>,
     },
+
+    /// Delete a saved search
+    Delete { name: String },
 }
 
 #[tokio::main]
@@ -85,28 +396,156 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Tail { project, level, trace } => {
-            tail(&cli.server, project, level, trace).await?;
+        Commands::Tail {
+            project,
+            level,
+            min_level,
+            trace,
+            query,
+        } => {
+            tail(&cli.server, project, level, min_level, trace, query).await?;
         }
         Commands::Search {
             query,
             project,
             level,
+            min_level,
             trace,
-            since,
+            time,
             limit,
+            sort,
+            cursor,
+            meta,
             json,
         } => {
-            search(&cli.server, query, project, level, trace, since, limit, json).await?;
+            search(
+                &cli.server,
+                query,
+                project,
+                level,
+                min_level,
+                trace,
+                time,
+                limit,
+                sort,
+                cursor,
+                meta,
+                json,
+            )
+            .await?;
+        }
+        Commands::Export {
+            query,
+            project,
+            level,
+            min_level,
+            trace,
+            time,
+            format,
+            fields,
+            limit,
+            output,
+        } => {
+            export(
+                &cli.server,
+                query,
+                project,
+                level,
+                min_level,
+                trace,
+                time,
+                format,
+                fields,
+                limit,
+                output,
+            )
+            .await?;
+        }
+        Commands::Histogram {
+            query,
+            project,
+            level,
+            min_level,
+            time,
+            interval,
+            group_by,
+            json,
+        } => {
+            histogram(
+                &cli.server,
+                query,
+                project,
+                level,
+                min_level,
+                time,
+                interval,
+                group_by,
+                json,
+            )
+            .await?;
+        }
+        Commands::Diff {
+            query,
+            project,
+            level,
+            min_level,
+            target,
+            baseline,
+            limit,
+            json,
+        } => {
+            diff(
+                &cli.server,
+                query,
+                project,
+                level,
+                min_level,
+                target,
+                baseline,
+                limit,
+                json,
+            )
+            .await?;
         }
         Commands::Projects => {
             projects(&cli.server).await?;
         }
-        Commands::Trace { trace_id } => {
-            trace(&cli.server, trace_id).await?;
+        Commands::Trace { trace_id, tree } => {
+            trace(&cli.server, trace_id, tree).await?;
+        }
+        Commands::Get { ids } => {
+            get(&cli.server, ids).await?;
+        }
+        Commands::Context {
+            id,
+            before,
+            after,
+            scope,
+            json,
+        } => {
+            context(&cli.server, id, before, after, scope, json).await?;
+        }
+        Commands::Errors {
+            time,
+            project,
+            min_level,
+        } => {
+            errors(&cli.server, time, project, min_level).await?;
+        }
+        Commands::Issues {
+            time,
+            project,
+            status,
+            sort,
+            json,
+        } => {
+            issues(&cli.server, time, project, status, sort, json).await?;
         }
-        Commands::Errors { since, project } => {
-            errors(&cli.server, since, project).await?;
+        Commands::Saved { action } => {
+            saved(&cli.server, action).await?;
+        }
+        Commands::Issue { action } => {
+            issue_status(&cli.server, action).await?;
         }
     }
 
@@ -119,7 +558,9 @@ async fn tail(
     server: &str,
     project: Option<String>,
     level: Option<String>,
+    min_level: Option<String>,
     trace: Option<String>,
+    query: Option<String>,
 ) -> anyhow::Result<()> {
     use tokio_tungstenite::{connect_async, tungstenite::Message};
 
@@ -130,9 +571,23 @@ async fn tail(
     if let Some(ref l) = level {
         url.push_str(&format!("&levels={}", l));
     }
+    if let Some(ref m) = min_level {
+        url.push_str(&format!("&minLevel={}", m));
+    }
     if let Some(ref t) = trace {
         url.push_str(&format!("&traceId={}", t));
     }
+    let (saved, query) = split_saved(query.as_deref());
+    if saved.is_some() || query.is_some() {
+        let mut parsed = reqwest::Url::parse(&url)?;
+        if let Some(name) = saved {
+            parsed.query_pairs_mut().append_pair("saved", name);
+        }
+        if let Some(q) = query {
+            parsed.query_pairs_mut().append_pair("query", q);
+        }
+        url = parsed.to_string();
+    }
 
     println!("Connecting to {}...", url);
 
@@ -147,6 +602,13 @@ async fn tail(
                 if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
                     if let Some(data) = v.get("data") {
                         print_log(data);
+                    } else if v.get("type").and_then(|t| t.as_str()) == Some("error") {
+                        anyhow::bail!(
+                            "{}",
+                            v.get("error")
+                                .and_then(|e| e.as_str())
+                                .unwrap_or("subscribe failed")
+                        );
                     } else if let Some(_connected) = v.get("type").and_then(|t| t.as_str()) {
                         println!("✓ Connected with filters: {:?}", v.get("filters"));
                     }
@@ -160,22 +622,39 @@ async fn tail(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn search(
     server: &str,
     query: Option<String>,
     project: Option<String>,
     level: Option<String>,
+    min_level: Option<String>,
     trace: Option<String>,
-    since: Option<String>,
+    time: TimeArgs,
     limit: usize,
+    sort: Option<String>,
+    cursor: Option<String>,
+    meta: Vec<String>,
     json: bool,
 ) -> anyhow::Result<()> {
     let client = Client::new();
     let url = format!("{}/search", server);
 
+    let meta_params = meta
+        .iter()
+        .map(|m| match m.split_once('=') {
+            Some((k, v)) => Ok((format!("meta.{}", k.trim_start_matches("meta.")), v)),
+            None => Err(anyhow::anyhow!("--meta expects KEY=VALUE, got {:?}", m)),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
     let mut params = vec![];
-    if let Some(ref q) = query {
-        params.push(("q", q.as_str()));
+    let (saved, query) = split_saved(query.as_deref());
+    if let Some(name) = saved {
+        params.push(("saved", name));
+    }
+    if let Some(q) = query {
+        params.push(("query", q));
     }
     if let Some(ref p) = project {
         params.push(("project", p.as_str()));
@@ -183,18 +662,38 @@ async fn search(
     if let Some(ref l) = level {
         params.push(("level", l.as_str()));
     }
+    if let Some(ref m) = min_level {
+        params.push(("minLevel", m.as_str()));
+    }
     if let Some(ref t) = trace {
         params.push(("trace_id", t.as_str()));
     }
-    if let Some(ref s) = since {
-        params.push(("since", s.as_str()));
+    time.push_params(&mut params);
+    if let Some(ref s) = sort {
+        params.push(("sort", s.as_str()));
+    }
+    if let Some(ref c) = cursor {
+        params.push(("cursor", c.as_str()));
     }
     let limit_str = limit.to_string();
     params.push(("limit", limit_str.as_str()));
+    for (k, v) in &meta_params {
+        params.push((k.as_str(), v));
+    }
 
     let url_with_params = reqwest::Url::parse_with_params(&url, &params)?;
     let resp = client.get(url_with_params).send().await?;
 
+    if resp.status() == reqwest::StatusCode::BAD_REQUEST {
+        let body: serde_json::Value = resp.json().await?;
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("bad request")
+        );
+    }
+
     if json {
         let body: serde_json::Value = resp.json().await?;
         println!("{}", serde_json::to_string_pretty(&body)?);
@@ -209,6 +708,11 @@ async fn search(
                 print_log(hit);
             }
         }
+        for (label, key) in [("Older", "nextCursor"), ("Newer", "prevCursor")] {
+            if let Some(c) = body.get(key).and_then(|c| c.as_str()) {
+                println!("\n{}: --cursor {}", label, c);
+            }
+        }
     }
 
     Ok(())
@@ -226,34 +730,740 @@ async fn projects(server: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn trace(server: &str, trace_id: String) -> anyhow::Result<()> {
+async fn trace(server: &str, trace_id: String, tree: bool) -> anyhow::Result<()> {
     let client = Client::new();
-    let resp = client
-        .get(format!("{}/trace/{}", server, trace_id))
-        .send()
-        .await?;
+    let mut url = format!("{}/trace/{}", server, trace_id);
+    if tree {
+        url.push_str("?view=tree");
+    }
+    let resp = client.get(url).send().await?;
     let body: serde_json::Value = resp.json().await?;
 
-    println!("{}", serde_json::to_string_pretty(&body)?);
+    if !tree {
+        println!("{}", serde_json::to_string_pretty(&body)?);
+        return Ok(());
+    }
+
+    let start = body["startMs"].as_i64().unwrap_or(0);
+    let duration = body["durationMs"].as_i64().unwrap_or(0);
+    println!(
+        "Trace {}  {}ms  {} spans, {} entries{}",
+        trace_id,
+        duration,
+        body["spanCount"],
+        body["entryCount"],
+        if body["error"].as_bool() == Some(true) {
+            "  \x1b[31m✗ error\x1b[0m"
+        } else {
+            ""
+        }
+    );
+    for span in body["roots"].as_array().into_iter().flatten() {
+        print_span(span, 0, start, duration);
+    }
+    let orphans = body["orphans"].as_array().cloned().unwrap_or_default();
+    if !orphans.is_empty() {
+        println!("\nOrphan spans (parent not in trace)");
+        for span in &orphans {
+            print_span(span, 0, start, duration);
+        }
+    }
+    if let Some(n) = body["unspanned"].as_u64().filter(|n| *n > 0) {
+        println!("\n{} entries without a span", n);
+    }
+    if body["truncated"].as_bool() == Some(true) {
+        println!(
+            "\n\x1b[33mTruncated: built from the newest {} entries only\x1b[0m",
+            body["entryCount"]
+        );
+    }
+    Ok(())
+}
+
+/// One waterfall row per span, children indented below their parent.
+fn print_span(span: &serde_json::Value, depth: usize, trace_start: i64, trace_ms: i64) {
+    const LABEL: usize = 40;
+    const WIDTH: i64 = 40;
+    let offset = span["startMs"].as_i64().unwrap_or(trace_start) - trace_start;
+    let ms = span["durationMs"].as_i64().unwrap_or(0);
+    let (lead, len) = if trace_ms == 0 {
+        (0, 1)
+    } else {
+        let lead = (offset * WIDTH / trace_ms).clamp(0, WIDTH - 1);
+        (lead, (ms * WIDTH / trace_ms).clamp(1, WIDTH - lead))
+    };
+
+    let mut label = format!(
+        "{}{} ({})",
+        "  ".repeat(depth),
+        span["name"].as_str().unwrap_or(""),
+        span["project"].as_str().unwrap_or("")
+    );
+    if label.chars().count() > LABEL {
+        label = label.chars().take(LABEL - 1).collect::<String>() + "…";
+    }
+    let error = span["error"].as_bool() == Some(true);
+    println!(
+        "{:<label_width$} |{}{}{}\x1b[0m{}| {:>6}ms{}",
+        label,
+        " ".repeat(lead as usize),
+        if error { "\x1b[31m" } else { "\x1b[36m" },
+        "█".repeat(len as usize),
+        " ".repeat((WIDTH - lead - len) as usize),
+        ms,
+        if error { " \x1b[31m✗\x1b[0m" } else { "" },
+        label_width = LABEL
+    );
+    for child in span["children"].as_array().into_iter().flatten() {
+        print_span(child, depth + 1, trace_start, trace_ms);
+    }
+}
+
+async fn get(server: &str, ids: Vec<String>) -> anyhow::Result<()> {
+    let client = Client::new();
+    let url = match ids.as_slice() {
+        [id] => reqwest::Url::parse(&format!("{}/logs/{}", server, id))?,
+        _ => {
+            reqwest::Url::parse_with_params(&format!("{}/logs", server), &[("ids", ids.join(","))])?
+        }
+    };
+
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await.unwrap_or_default();
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or(status.as_str())
+        );
+    }
+    let body: serde_json::Value = resp.json().await?;
+
+    if let Some(log) = body.get("log") {
+        println!("{}", serde_json::to_string_pretty(log)?);
+        return Ok(());
+    }
+    println!("{}", serde_json::to_string_pretty(&body["logs"])?);
+    for id in body["missing"].as_array().into_iter().flatten() {
+        eprintln!("Not found: {}", id.as_str().unwrap_or_default());
+    }
+    Ok(())
+}
+
+async fn context(
+    server: &str,
+    id: String,
+    before: usize,
+    after: usize,
+    scope: String,
+    json: bool,
+) -> anyhow::Result<()> {
+    let before = before.to_string();
+    let after = after.to_string();
+    let params = [
+        ("before", before.as_str()),
+        ("after", after.as_str()),
+        ("scope", scope.as_str()),
+    ];
+    let url = reqwest::Url::parse_with_params(&format!("{}/logs/{}/context", server, id), &params)?;
+
+    let resp = Client::new().get(url).send().await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await.unwrap_or_default();
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or(status.as_str())
+        );
+    }
+    let body: serde_json::Value = resp.json().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&body)?);
+        return Ok(());
+    }
+    for entry in body["entries"].as_array().into_iter().flatten() {
+        if entry.get("anchor").and_then(|a| a.as_bool()) == Some(true) {
+            print!("\x1b[1m>\x1b[0m ");
+        } else {
+            print!("  ");
+        }
+        print_log(entry);
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn export(
+    server: &str,
+    query: Option<String>,
+    project: Option<String>,
+    level: Option<String>,
+    min_level: Option<String>,
+    trace: Option<String>,
+    time: TimeArgs,
+    format: String,
+    fields: Option<String>,
+    limit: Option<usize>,
+    output: Option<std::path::PathBuf>,
+) -> anyhow::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut params = vec![("format", format.as_str())];
+    let (saved, query) = split_saved(query.as_deref());
+    if let Some(name) = saved {
+        params.push(("saved", name));
+    }
+    if let Some(q) = query {
+        params.push(("query", q));
+    }
+    if let Some(ref p) = project {
+        params.push(("project", p.as_str()));
+    }
+    if let Some(ref l) = level {
+        params.push(("level", l.as_str()));
+    }
+    if let Some(ref m) = min_level {
+        params.push(("minLevel", m.as_str()));
+    }
+    if let Some(ref t) = trace {
+        params.push(("trace_id", t.as_str()));
+    }
+    time.push_params(&mut params);
+    if let Some(ref f) = fields {
+        params.push(("fields", f.as_str()));
+    }
+    let limit_str = limit.map(|l| l.to_string());
+    if let Some(ref l) = limit_str {
+        params.push(("limit", l.as_str()));
+    }
+    let url = reqwest::Url::parse_with_params(&format!("{}/export", server), &params)?;
+
+    let mut resp = Client::new().get(url).send().await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await.unwrap_or_default();
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or(status.as_str())
+        );
+    }
+
+    let mut out: Box<dyn tokio::io::AsyncWrite + Unpin> = match output {
+        Some(ref path) => Box::new(tokio::fs::File::create(path).await?),
+        None => Box::new(tokio::io::stdout()),
+    };
+    let mut bytes = 0;
+    while let Some(chunk) = resp.chunk().await? {
+        bytes += chunk.len();
+        out.write_all(&chunk).await?;
+    }
+    out.flush().await?;
+
+    if let Some(path) = output {
+        eprintln!("Wrote {} bytes to {}", bytes, path.display());
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn histogram(
+    server: &str,
+    query: Option<String>,
+    project: Option<String>,
+    level: Option<String>,
+    min_level: Option<String>,
+    time: TimeArgs,
+    interval: Option<String>,
+    group_by: Option<String>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut params = vec![];
+    let (saved, query) = split_saved(query.as_deref());
+    if let Some(name) = saved {
+        params.push(("saved", name));
+    }
+    if let Some(q) = query {
+        params.push(("query", q));
+    }
+    if let Some(ref p) = project {
+        params.push(("project", p.as_str()));
+    }
+    if let Some(ref l) = level {
+        params.push(("level", l.as_str()));
+    }
+    if let Some(ref m) = min_level {
+        params.push(("minLevel", m.as_str()));
+    }
+    time.push_params(&mut params);
+    if let Some(ref i) = interval {
+        params.push(("interval", i.as_str()));
+    }
+    if let Some(ref g) = group_by {
+        params.push(("groupBy", g.as_str()));
+    }
+    let url = reqwest::Url::parse_with_params(&format!("{}/histogram", server), &params)?;
+
+    let resp = Client::new().get(url).send().await?;
+    if resp.status() == reqwest::StatusCode::BAD_REQUEST {
+        let body: serde_json::Value = resp.json().await?;
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("bad request")
+        );
+    }
+    let body: serde_json::Value = resp.json().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&body)?);
+        return Ok(());
+    }
+
+    let buckets = body["buckets"].as_array().cloned().unwrap_or_default();
+    let counts: Vec<u64> = buckets
+        .iter()
+        .map(|b| b["count"].as_u64().unwrap_or(0))
+        .collect();
+    let max = counts.iter().copied().max().unwrap_or(0);
+    println!(
+        "{} logs in {} buckets of {}",
+        body["total"],
+        buckets.len(),
+        body["interval"].as_str().unwrap_or("?")
+    );
+    println!("{}\n", sparkline(&counts, max));
+
+    const WIDTH: u64 = 40;
+    for (bucket, count) in buckets.iter().zip(&counts) {
+        let time = bucket["time"].as_str().unwrap_or("");
+        let bar = if max == 0 {
+            0
+        } else {
+            (count * WIDTH).div_ceil(max)
+        };
+        let mut line = format!(
+            "{}  {:<width$}  {}",
+            time.trim_end_matches('Z').replace('T', " "),
+            "█".repeat(bar as usize),
+            count,
+            width = WIDTH as usize
+        );
+        if let Some(groups) = bucket["groups"].as_object() {
+            let mut groups: Vec<_> = groups
+                .iter()
+                .filter_map(|(k, v)| Some((k, v.as_u64()?)))
+                .collect();
+            groups.sort_by_key(|(_, v)| std::cmp::Reverse(*v));
+            let parts: Vec<String> = groups.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            if !parts.is_empty() {
+                line.push_str(&format!("  ({})", parts.join(" ")));
+            }
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn diff(
+    server: &str,
+    query: Option<String>,
+    project: Option<String>,
+    level: Option<String>,
+    min_level: Option<String>,
+    target: Option<String>,
+    baseline: Option<String>,
+    limit: Option<usize>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut params = vec![];
+    let (saved, query) = split_saved(query.as_deref());
+    if let Some(name) = saved {
+        params.push(("saved", name.to_string()));
+    }
+    if let Some(q) = query {
+        params.push(("query", q.to_string()));
+    }
+    for (key, value) in [
+        ("project", project),
+        ("level", level),
+        ("minLevel", min_level),
+        ("target", target),
+        ("baseline", baseline),
+        ("limit", limit.map(|l| l.to_string())),
+    ] {
+        if let Some(v) = value {
+            params.push((key, v));
+        }
+    }
+    let url = reqwest::Url::parse_with_params(&format!("{}/diff", server), &params)?;
+
+    let resp = Client::new().get(url).send().await?;
+    if resp.status() == reqwest::StatusCode::BAD_REQUEST {
+        let body: serde_json::Value = resp.json().await?;
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("bad request")
+        );
+    }
+    let body: serde_json::Value = resp.json().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&body)?);
+        return Ok(());
+    }
+
+    let window = |w: &serde_json::Value| {
+        let time = |ms: &serde_json::Value| {
+            ms.as_i64()
+                .and_then(chrono::DateTime::from_timestamp_millis)
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default()
+        };
+        format!(
+            "{} → {}  ({} logs)",
+            time(&w["from"]),
+            time(&w["to"]),
+            w["total"]
+        )
+    };
+    println!("Baseline  {}", window(&body["baseline"]));
+    println!("Target    {}", window(&body["target"]));
+
+    let change = |c: &serde_json::Value| {
+        let ratio = c["ratio"]
+            .as_f64()
+            .map(|r| format!("  ×{}", r))
+            .unwrap_or_default();
+        format!(
+            "{:>7} → {:<7} {:>+9.1}{}",
+            c["baseline"],
+            c["target"],
+            c["delta"].as_f64().unwrap_or(0.0),
+            ratio
+        )
+    };
+    for (key, title) in [
+        ("projects", "Projects"),
+        ("levels", "Levels"),
+        ("fingerprints", "Issues"),
+    ] {
+        let changes = body[key].as_array().cloned().unwrap_or_default();
+        if changes.is_empty() {
+            continue;
+        }
+        println!("\n{}", title);
+        for c in &changes {
+            let name = c["title"].as_str().or(c["value"].as_str()).unwrap_or("");
+            println!("  {}  {}", change(c), name);
+        }
+    }
+    for (key, title) in [
+        ("new", "New patterns"),
+        ("gone", "Gone patterns"),
+        ("changed", "Changed patterns"),
+    ] {
+        let patterns = body["patterns"][key]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        if patterns.is_empty() {
+            continue;
+        }
+        println!("\n{}", title);
+        for p in &patterns {
+            println!("  {}  {}", change(p), p["pattern"].as_str().unwrap_or(""));
+        }
+    }
     Ok(())
 }
 
-async fn errors(server: &str, since: String, project: Option<String>) -> anyhow::Result<()> {
+/// One block character per count, scaled to `max`.
+fn sparkline(counts: &[u64], max: u64) -> String {
+    const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    counts
+        .iter()
+        .map(|&c| match (c, max) {
+            (0, _) | (_, 0) => ' ',
+            _ => TICKS[((c * 7).div_ceil(max)) as usize],
+        })
+        .collect()
+}
+
+async fn errors(
+    server: &str,
+    mut time: TimeArgs,
+    project: Option<String>,
+    min_level: Option<String>,
+) -> anyhow::Result<()> {
     let client = Client::new();
-    let mut url = format!("{}/errors?since={}", server, since);
+    if time.since.is_none() && time.from.is_none() {
+        time.since = Some("1h".into());
+    }
+    let mut params = vec![];
+    time.push_params(&mut params);
     if let Some(ref p) = project {
-        url.push_str(&format!("&project={}", p));
+        params.push(("project", p.as_str()));
+    }
+    if let Some(ref m) = min_level {
+        params.push(("minLevel", m.as_str()));
     }
+    let url = reqwest::Url::parse_with_params(&format!("{}/errors", server), &params)?;
 
-    let resp = client.get(&url).send().await?;
+    let resp = client.get(url).send().await?;
+    if resp.status() == reqwest::StatusCode::BAD_REQUEST {
+        let body: serde_json::Value = resp.json().await?;
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("bad request")
+        );
+    }
     let body: serde_json::Value = resp.json().await?;
 
     println!("{}", serde_json::to_string_pretty(&body)?);
     Ok(())
 }
 
+async fn issues(
+    server: &str,
+    time: TimeArgs,
+    project: Option<String>,
+    status: Option<String>,
+    sort: Option<String>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let client = Client::new();
+    let mut params = vec![];
+    time.push_params(&mut params);
+    if let Some(ref p) = project {
+        params.push(("project", p.as_str()));
+    }
+    if let Some(ref s) = status {
+        params.push(("status", s.as_str()));
+    }
+    if let Some(ref s) = sort {
+        params.push(("sort", s.as_str()));
+    }
+    let url = reqwest::Url::parse_with_params(&format!("{}/issues", server), &params)?;
+    let body: serde_json::Value = client.get(url).send().await?.json().await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&body)?);
+        return Ok(());
+    }
+
+    let issues = body
+        .get("issues")
+        .and_then(|i| i.as_array())
+        .cloned()
+        .unwrap_or_default();
+    println!(
+        "{} issues:\n",
+        body.get("totalIssues")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    );
+    for issue in &issues {
+        print_issue(issue);
+    }
+    Ok(())
+}
+
+async fn saved(server: &str, action: SavedAction) -> anyhow::Result<()> {
+    let client = Client::new();
+    let base = format!("{}/saved-searches", server);
+    let request = match action {
+        SavedAction::List { owner } => {
+            let params: Vec<(&str, &str)> = owner.iter().map(|o| ("owner", o.as_str())).collect();
+            client.get(reqwest::Url::parse_with_params(&base, &params)?)
+        }
+        SavedAction::Show { name } => client.get(format!("{}/{}", base, name)),
+        SavedAction::Save {
+            name,
+            query,
+            project,
+            level,
+            min_level,
+            trace,
+            time,
+            owner,
+        } => client
+            .put(format!("{}/{}", base, name))
+            .json(&serde_json::json!({
+                "owner": owner,
+                "query": query,
+                "filters": {
+                    "project": project,
+                    "level": level,
+                    "minLevel": min_level,
+                    "traceId": trace,
+                },
+                "since": time.since,
+                "from": time.from,
+                "to": time.to,
+            })),
+        SavedAction::Delete { name } => client.delete(format!("{}/{}", base, name)),
+    };
+
+    let resp = request.send().await?;
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().await?;
+    if !status.is_success() {
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("request failed")
+        );
+    }
+
+    if let Some(searches) = body.get("savedSearches").and_then(|s| s.as_array()) {
+        if searches.is_empty() {
+            println!("No saved searches");
+        }
+        for search in searches {
+            print_saved(search);
+        }
+    } else if let Some(search) = body.get("savedSearch") {
+        print_saved(search);
+    } else if let Some(name) = body.get("deleted").and_then(|n| n.as_str()) {
+        println!("Deleted @{}", name);
+    }
+    Ok(())
+}
+
+fn print_saved(search: &serde_json::Value) {
+    let name = search["name"].as_str().unwrap_or("");
+    let owner = search["owner"]
+        .as_str()
+        .map(|o| format!(" \x1b[90m({}){}", o, "\x1b[0m"))
+        .unwrap_or_default();
+    println!("\x1b[1m@{}\x1b[0m{}", name, owner);
+
+    let mut parts = Vec::new();
+    if let Some(q) = search["query"].as_str() {
+        parts.push(format!("query={}", q));
+    }
+    if let Some(filters) = search["filters"].as_object() {
+        for (key, value) in filters {
+            if let Some(v) = value.as_str() {
+                parts.push(format!("{}={}", key, v));
+            }
+        }
+    }
+    for key in ["since", "from", "to"] {
+        if let Some(v) = search[key].as_str() {
+            parts.push(format!("{}={}", key, v));
+        }
+    }
+    println!("  {}", parts.join("  "));
+}
+
+async fn issue_status(server: &str, action: IssueAction) -> anyhow::Result<()> {
+    let (fingerprint, body) = match action {
+        IssueAction::Resolve {
+            fingerprint,
+            version,
+        } => (
+            fingerprint,
+            serde_json::json!({ "status": "resolved", "version": version }),
+        ),
+        IssueAction::Ignore {
+            fingerprint,
+            duration,
+            version,
+        } => (
+            fingerprint,
+            serde_json::json!({ "status": "ignored", "for": duration, "version": version }),
+        ),
+        IssueAction::Reopen { fingerprint } => {
+            (fingerprint, serde_json::json!({ "status": "unresolved" }))
+        }
+    };
+
+    let mut url = reqwest::Url::parse(server)?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow::anyhow!("invalid server URL {}", server))?
+        .pop_if_empty()
+        .extend(["issues", fingerprint.as_str(), "status"]);
+
+    let client = Client::new();
+    let resp = client.post(url).json(&body).send().await?;
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().await?;
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "{}",
+            body.get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("request failed")
+        );
+    }
+    if let Some(issue) = body.get("issue") {
+        print_issue(issue);
+    }
+    Ok(())
+}
+
 // ─── Helpers ───
 
+fn print_issue(issue: &serde_json::Value) {
+    let get = |k: &str| issue.get(k).and_then(|v| v.as_str()).unwrap_or("");
+    let count = issue.get("count").and_then(|v| v.as_u64()).unwrap_or(0);
+    let status = match get("status") {
+        "" | "unresolved" => String::new(),
+        s => format!(" \x1b[90m[{}]\x1b[0m", s),
+    };
+    let regressions = issue
+        .get("regressionCount")
+        .and_then(|v| v.as_u64())
+        .filter(|n| *n > 0)
+        .map(|n| format!(" \x1b[35m(regressed {}x)\x1b[0m", n))
+        .unwrap_or_default();
+    println!(
+        "\x1b[31m{}\x1b[0m {:>7}x  {}  {}{}{}",
+        get("fingerprint"),
+        count,
+        get("project"),
+        get("title"),
+        status,
+        regressions
+    );
+    println!(
+        "    \x1b[90mfirst {}  last {}\x1b[0m",
+        get("firstSeen"),
+        get("lastSeen")
+    );
+}
+
+/// Split `@name rest` into a saved search name and the query narrowing it.
+fn split_saved(query: Option<&str>) -> (Option<&str>, Option<&str>) {
+    let Some(q) = query.map(str::trim) else {
+        return (None, None);
+    };
+    match q.strip_prefix('@') {
+        Some(rest) => {
+            let (name, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let rest = rest.trim();
+            (Some(name), (!rest.is_empty()).then_some(rest))
+        }
+        None => (None, Some(q)),
+    }
+}
+
 fn print_log(log: &serde_json::Value) {
     let ts = log
         .get("timestamp")
@@ -272,7 +1482,7 @@ fn print_log(log: &serde_json::Value) {
     let color = match level {
         "error" | "fatal" => "\x1b[31m",
         "warn" => "\x1b[33m",
-        "debug" => "\x1b[90m",
+        "debug" | "trace" => "\x1b[90m",
         "info" => "\x1b[36m",
         _ => "\x1b[0m",
     };
@@ -284,8 +1494,71 @@ fn print_log(log: &serde_json::Value) {
         .map(|t| format!(" [{}]", &t[..8.min(t.len())]))
         .unwrap_or_default();
 
+    let repeats = log
+        .get("repeatCount")
+        .and_then(|v| v.as_u64())
+        .map(|n| format!(" \x1b[90m(repeated {} times){}", n, reset))
+        .unwrap_or_default();
+
     println!(
-        "{}{}{} {:12} {} [{}] {}",
-        color, level, reset, ts, project, trace, message
+        "{}{}{} {:12} {} [{}] {}{}",
+        color, level, reset, ts, project, trace, message, repeats
     );
+
+    if let Some(error) = log.get("error") {
+        print_error(error, MAX_FRAMES);
+    }
+}
+
+/// Frames shown under each log line.
+const MAX_FRAMES: usize = 5;
+
+/// Render an entry's structured exception: `Type: message`, then frames.
+fn print_error(error: &serde_json::Value, max_frames: usize) {
+    let get = |k: &str| error.get(k).and_then(|v| v.as_str());
+    let header = match (get("type"), get("message")) {
+        (Some(t), Some(m)) => format!("{}: {}", t, m),
+        (Some(t), None) => t.to_string(),
+        (None, Some(m)) => m.to_string(),
+        (None, None) => String::new(),
+    };
+    if !header.is_empty() {
+        println!("    \x1b[31m{}\x1b[0m", header);
+    }
+
+    let lines: Vec<String> = match error.get("frames").and_then(|v| v.as_array()) {
+        Some(frames) => frames.iter().map(format_frame).collect(),
+        // Unparsed stack: show it as sent
+        None => get("stack")
+            .unwrap_or("")
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(String::from)
+            .collect(),
+    };
+    for line in lines.iter().take(max_frames) {
+        println!("      \x1b[90m{}\x1b[0m", line);
+    }
+    if lines.len() > max_frames {
+        println!("      \x1b[90m... {} more\x1b[0m", lines.len() - max_frames);
+    }
+}
+
+fn format_frame(frame: &serde_json::Value) -> String {
+    let function = frame
+        .get("function")
+        .and_then(|v| v.as_str())
+        .unwrap_or("<anonymous>");
+    let Some(file) = frame.get("file").and_then(|v| v.as_str()) else {
+        return format!("at {}", function);
+    };
+    let mut location = file.to_string();
+    for key in ["line", "column"] {
+        match frame.get(key).and_then(|v| v.as_u64()) {
+            Some(n) => location.push_str(&format!(":{}", n)),
+            None => break,
+        }
+    }
+    format!("at {} ({})", function, location)
 }
@@ -84,7 +84,7 @@ server.setRequestHandler("tools/list", async () => ({
         properties: {
           query: { type: "string", description: "Full-text search (message, source, meta). Empty = filter only." },
           project: { type: "string", description: "Filter by project name" },
-          level: { type: "string", enum: ["debug", "info", "warn", "error", "fatal"] },
+          level: { type: "string", enum: ["trace", "debug", "info", "warn", "error", "fatal"] },
           traceId: { type: "string", description: "Filter by trace ID" },
           environment: { type: "string", description: "Filter by environment" },
           since: { type: "string", description: "Time range: 5m, 1h, 2d" },
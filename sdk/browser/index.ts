@@ -51,7 +51,7 @@ export function createBrowserLogger(opts: BrowserLoggerOptions) {
     }
   }
 
-  function log(level: string, message: string, meta?: Record<string, any>) {
+  function log(level: string, message: string, meta?: Record<string, any>, error?: any) {
     queue.push({
       timestamp: new Date().toISOString(),
       project: opts.project,
@@ -62,6 +62,9 @@ export function createBrowserLogger(opts: BrowserLoggerOptions) {
         ...meta,
         url: typeof location !== "undefined" ? location.href : undefined,
       },
+      error: error instanceof Error
+        ? { type: error.name, message: error.message, stack: error.stack }
+        : undefined,
     });
   }
 
@@ -70,16 +73,14 @@ export function createBrowserLogger(opts: BrowserLoggerOptions) {
     window.addEventListener("error", (e) => {
       log("error", e.message, {
         source: `${e.filename}:${e.lineno}:${e.colno}`,
-        stack: e.error?.stack,
         type: "unhandled_error",
-      });
+      }, e.error);
     });
 
     window.addEventListener("unhandledrejection", (e) => {
       log("error", `Unhandled promise rejection: ${e.reason}`, {
-        stack: e.reason?.stack,
         type: "unhandled_rejection",
-      });
+      }, e.reason);
     });
   }
 
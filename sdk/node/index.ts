@@ -24,6 +24,8 @@ export interface LogMeta {
   parentSpanId?: string;
   meta?: Record<string, any>;
   source?: string;
+  /** Exception to attach as `error.type` / `error.message` / `error.stack` */
+  error?: Error;
 }
 
 export interface TraceContext {
@@ -95,6 +97,13 @@ export function createLogger(opts: LogstreamOptions) {
     if (extra?.parentSpanId) entry.parentSpanId = extra.parentSpanId;
     if (extra?.meta) entry.meta = extra.meta;
     if (extra?.source) entry.source = extra.source;
+    if (extra?.error) {
+      entry.error = {
+        type: extra.error.name,
+        message: extra.error.message,
+        stack: extra.error.stack,
+      };
+    }
 
     queue.push(entry);
     if (queue.length >= batchSize) flush();
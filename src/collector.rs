@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::routing::{get, post};
 use axum::Router;
@@ -7,10 +8,15 @@ use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 
 use crate::config::Config;
-use crate::meili::MeiliBatcher;
+use crate::issues;
+use crate::meili::{self, MeiliBatcher};
+use crate::patterns;
+use crate::pipeline::IngestPipeline;
 use crate::routes::{self, AppState};
 use crate::subscribers::SubscriberManager;
 
+const PERSIST_INTERVAL: Duration = Duration::from_secs(2);
+
 /// Start the log collector server.
 pub async fn run(cfg: Config) -> anyhow::Result<()> {
     // Initialize Meilisearch batcher
@@ -22,11 +28,22 @@ pub async fn run(cfg: Config) -> anyhow::Result<()> {
     // Subscriber manager
     let subscribers = SubscriberManager::new();
 
+    // Ingest pipeline (sampling, rate limits, redaction, issues, dedup)
+    let pipeline = IngestPipeline::new(&cfg.pipeline)?;
+    if let Err(e) = pipeline.issues.load(&meili_client).await {
+        tracing::warn!("Could not load issues (run `logstream init`?): {}", e);
+    }
+    if let Err(e) = pipeline.patterns.load(&meili_client).await {
+        tracing::warn!("Could not load patterns (run `logstream init`?): {}", e);
+    }
+    sync_meta_filterable(&pipeline, &meili_client).await;
+
     // Shared state
     let state = Arc::new(AppState {
         meili_tx,
         subscribers,
         meili_client,
+        pipeline,
     });
 
     // CORS
@@ -39,35 +56,136 @@ pub async fn run(cfg: Config) -> anyhow::Result<()> {
     let app = Router::new()
         .route("/health", get(routes::health))
         .route("/ingest", post(routes::ingest))
+        .route("/ingest/stats", get(routes::ingest_stats))
         .route("/search", get(routes::search))
+        .route("/export", get(routes::export))
+        .route("/histogram", get(routes::histogram))
+        .route("/stats", get(routes::stats))
+        .route("/diff", get(routes::diff))
+        .route("/patterns", get(routes::patterns))
         .route("/projects", get(routes::projects))
+        .route("/logs", get(routes::logs))
+        .route("/logs/{id}", get(routes::log))
+        .route("/logs/{id}/context", get(routes::log_context))
+        .route("/traces", get(routes::traces))
         .route("/trace/{trace_id}", get(routes::trace))
         .route("/request/{request_id}", get(routes::request))
         .route("/errors", get(routes::errors))
+        .route("/issues", get(routes::issues))
+        .route("/issues/{fingerprint}", get(routes::issue))
+        .route("/issues/{fingerprint}/status", post(routes::issue_status))
+        .route("/fields", get(routes::fields))
+        .route(
+            "/saved-searches",
+            get(routes::list_saved).post(routes::create_saved),
+        )
+        .route(
+            "/saved-searches/{name}",
+            get(routes::get_saved)
+                .put(routes::put_saved)
+                .delete(routes::delete_saved),
+        )
+        .route("/admin/meta", get(routes::meta_keys))
+        .route("/admin/meta/promote", post(routes::promote_meta))
         .route("/ws", get(routes::ws_handler))
         .nest_service("/dashboard", ServeDir::new("dashboard"))
         .route("/", get(axum::response::Redirect::to("/dashboard")))
         .layer(cors)
-        .with_state(state);
+        .with_state(state.clone());
 
     // Spawn batcher
     tokio::spawn(batcher.run());
 
+    // Spawn issue, pattern and trace persister
+    tokio::spawn(run_persister(state.clone()));
+
+    // Spawn repeat-count flusher for collapsed duplicates
+    if state.pipeline.dedup.enabled() {
+        tokio::spawn(run_dedup_flusher(state.clone()));
+    }
+
     // Start server
     let addr = format!("0.0.0.0:{}", cfg.port);
     tracing::info!("Logstream collector listening on {}", addr);
     tracing::info!("  Meilisearch: {}", cfg.meili_host);
+    if cfg.with_mcp {
+        tracing::info!("  MCP: stdio");
+    }
     tracing::info!("  Endpoints:");
     tracing::info!("    POST /ingest        — HTTP log ingestion");
+    tracing::info!("    GET  /ingest/stats  — Sampled / rate-limited counts");
     tracing::info!("    GET  /ws            — WebSocket (ingest + subscribe)");
     tracing::info!("    GET  /search        — Query logs");
+    tracing::info!("    GET  /export        — Stream all matches (NDJSON / CSV / JSON)");
+    tracing::info!("    GET  /histogram     — Counts per time bucket");
+    tracing::info!("    GET  /stats         — Field aggregations");
+    tracing::info!("    GET  /diff          — Compare two time windows");
+    tracing::info!("    GET  /patterns      — Message templates with counts");
+    tracing::info!("    GET  /logs/:id      — One log entry (+ /context)");
     tracing::info!("    GET  /projects      — Project breakdown");
+    tracing::info!("    GET  /traces        — Trace list with summaries");
     tracing::info!("    GET  /trace/:id     — Trace timeline");
     tracing::info!("    GET  /request/:id   — Request timeline");
     tracing::info!("    GET  /errors        — Error summary");
+    tracing::info!("    GET  /issues        — Errors grouped by fingerprint");
+    tracing::info!("    POST /issues/:fp/status — Resolve / ignore / reopen");
+    tracing::info!("    GET  /fields        — Observed field schema");
+    tracing::info!("    *    /saved-searches — Saved search CRUD");
+    tracing::info!("    GET  /admin/meta    — Flattened meta keys");
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     axum::serve(listener, app).await?;
 
     Ok(())
 }
+
+/// Merge meta keys already filterable in Meilisearch with those listed in
+/// the config file, updating the index if the config adds any.
+async fn sync_meta_filterable(pipeline: &IngestPipeline, client: &Client) {
+    let existing = match meili::meta_filterable(client).await {
+        Ok(keys) => keys,
+        Err(e) => {
+            tracing::warn!("Could not read filterable attributes: {}", e);
+            return;
+        }
+    };
+    let configured = pipeline.meta.filterable();
+    let all = pipeline.meta.promote(existing.clone());
+    if !configured.is_subset(&existing) {
+        if let Err(e) = meili::set_meta_filterable(client, &all).await {
+            tracing::warn!("Could not make meta keys filterable: {}", e);
+        }
+    }
+}
+
+/// Periodically push repeat counts of collapsed duplicates to Meilisearch
+/// (re-indexing the first entry under its own ID) and to live subscribers.
+async fn run_dedup_flusher(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(state.pipeline.dedup.update_interval());
+    loop {
+        interval.tick().await;
+        for entry in state.pipeline.dedup.flush() {
+            state.subscribers.broadcast_repeat(&entry);
+            let _ = state.meili_tx.send(entry);
+        }
+    }
+}
+
+/// Periodically write changed issues, patterns and trace summaries to their
+/// indexes.
+async fn run_persister(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PERSIST_INTERVAL);
+    loop {
+        interval.tick().await;
+        // Failed writes are retried on the next tick rather than lost
+        let dirty = state.pipeline.issues.take_dirty();
+        if !issues::persist(&state.meili_client, &dirty).await {
+            state.pipeline.issues.mark_dirty(&dirty);
+        }
+        let dirty = state.pipeline.patterns.take_dirty();
+        if !patterns::persist(&state.meili_client, &dirty).await {
+            state.pipeline.patterns.mark_dirty(&dirty);
+        }
+        state.pipeline.traces.flush(&state.meili_client).await;
+    }
+}
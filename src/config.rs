@@ -1,7 +1,46 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::dedup::DedupConfig;
+use crate::meta::MetaConfig;
+use crate::patterns::PatternConfig;
+use crate::pipeline::ClockSkewConfig;
+use crate::redact::RedactionConfig;
+use crate::sampling::SamplingConfig;
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub port: u16,
     pub meili_host: String,
     pub meili_key: String,
     pub with_mcp: bool,
+    pub pipeline: PipelineConfig,
+}
+
+/// Ingest pipeline settings, loaded from the optional `--config` JSON file.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PipelineConfig {
+    pub redaction: RedactionConfig,
+    pub sampling: SamplingConfig,
+    pub dedup: DedupConfig,
+    pub clock_skew: ClockSkewConfig,
+    pub meta: MetaConfig,
+    pub patterns: PatternConfig,
+}
+
+impl PipelineConfig {
+    /// Load from a JSON file, or use defaults when no path is given.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        match path {
+            Some(p) => {
+                let raw = std::fs::read_to_string(p)
+                    .map_err(|e| anyhow::anyhow!("reading {}: {}", p.display(), e))?;
+                serde_json::from_str(&raw)
+                    .map_err(|e| anyhow::anyhow!("parsing {}: {}", p.display(), e))
+            }
+            None => Ok(Self::default()),
+        }
+    }
 }
@@ -0,0 +1,203 @@
+//! Entries around one log entry, for `/logs/{id}/context`.
+//!
+//! Neighbors are ordered by `timestampMs`, then `id`, the same order cursor
+//! pages use. Entries sharing the anchor's timestamp are fetched as one
+//! group to find where the anchor sits among them, since Meilisearch can't
+//! range-filter on `id`.
+
+use meilisearch_sdk::indexes::Index;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::filter::{self, Filter, FilterError, Op};
+use crate::meili;
+
+pub const DEFAULT_LINES: usize = 50;
+pub const MAX_LINES: usize = 500;
+/// Entries with the anchor's exact timestamp fetched at most.
+const MAX_GROUP: usize = 1000;
+
+/// Which entries count as neighbors of the anchor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Same project
+    #[default]
+    Project,
+    /// Same `requestId`
+    Request,
+    /// Same `traceId`
+    Trace,
+    /// Same project and `source`
+    Source,
+}
+
+impl Scope {
+    /// Filter clauses selecting the anchor's neighbors.
+    fn filters(self, anchor: &Value) -> Result<Vec<Filter>, ContextError> {
+        let field = |name: &'static str| {
+            anchor
+                .get(name)
+                .and_then(Value::as_str)
+                .map(|v| Filter::eq(name, v))
+                .ok_or(ContextError::MissingField(name))
+        };
+        Ok(match self {
+            Scope::Project => vec![field("project")?],
+            Scope::Request => vec![field("requestId")?],
+            Scope::Trace => vec![field("traceId")?],
+            Scope::Source => vec![field("project")?, field("source")?],
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ContextError {
+    NotFound,
+    /// The anchor has no value for the scope's field
+    MissingField(&'static str),
+    Filter(FilterError),
+    Search(meilisearch_sdk::errors::Error),
+}
+
+impl std::fmt::Display for ContextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContextError::NotFound => write!(f, "log entry not found"),
+            ContextError::MissingField(field) => {
+                write!(f, "log entry has no {} to scope by", field)
+            }
+            ContextError::Filter(e) => write!(f, "{}", e),
+            ContextError::Search(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<FilterError> for ContextError {
+    fn from(e: FilterError) -> Self {
+        ContextError::Filter(e)
+    }
+}
+
+impl From<meilisearch_sdk::errors::Error> for ContextError {
+    fn from(e: meilisearch_sdk::errors::Error) -> Self {
+        ContextError::Search(e)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Context {
+    pub anchor: String,
+    pub scope: Scope,
+    /// Position of the anchor in `entries`
+    pub anchor_index: usize,
+    /// Oldest first; the anchor has `"anchor": true`
+    pub entries: Vec<Value>,
+}
+
+/// Up to `before` older and `after` newer entries around the entry `id`.
+pub async fn around(
+    index: &Index,
+    id: &str,
+    scope: Scope,
+    before: usize,
+    after: usize,
+) -> Result<Context, ContextError> {
+    let mut anchor = meili::get_log(index, id)
+        .await?
+        .ok_or(ContextError::NotFound)?;
+    let scoped = scope.filters(&anchor)?;
+    let time = anchor
+        .get("timestampMs")
+        .and_then(Value::as_i64)
+        .unwrap_or(0);
+
+    // Ties on the timestamp, split around the anchor
+    let group = fetch(
+        index,
+        &scoped,
+        Filter::eq("timestampMs", time),
+        "asc",
+        MAX_GROUP,
+    )
+    .await?;
+    let at = group
+        .iter()
+        .position(|doc| doc.get("id").and_then(Value::as_str) == Some(id))
+        .unwrap_or(group.len());
+    let (group_before, group_after) = group.split_at(at);
+    let group_after = group_after.get(1..).unwrap_or_default();
+
+    let mut older: Vec<Value> = group_before.iter().rev().take(before).cloned().collect();
+    if older.len() < before {
+        let rest = before - older.len();
+        older.extend(
+            fetch(
+                index,
+                &scoped,
+                Filter::cmp("timestampMs", Op::Lt, time),
+                "desc",
+                rest,
+            )
+            .await?,
+        );
+    }
+    older.reverse();
+
+    let mut newer: Vec<Value> = group_after.iter().take(after).cloned().collect();
+    if newer.len() < after {
+        let rest = after - newer.len();
+        newer.extend(
+            fetch(
+                index,
+                &scoped,
+                Filter::cmp("timestampMs", Op::Gt, time),
+                "asc",
+                rest,
+            )
+            .await?,
+        );
+    }
+
+    if let Some(obj) = anchor.as_object_mut() {
+        obj.insert("anchor".into(), Value::Bool(true));
+    }
+    let anchor_index = older.len();
+    let entries = older
+        .into_iter()
+        .chain(std::iter::once(anchor))
+        .chain(newer)
+        .collect();
+    Ok(Context {
+        anchor: id.to_string(),
+        scope,
+        anchor_index,
+        entries,
+    })
+}
+
+/// Entries in scope matching `range`, sorted by time and id in `order`.
+async fn fetch(
+    index: &Index,
+    scoped: &[Filter],
+    range: Filter,
+    order: &str,
+    limit: usize,
+) -> Result<Vec<Value>, ContextError> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+    let filter = filter::render_all(scoped.iter().cloned().chain([range]))?;
+    let sort_by = [format!("timestampMs:{}", order), format!("id:{}", order)];
+    let sort: Vec<&str> = sort_by.iter().map(String::as_str).collect();
+
+    let mut search = index.search();
+    search.with_sort(&sort);
+    search.with_limit(limit);
+    if let Some(ref f) = filter {
+        search.with_filter(f);
+    }
+    let results = search.execute::<Value>().await?;
+    Ok(results.hits.into_iter().map(|h| h.result).collect())
+}
@@ -0,0 +1,576 @@
+//! Cursor pagination for `/search`.
+//!
+//! Hits are ordered newest first by a numeric time field (`timestampMs`, or
+//! `receivedAtMs` with `sort=received`) and then by `id`. A cursor marks one
+//! hit by its (time value, id) pair, and each page is a range query for the
+//! hits strictly past that pair. Pages don't shift as logs arrive, even ones
+//! sharing the boundary's timestamp, and never need deep offsets, which
+//! Meilisearch caps at `maxTotalHits`.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::{SearchResults, Selectors};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::filter::{self, Filter, FilterError, Op};
+
+/// Time fields results can be paged on.
+const FIELDS: [&str; 2] = ["timestampMs", "receivedAtMs"];
+
+/// Paging field for a `sort` parameter.
+pub fn sort_field(sort: Option<&str>) -> &'static str {
+    match sort {
+        Some("received") => "receivedAtMs",
+        _ => "timestampMs",
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Order {
+    #[serde(rename = "d")]
+    Desc,
+    #[serde(rename = "a")]
+    Asc,
+}
+
+impl Order {
+    fn as_str(self) -> &'static str {
+        match self {
+            Order::Desc => "desc",
+            Order::Asc => "asc",
+        }
+    }
+
+    /// Values strictly past a position.
+    fn beyond(self) -> Op {
+        match self {
+            Order::Desc => Op::Lt,
+            Order::Asc => Op::Gt,
+        }
+    }
+}
+
+/// Decoded form of `nextCursor` / `prevCursor`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(rename = "f")]
+    field: String,
+    #[serde(rename = "v")]
+    value: i64,
+    /// `id` of the marked hit, breaking ties between equal values
+    #[serde(rename = "k")]
+    id: String,
+    /// `Desc` pages toward older hits, `Asc` toward newer ones
+    #[serde(rename = "d")]
+    direction: Order,
+}
+
+impl Cursor {
+    pub fn decode(s: &str) -> Result<Self, String> {
+        URL_SAFE_NO_PAD
+            .decode(s)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Cursor>(&bytes).ok())
+            .filter(|c| FIELDS.contains(&c.field.as_str()))
+            .ok_or_else(|| "invalid cursor".to_string())
+    }
+
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn toward(&self, direction: Order) -> Self {
+        Self {
+            direction,
+            ..self.clone()
+        }
+    }
+
+    /// Hits strictly past the marked one in its direction.
+    fn beyond(&self) -> Filter {
+        let op = self.direction.beyond();
+        Filter::Or(vec![
+            Filter::cmp(&self.field, op, self.value),
+            Filter::And(vec![
+                Filter::eq(&self.field, self.value),
+                Filter::cmp("id", op, self.id.as_str()),
+            ]),
+        ])
+    }
+}
+
+#[derive(Debug)]
+pub enum PageError {
+    Filter(FilterError),
+    Search(meilisearch_sdk::errors::Error),
+}
+
+impl std::fmt::Display for PageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageError::Filter(e) => write!(f, "{}", e),
+            PageError::Search(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<FilterError> for PageError {
+    fn from(e: FilterError) -> Self {
+        PageError::Filter(e)
+    }
+}
+
+impl From<meilisearch_sdk::errors::Error> for PageError {
+    fn from(e: meilisearch_sdk::errors::Error) -> Self {
+        PageError::Search(e)
+    }
+}
+
+/// One page of hits, newest first.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    /// Matches for the whole query, ignoring the cursor
+    pub total_hits: Option<usize>,
+    pub facets: Option<HashMap<String, HashMap<String, usize>>>,
+    pub hits: Vec<Value>,
+    /// Older hits; `null` once the end is reached
+    pub next_cursor: Option<String>,
+    /// Newer hits, including ones that arrive later
+    pub prev_cursor: Option<String>,
+}
+
+/// Cursor marking `doc`, paging in `direction`.
+fn cursor_at(doc: &Value, field: &str, direction: Order) -> String {
+    Cursor {
+        field: field.to_string(),
+        value: doc.get(field).and_then(Value::as_i64).unwrap_or(0),
+        id: doc
+            .get("id")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        direction,
+    }
+    .encode()
+}
+
+/// What to search: full-text query and filters, shared by every page.
+pub struct Search<'a> {
+    pub index: &'a Index,
+    pub text: &'a str,
+    pub filters: Vec<Filter>,
+    pub facets: &'a [&'a str],
+    /// Report `totalHits` and `facets` for the whole query, which costs an
+    /// extra query on cursor pages
+    pub totals: bool,
+}
+
+impl Search<'_> {
+    /// Fetch `limit` hits starting at `cursor`, or the newest page.
+    pub async fn page(
+        &self,
+        field: &str,
+        cursor: Option<&Cursor>,
+        limit: usize,
+    ) -> Result<Page, PageError> {
+        let field = cursor.map_or(field, |c| c.field.as_str());
+        paginate(
+            field,
+            cursor,
+            limit,
+            self.totals,
+            |extra, order, limit, with_facets| self.run(field, extra, order, limit, with_facets),
+        )
+        .await
+    }
+
+    /// Visit every hit newest first, `page_size` at a time, stopping after
+    /// `max` hits. Returns whether every hit was visited.
+    pub async fn scan(
+        &self,
+        field: &str,
+        page_size: usize,
+        max: usize,
+        visit: impl FnMut(&Value),
+    ) -> Result<bool, PageError> {
+        scan(
+            |cursor, limit| async move { self.page(field, cursor.as_ref(), limit).await },
+            page_size,
+            max,
+            visit,
+        )
+        .await
+    }
+
+    async fn run(
+        &self,
+        field: &str,
+        extra: Vec<Filter>,
+        order: Order,
+        limit: usize,
+        with_facets: bool,
+    ) -> Result<SearchResults<Value>, PageError> {
+        let filter = filter::render_all(self.filters.iter().cloned().chain(extra))?;
+        let sort_by = [
+            format!("{}:{}", field, order.as_str()),
+            format!("id:{}", order.as_str()),
+        ];
+        let sort: Vec<&str> = sort_by.iter().map(String::as_str).collect();
+
+        let mut search = self.index.search();
+        search.with_query(self.text);
+        search.with_sort(&sort);
+        search.with_limit(limit);
+        if with_facets {
+            search.with_facets(Selectors::Some(self.facets));
+        }
+        if let Some(ref f) = filter {
+            search.with_filter(f);
+        }
+        Ok(search.execute::<Value>().await?)
+    }
+}
+
+/// Page through hits with `run`, which searches with extra filters, an
+/// order, a limit and whether to include totals and facets.
+async fn paginate<F, Fut>(
+    field: &str,
+    cursor: Option<&Cursor>,
+    limit: usize,
+    totals: bool,
+    run: F,
+) -> Result<Page, PageError>
+where
+    F: Fn(Vec<Filter>, Order, usize, bool) -> Fut,
+    Fut: Future<Output = Result<SearchResults<Value>, PageError>>,
+{
+    let Some(cursor) = cursor else {
+        let results = run(Vec::new(), Order::Desc, limit, totals).await?;
+        let hits = docs(results.hits);
+        let full = hits.len() == limit;
+        return Ok(assemble(
+            field,
+            hits,
+            results.estimated_total_hits,
+            results.facet_distribution,
+            full,
+            None,
+        ));
+    };
+
+    let summary = if totals {
+        Some(run(Vec::new(), Order::Desc, 0, true).await?)
+    } else {
+        None
+    };
+    let dir = cursor.direction;
+    let results = run(vec![cursor.beyond()], dir, limit, false).await?;
+    let mut hits = docs(results.hits);
+    // Toward newer hits there is always an older page: the cursor's own
+    let full = dir == Order::Asc || hits.len() == limit;
+    if dir == Order::Asc {
+        hits.reverse();
+    }
+    Ok(assemble(
+        field,
+        hits,
+        summary.as_ref().and_then(|s| s.estimated_total_hits),
+        summary.and_then(|s| s.facet_distribution),
+        full,
+        Some(cursor),
+    ))
+}
+
+/// [`Search::scan`] over pages fetched by `page`.
+async fn scan<F, Fut>(
+    page: F,
+    page_size: usize,
+    max: usize,
+    mut visit: impl FnMut(&Value),
+) -> Result<bool, PageError>
+where
+    F: Fn(Option<Cursor>, usize) -> Fut,
+    Fut: Future<Output = Result<Page, PageError>>,
+{
+    let mut seen = 0;
+    let mut next: Option<Cursor> = None;
+    while seen < max {
+        let current = page(next.take(), page_size.min(max - seen)).await?;
+        seen += current.hits.len();
+        current.hits.iter().for_each(&mut visit);
+        next = current
+            .next_cursor
+            .as_deref()
+            .and_then(|c| Cursor::decode(c).ok());
+        if next.is_none() {
+            return Ok(true);
+        }
+    }
+    // A full last page still has a next cursor: see whether anything is left
+    Ok(page(next, 1).await?.hits.is_empty())
+}
+
+fn docs(hits: Vec<meilisearch_sdk::search::SearchResult<Value>>) -> Vec<Value> {
+    hits.into_iter().map(|h| h.result).collect()
+}
+
+fn assemble(
+    field: &str,
+    hits: Vec<Value>,
+    total_hits: Option<usize>,
+    facets: Option<HashMap<String, HashMap<String, usize>>>,
+    full: bool,
+    cursor: Option<&Cursor>,
+) -> Page {
+    let (next_cursor, prev_cursor) = match (hits.first(), hits.last()) {
+        (Some(first), Some(last)) => (
+            full.then(|| cursor_at(last, field, Order::Desc)),
+            Some(cursor_at(first, field, Order::Asc)),
+        ),
+        // Nothing here yet: keep the position so the caller can retry
+        _ => (
+            cursor
+                .filter(|c| c.direction == Order::Asc)
+                .map(|c| c.toward(Order::Desc).encode()),
+            cursor.map(|c| c.toward(Order::Asc).encode()),
+        ),
+    };
+    Page {
+        total_hits,
+        facets,
+        hits,
+        next_cursor,
+        prev_cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::filter::Value as FilterValue;
+
+    /// Hits as (timestampMs, id), with long runs of equal timestamps.
+    fn docs() -> Vec<(i64, String)> {
+        [9, 7, 7, 7, 7, 7, 7, 5, 4, 4, 3, 3, 3, 3, 3, 3, 3, 1, 1]
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (v, format!("id{:02}", i)))
+            .collect()
+    }
+
+    /// Ids of `docs()` newest first, ties by id descending.
+    fn newest_first() -> Vec<String> {
+        let mut docs = docs();
+        docs.sort();
+        docs.into_iter().rev().map(|(_, id)| id).collect()
+    }
+
+    /// Whether the doc (timestampMs, id) matches `filter`.
+    fn matches(filter: &Filter, doc: &(i64, String)) -> bool {
+        let (v, id) = doc;
+        match filter {
+            Filter::Cmp { field, op, value } => {
+                let ord = match (field.as_str(), value) {
+                    ("timestampMs", FilterValue::Int(x)) => v.cmp(x),
+                    ("id", FilterValue::Str(x)) => id.as_str().cmp(x.as_str()),
+                    _ => panic!("unexpected filter {:?}", filter),
+                };
+                match op {
+                    Op::Eq => ord.is_eq(),
+                    Op::Ne => ord.is_ne(),
+                    Op::Gt => ord.is_gt(),
+                    Op::Gte => ord.is_ge(),
+                    Op::Lt => ord.is_lt(),
+                    Op::Lte => ord.is_le(),
+                }
+            }
+            Filter::And(items) => items.iter().all(|f| matches(f, doc)),
+            Filter::Or(items) => items.iter().any(|f| matches(f, doc)),
+            f => panic!("unexpected filter {:?}", f),
+        }
+    }
+
+    /// Search `docs` the way Meilisearch would for one `run` call.
+    fn search(
+        docs: &[(i64, String)],
+        extra: Vec<Filter>,
+        order: Order,
+        limit: usize,
+    ) -> Result<SearchResults<Value>, PageError> {
+        let mut hits: Vec<&(i64, String)> = docs
+            .iter()
+            .filter(|doc| extra.iter().all(|f| matches(f, doc)))
+            .collect();
+        hits.sort();
+        if order == Order::Desc {
+            hits.reverse();
+        }
+        let hits: Vec<Value> = hits
+            .into_iter()
+            .take(limit)
+            .map(|(v, id)| json!({ "timestampMs": v, "id": id }))
+            .collect();
+        Ok(serde_json::from_value(json!({
+            "hits": hits,
+            "processingTimeMs": 0,
+            "query": "",
+        }))
+        .unwrap())
+    }
+
+    fn page(docs: &[(i64, String)], cursor: Option<&str>, limit: usize) -> Page {
+        let cursor = cursor.map(|c| Cursor::decode(c).unwrap());
+        let run = |extra, order, limit, _| std::future::ready(search(docs, extra, order, limit));
+        futures::executor::block_on(paginate("timestampMs", cursor.as_ref(), limit, false, run))
+            .unwrap()
+    }
+
+    fn ids(page: &Page) -> Vec<String> {
+        page.hits
+            .iter()
+            .map(|h| h["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn pages_forward_without_gaps_or_repeats() {
+        let all = newest_first();
+        for limit in 1..=6 {
+            let mut seen = Vec::new();
+            let mut current = page(&docs(), None, limit);
+            loop {
+                seen.extend(ids(&current));
+                match current.next_cursor {
+                    Some(ref c) => current = page(&docs(), Some(c), limit),
+                    None => break,
+                }
+                if current.hits.is_empty() {
+                    break;
+                }
+            }
+            assert_eq!(seen, all, "limit {}", limit);
+        }
+    }
+
+    /// Walk forward and back in every pattern of `moves` steps, checking
+    /// each page against the slice of hits it should hold.
+    #[test]
+    fn pages_back_and_forth_without_gaps_or_repeats() {
+        let all = newest_first();
+        let moves = 8;
+        for limit in 1..=5 {
+            for pattern in 0..(1u32 << moves) {
+                let mut current = page(&docs(), None, limit);
+                // Position of the current page in `all`
+                let mut start = 0;
+                assert_eq!(ids(&current), all[..limit.min(all.len())]);
+                for step in 0..moves {
+                    let end = start + current.hits.len();
+                    let forward = pattern & (1 << step) != 0;
+                    let (cursor, expected) = if forward {
+                        let Some(c) = current.next_cursor.clone() else {
+                            assert_eq!(end, all.len(), "limit {} pattern {:b}", limit, pattern);
+                            continue;
+                        };
+                        (c, end..(end + limit).min(all.len()))
+                    } else {
+                        if start == 0 {
+                            continue;
+                        }
+                        let c = current.prev_cursor.clone().unwrap();
+                        (c, start.saturating_sub(limit)..start)
+                    };
+                    let next = page(&docs(), Some(&cursor), limit);
+                    assert_eq!(
+                        ids(&next),
+                        all[expected.clone()],
+                        "limit {} pattern {:b} step {}",
+                        limit,
+                        pattern,
+                        step
+                    );
+                    if next.hits.is_empty() {
+                        continue;
+                    }
+                    start = expected.start;
+                    current = next;
+                }
+            }
+        }
+    }
+
+    /// Entries sharing the boundary's timestamp arrive between pages; every
+    /// original hit is still seen exactly once, in order.
+    #[test]
+    fn same_millisecond_arrivals_do_not_shift_pages() {
+        let all = newest_first();
+        for limit in 1..=6 {
+            let mut docs = docs();
+            let mut seen = Vec::new();
+            let mut current = page(&docs, None, limit);
+            let mut arrivals = 0;
+            loop {
+                seen.extend(ids(&current));
+                let Some(last) = current.hits.last() else {
+                    break;
+                };
+                let ts = last["timestampMs"].as_i64().unwrap();
+                // One id sorting before the boundary, one after it
+                for prefix in ["a", "z"] {
+                    docs.push((ts, format!("{}{:02}", prefix, arrivals)));
+                    arrivals += 1;
+                }
+                match current.next_cursor {
+                    Some(ref c) => current = page(&docs, Some(c), limit),
+                    None => break,
+                }
+            }
+            let originals: Vec<String> =
+                seen.into_iter().filter(|id| id.starts_with("id")).collect();
+            assert_eq!(originals, all, "limit {}", limit);
+        }
+    }
+
+    /// Ids visited by scanning `docs()`, and whether the scan was complete.
+    fn scanned(page_size: usize, max: usize) -> (Vec<String>, bool) {
+        let docs = docs();
+        let mut visited = Vec::new();
+        let fetch = |cursor: Option<Cursor>, limit| {
+            let run =
+                |extra, order, limit, _| std::future::ready(search(&docs, extra, order, limit));
+            async move { paginate("timestampMs", cursor.as_ref(), limit, false, run).await }
+        };
+        let complete = futures::executor::block_on(scan(fetch, page_size, max, |doc| {
+            visited.push(doc["id"].as_str().unwrap().to_string())
+        }))
+        .unwrap();
+        (visited, complete)
+    }
+
+    #[test]
+    fn scan_reports_whether_it_saw_every_hit() {
+        let all = newest_first();
+        for (page_size, max) in [(4, 100), (19, 19), (4, 19), (100, 19), (5, 20)] {
+            assert_eq!(
+                scanned(page_size, max),
+                (all.clone(), true),
+                "{page_size} {max}"
+            );
+        }
+        for (page_size, max) in [(4, 18), (5, 10), (100, 1)] {
+            assert_eq!(
+                scanned(page_size, max),
+                (all[..max].to_vec(), false),
+                "{page_size} {max}"
+            );
+        }
+    }
+}
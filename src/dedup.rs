@@ -0,0 +1,280 @@
+//! Deduplication and burst collapsing of repeated messages.
+//!
+//! The first entry for a (project, level, message) tuple passes through
+//! untouched. Only exact repeats collapse (ignoring whitespace): messages
+//! that differ in a number or a name may be different events, and folding
+//! them into one would hide all but the first. Repeats within the window are
+//! suppressed and counted;
+//! a periodic flush re-indexes the first entry (same ID) with `repeatCount`,
+//! `firstSeen` and `lastSeen`, and notifies live subscribers.
+
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::Deserialize;
+
+use crate::models::LogEntry;
+
+/// `dedup` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    /// How long a group absorbs repeats after its first entry
+    pub window_secs: u64,
+    /// How often repeat counts are pushed to Meilisearch and subscribers
+    pub update_interval_secs: u64,
+    /// Maximum number of groups tracked at once; new messages pass through beyond this
+    pub max_groups: usize,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 60,
+            update_interval_secs: 5,
+            max_groups: 10_000,
+        }
+    }
+}
+
+struct Group {
+    entry: LogEntry,
+    count: u64,
+    reported: u64,
+    first_ms: i64,
+    last_ms: i64,
+}
+
+impl Group {
+    /// The first entry annotated with the current repeat counts.
+    fn snapshot(&mut self) -> LogEntry {
+        self.reported = self.count;
+        let mut entry = self.entry.clone();
+        entry.repeat_count = Some(self.count);
+        entry.first_seen = Some(format_ms(self.first_ms));
+        entry.last_seen = Some(format_ms(self.last_ms));
+        entry
+    }
+}
+
+pub struct Deduplicator {
+    cfg: DedupConfig,
+    groups: DashMap<String, Group>,
+    /// Final updates of groups replaced before the flusher saw them
+    closed: Mutex<Vec<LogEntry>>,
+}
+
+impl Deduplicator {
+    pub fn new(cfg: &DedupConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            groups: DashMap::new(),
+            closed: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.cfg.enabled
+    }
+
+    pub fn update_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.cfg.update_interval_secs.max(1))
+    }
+
+    /// Returns `true` if the entry is a repeat and should be suppressed.
+    pub fn check(&self, entry: &LogEntry) -> bool {
+        self.check_at(entry, Utc::now().timestamp_millis())
+    }
+
+    fn check_at(&self, entry: &LogEntry, now: i64) -> bool {
+        if !self.cfg.enabled {
+            return false;
+        }
+        let message: Vec<&str> = entry.message.split_whitespace().collect();
+        let key = format!(
+            "{}\u{1f}{}\u{1f}{}",
+            entry.project,
+            entry.level,
+            message.join(" ")
+        );
+        let window_ms = (self.cfg.window_secs * 1000) as i64;
+
+        let group = Group {
+            entry: entry.clone(),
+            count: 1,
+            reported: 1,
+            first_ms: now,
+            last_ms: now,
+        };
+        // Read before `entry()`: `len()` locks every shard, including the one
+        // the entry guard holds.
+        let full = self.groups.len() >= self.cfg.max_groups;
+
+        match self.groups.entry(key) {
+            Entry::Occupied(mut occupied) => {
+                let current = occupied.get_mut();
+                if now - current.first_ms < window_ms {
+                    current.count += 1;
+                    current.last_ms = now;
+                    return true;
+                }
+                // The previous group's window has closed
+                let mut old = occupied.insert(group);
+                if old.count > old.reported {
+                    self.closed.lock().unwrap().push(old.snapshot());
+                }
+                false
+            }
+            Entry::Vacant(vacant) => {
+                if !full {
+                    vacant.insert(group);
+                }
+                false
+            }
+        }
+    }
+
+    /// Collect groups with unreported repeats (as updated entries) and drop
+    /// groups whose window has closed.
+    pub fn flush(&self) -> Vec<LogEntry> {
+        self.flush_at(Utc::now().timestamp_millis())
+    }
+
+    fn flush_at(&self, now: i64) -> Vec<LogEntry> {
+        let window_ms = (self.cfg.window_secs * 1000) as i64;
+        let mut updates = std::mem::take(&mut *self.closed.lock().unwrap());
+
+        self.groups.retain(|_, group| {
+            if group.count > group.reported {
+                updates.push(group.snapshot());
+            }
+            now - group.first_ms < window_ms
+        });
+
+        updates
+    }
+}
+
+fn format_ms(ms: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(ms)
+        .unwrap_or_else(Utc::now)
+        .to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: i64 = 1_700_000_000_000;
+
+    fn dedup(max_groups: usize) -> Deduplicator {
+        Deduplicator::new(&DedupConfig {
+            enabled: true,
+            window_secs: 60,
+            update_interval_secs: 5,
+            max_groups,
+        })
+    }
+
+    fn entry(project: &str, level: &str, message: &str) -> LogEntry {
+        serde_json::from_value(serde_json::json!({
+            "project": project,
+            "level": level,
+            "message": message,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn collapses_exact_repeats_only() {
+        let dedup = dedup(100);
+        let first = entry("api", "info", "user 'alice' deleted");
+        assert!(!dedup.check_at(&first, T0));
+        assert!(dedup.check_at(&first, T0 + 1));
+        assert!(dedup.check_at(&entry("api", "info", "user  'alice'   deleted "), T0 + 2));
+        for (project, level, message) in [
+            ("api", "info", "user 'bob' deleted"),
+            ("api", "info", "retry 3/5"),
+            ("api", "info", "retry 4/5"),
+            ("api", "warn", "user 'alice' deleted"),
+            ("web", "info", "user 'alice' deleted"),
+        ] {
+            assert!(
+                !dedup.check_at(&entry(project, level, message), T0 + 3),
+                "{} {} {}",
+                project,
+                level,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn flush_reports_repeat_counts_once() {
+        let dedup = dedup(100);
+        let first = entry("api", "info", "cache miss");
+        dedup.check_at(&first, T0);
+        assert!(dedup.flush_at(T0 + 1).is_empty());
+
+        dedup.check_at(&first, T0 + 1000);
+        dedup.check_at(&first, T0 + 2000);
+        let updates = dedup.flush_at(T0 + 3000);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].id, first.id);
+        assert_eq!(updates[0].repeat_count, Some(3));
+        assert_eq!(
+            updates[0].first_seen.as_deref(),
+            Some(format_ms(T0).as_str())
+        );
+        assert_eq!(
+            updates[0].last_seen.as_deref(),
+            Some(format_ms(T0 + 2000).as_str())
+        );
+        assert!(dedup.flush_at(T0 + 4000).is_empty());
+    }
+
+    #[test]
+    fn closed_windows_start_a_new_group() {
+        let dedup = dedup(100);
+        let first = entry("api", "info", "cache miss");
+        dedup.check_at(&first, T0);
+        dedup.check_at(&first, T0 + 1000);
+
+        // The next entry after the window is stored, and the old group's
+        // last count still goes out
+        let next = entry("api", "info", "cache miss");
+        assert!(!dedup.check_at(&next, T0 + 60_000));
+        let updates = dedup.flush_at(T0 + 60_001);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].id, first.id);
+        assert_eq!(updates[0].repeat_count, Some(2));
+
+        // Groups are dropped once their window closes
+        assert!(dedup.flush_at(T0 + 120_000).is_empty());
+        assert!(dedup.groups.is_empty());
+    }
+
+    #[test]
+    fn passes_new_messages_through_when_full() {
+        let dedup = dedup(2);
+        dedup.check_at(&entry("api", "info", "a"), T0);
+        dedup.check_at(&entry("api", "info", "b"), T0);
+        let c = entry("api", "info", "c");
+        assert!(!dedup.check_at(&c, T0));
+        assert!(!dedup.check_at(&c, T0 + 1));
+        assert!(dedup.check_at(&entry("api", "info", "a"), T0 + 1));
+    }
+
+    #[test]
+    fn disabled_never_suppresses() {
+        let dedup = Deduplicator::new(&DedupConfig::default());
+        let first = entry("api", "info", "cache miss");
+        assert!(!dedup.check_at(&first, T0));
+        assert!(!dedup.check_at(&first, T0));
+        assert!(dedup.flush_at(T0).is_empty());
+    }
+}
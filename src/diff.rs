@@ -0,0 +1,339 @@
+//! Compare two time windows for `/diff`.
+//!
+//! Counts per project, level, fingerprint and message pattern (the Drain
+//! `patternId` assigned at ingest, see `patterns`) all come from facets and
+//! are exact. Windows include their end but not their start, so adjacent
+//! windows never share an entry. Baseline counts are scaled to the target's
+//! length before comparing, so windows of different lengths compare by rate.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cursor::PageError;
+use crate::filter::{self, Filter, Op};
+use crate::meili::MAX_VALUES_PER_FACET;
+use crate::patterns::PatternMiner;
+use crate::timerange::TimeRange;
+
+/// Facets compared between the windows.
+const DIMENSIONS: [&str; 4] = ["project", "level", "fingerprint", "patternId"];
+pub const DEFAULT_LIMIT: usize = 20;
+/// Factor a pattern's rate must grow or shrink by to count as changed.
+const MIN_RATIO: f64 = 2.0;
+/// Smallest scaled difference worth reporting as a change.
+const MIN_DELTA: f64 = 5.0;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Window {
+    pub from: i64,
+    pub to: i64,
+    /// Entries matching the filters in the window
+    pub total: usize,
+    /// True when a facet returned as many values as Meilisearch keeps, so
+    /// rarer ones may be missing
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub value: String,
+    /// Issue title, for fingerprints
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub baseline: usize,
+    pub target: usize,
+    /// Target minus the scaled baseline
+    pub delta: f64,
+    /// Target over the scaled baseline; absent when the baseline is zero
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ratio: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatternChange {
+    /// `patternId`
+    pub id: String,
+    /// Template, e.g. `db pool exhausted after <*>`; empty when this server
+    /// doesn't know the `patternId`
+    pub pattern: String,
+    /// Most recent message with this pattern, empty when unknown
+    pub example: String,
+    pub baseline: usize,
+    pub target: usize,
+    pub delta: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ratio: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Patterns {
+    /// Only in the target, most frequent first
+    pub new: Vec<PatternChange>,
+    /// Only in the baseline, most frequent first
+    pub gone: Vec<PatternChange>,
+    /// In both, with a rate at least [`MIN_RATIO`] times higher or lower;
+    /// largest change first
+    pub changed: Vec<PatternChange>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diff {
+    pub baseline: Window,
+    pub target: Window,
+    /// Target length over baseline length, applied to baseline counts
+    pub scale: f64,
+    /// Largest change first
+    pub projects: Vec<Change>,
+    pub levels: Vec<Change>,
+    pub fingerprints: Vec<Change>,
+    pub patterns: Patterns,
+}
+
+/// What to compare.
+pub struct Request<'a> {
+    pub index: &'a Index,
+    pub text: &'a str,
+    /// Filters applied to both windows
+    pub filters: Vec<Filter>,
+    pub baseline: TimeRange,
+    pub target: TimeRange,
+    /// Entries returned per list
+    pub limit: usize,
+    /// Templates for `patternId` values
+    pub patterns: &'a PatternMiner,
+}
+
+/// Counts for one window.
+struct Counts {
+    window: Window,
+    facets: HashMap<String, HashMap<String, usize>>,
+}
+
+impl Request<'_> {
+    pub async fn run(self) -> Result<Diff, PageError> {
+        let (baseline, target) =
+            futures::try_join!(self.count(&self.baseline), self.count(&self.target))?;
+        let scale = span(&self.target) as f64 / span(&self.baseline).max(1) as f64;
+
+        let dimension = |name: &str| {
+            let empty = HashMap::new();
+            let b = baseline.facets.get(name).unwrap_or(&empty);
+            let t = target.facets.get(name).unwrap_or(&empty);
+            let mut changes: Vec<Change> = keys(b, t)
+                .into_iter()
+                .map(|value| {
+                    let (b, t) = (get(b, value), get(t, value));
+                    let (delta, ratio) = compare(b, t, scale);
+                    Change {
+                        value: value.clone(),
+                        title: None,
+                        baseline: b,
+                        target: t,
+                        delta,
+                        ratio,
+                    }
+                })
+                .filter(|c| c.delta != 0.0)
+                .collect();
+            changes.sort_by(|a, b| b.delta.abs().total_cmp(&a.delta.abs()));
+            changes.truncate(self.limit);
+            changes
+        };
+        let projects = dimension("project");
+        let levels = dimension("level");
+        let fingerprints = dimension("fingerprint");
+        let empty = HashMap::new();
+        let patterns = compare_patterns(
+            self.patterns,
+            baseline.facets.get("patternId").unwrap_or(&empty),
+            target.facets.get("patternId").unwrap_or(&empty),
+            scale,
+            self.limit,
+        );
+
+        Ok(Diff {
+            baseline: baseline.window,
+            target: target.window,
+            scale,
+            projects,
+            levels,
+            fingerprints,
+            patterns,
+        })
+    }
+
+    /// Facet counts of one window.
+    async fn count(&self, range: &TimeRange) -> Result<Counts, PageError> {
+        let mut window = Vec::new();
+        if let Some(from) = range.from {
+            window.push(Filter::cmp("timestampMs", Op::Gt, from));
+        }
+        if let Some(to) = range.to {
+            window.push(Filter::cmp("timestampMs", Op::Lte, to));
+        }
+        let rendered = filter::render_all(self.filters.iter().cloned().chain(window))?;
+        let mut search = self.index.search();
+        search.with_query(self.text);
+        search.with_limit(0);
+        search.with_facets(Selectors::Some(&DIMENSIONS));
+        if let Some(ref f) = rendered {
+            search.with_filter(f);
+        }
+        let facets = search
+            .execute::<Value>()
+            .await?
+            .facet_distribution
+            .unwrap_or_default();
+        let total = facets
+            .get("level")
+            .map_or(0, |levels| levels.values().sum());
+        let truncated = facets
+            .values()
+            .any(|counts| counts.len() >= MAX_VALUES_PER_FACET);
+
+        Ok(Counts {
+            window: Window {
+                from: range.from.unwrap_or_default(),
+                to: range.to.unwrap_or_default(),
+                total,
+                truncated,
+            },
+            facets,
+        })
+    }
+}
+
+/// Sort the patterns of both windows into new, gone and changed, keeping
+/// `limit` of each. Ids without a known template (e.g. assigned by another
+/// instance) are still reported, with an empty template.
+fn compare_patterns(
+    patterns: &PatternMiner,
+    b_counts: &HashMap<String, usize>,
+    t_counts: &HashMap<String, usize>,
+    scale: f64,
+    limit: usize,
+) -> Patterns {
+    let mut changes = Patterns::default();
+    for id in keys(b_counts, t_counts) {
+        let pattern = patterns.get(id);
+        let (b, t) = (get(b_counts, id), get(t_counts, id));
+        let (delta, ratio) = compare(b, t, scale);
+        let change = PatternChange {
+            id: id.clone(),
+            pattern: pattern
+                .as_ref()
+                .map(|p| p.template.clone())
+                .unwrap_or_default(),
+            example: pattern.map(|p| p.sample).unwrap_or_default(),
+            baseline: b,
+            target: t,
+            delta,
+            ratio,
+        };
+        if b == 0 {
+            changes.new.push(change);
+        } else if t == 0 {
+            changes.gone.push(change);
+        } else if delta.abs() >= MIN_DELTA
+            && ratio.is_some_and(|r| r >= MIN_RATIO || r <= 1.0 / MIN_RATIO)
+        {
+            changes.changed.push(change);
+        }
+    }
+    changes.new.sort_by_key(|p| Reverse(p.target));
+    changes.gone.sort_by_key(|p| Reverse(p.baseline));
+    changes
+        .changed
+        .sort_by(|a, b| b.delta.abs().total_cmp(&a.delta.abs()));
+    for list in [&mut changes.new, &mut changes.gone, &mut changes.changed] {
+        list.truncate(limit);
+    }
+    changes
+}
+
+fn span(range: &TimeRange) -> i64 {
+    range.to.unwrap_or_default() - range.from.unwrap_or_default()
+}
+
+/// Keys of either map, sorted so equal changes keep a stable order.
+fn keys<'a, V>(a: &'a HashMap<String, V>, b: &'a HashMap<String, V>) -> Vec<&'a String> {
+    let mut keys: Vec<&String> = a
+        .keys()
+        .chain(b.keys())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    keys.sort();
+    keys
+}
+
+fn get(counts: &HashMap<String, usize>, key: &str) -> usize {
+    counts.get(key).copied().unwrap_or(0)
+}
+
+/// Difference and ratio between the target and the scaled baseline.
+fn compare(baseline: usize, target: usize, scale: f64) -> (f64, Option<f64>) {
+    let expected = baseline as f64 * scale;
+    let delta = ((target as f64 - expected) * 10.0).round() / 10.0;
+    let ratio = (baseline > 0).then(|| ((target as f64 / expected) * 100.0).round() / 100.0);
+    (delta, ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LogEntry;
+    use crate::patterns::PatternConfig;
+
+    fn counts(pairs: &[(&str, usize)]) -> HashMap<String, usize> {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn reports_unknown_pattern_ids() {
+        let miner = PatternMiner::new(&PatternConfig::default()).unwrap();
+        let mut entry: LogEntry = serde_json::from_value(
+            serde_json::json!({ "project": "api", "message": "db pool exhausted after 5000ms" }),
+        )
+        .unwrap();
+        miner.assign(&mut entry);
+        let known = entry.pattern_id.unwrap();
+
+        let baseline = counts(&[("gone0000000a", 4)]);
+        let target = counts(&[(known.as_str(), 30), ("elsewhere001", 7)]);
+        let patterns = compare_patterns(&miner, &baseline, &target, 1.0, 10);
+
+        assert_eq!(patterns.new.len(), 2);
+        assert_eq!(patterns.new[0].id, known);
+        assert_eq!(patterns.new[0].pattern, "db pool exhausted after <*>");
+        assert_eq!(patterns.new[0].example, "db pool exhausted after 5000ms");
+        assert_eq!(patterns.new[1].id, "elsewhere001");
+        assert_eq!(patterns.new[1].pattern, "");
+        assert_eq!(patterns.new[1].target, 7);
+        assert_eq!(patterns.gone.len(), 1);
+        assert_eq!(patterns.gone[0].id, "gone0000000a");
+        assert_eq!(patterns.gone[0].baseline, 4);
+    }
+
+    #[test]
+    fn changed_patterns_need_a_large_enough_shift() {
+        let miner = PatternMiner::new(&PatternConfig::default()).unwrap();
+        let baseline = counts(&[("double", 10), ("small", 2), ("steady", 100)]);
+        let target = counts(&[("double", 20), ("small", 6), ("steady", 120)]);
+        let patterns = compare_patterns(&miner, &baseline, &target, 1.0, 10);
+        let changed: Vec<&str> = patterns.changed.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(changed, ["double"]);
+        // Baselines are scaled to the target's length first
+        let patterns = compare_patterns(&miner, &baseline, &target, 2.0, 10);
+        assert!(patterns.changed.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+//! Streaming bulk export for `/export`.
+//!
+//! Walks the same cursor pages as `/search`, so exports aren't limited by
+//! Meilisearch's `maxTotalHits`, and writes each page out as soon as it
+//! arrives instead of buffering the whole result.
+
+use std::io;
+
+use futures::stream::{self, Stream};
+use meilisearch_sdk::indexes::Index;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::cursor::{self, Cursor};
+use crate::filter::Filter;
+use crate::query;
+
+/// Hits fetched per page while exporting.
+const PAGE_SIZE: usize = 1000;
+
+/// CSV columns when `fields` isn't given.
+pub const DEFAULT_COLUMNS: &[&str] = &[
+    "timestamp",
+    "project",
+    "level",
+    "message",
+    "traceId",
+    "requestId",
+    "environment",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON document per line
+    #[default]
+    Ndjson,
+    Csv,
+    /// A single JSON array
+    Json,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// What to export and how to write it.
+pub struct Export {
+    pub index: Index,
+    pub text: String,
+    pub filters: Vec<Filter>,
+    /// Paging field, see [`cursor::sort_field`]
+    pub field: &'static str,
+    /// Stop after this many entries
+    pub limit: Option<usize>,
+    pub format: ExportFormat,
+    /// CSV columns; dotted paths reach into `meta`, e.g. `meta.http.status`
+    pub columns: Vec<String>,
+}
+
+struct State {
+    export: Export,
+    cursor: Option<Cursor>,
+    written: usize,
+    started: bool,
+    done: bool,
+}
+
+impl Export {
+    /// Stream every matching entry, newest first.
+    pub fn stream(self) -> impl Stream<Item = Result<String, io::Error>> {
+        let state = State {
+            export: self,
+            cursor: None,
+            written: 0,
+            started: false,
+            done: false,
+        };
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+            match state.next_chunk().await {
+                Ok(chunk) => Some((Ok(chunk), state)),
+                Err(e) => {
+                    state.done = true;
+                    Some((Err(io::Error::other(e)), state))
+                }
+            }
+        })
+    }
+
+    fn header(&self) -> String {
+        match self.format {
+            ExportFormat::Ndjson => String::new(),
+            ExportFormat::Csv => csv_row(self.columns.iter().map(|c| csv_cell(c))),
+            ExportFormat::Json => "[".to_string(),
+        }
+    }
+
+    fn write(&self, out: &mut String, doc: &Value, first: bool) {
+        match self.format {
+            ExportFormat::Ndjson => {
+                out.push_str(&doc.to_string());
+                out.push('\n');
+            }
+            ExportFormat::Csv => out.push_str(&csv_row(
+                self.columns
+                    .iter()
+                    .map(|c| query::lookup(doc, c).map(csv_value).unwrap_or_default()),
+            )),
+            ExportFormat::Json => {
+                if !first {
+                    out.push(',');
+                }
+                out.push('\n');
+                out.push_str(&doc.to_string());
+            }
+        }
+    }
+
+    fn footer(&self, empty: bool) -> &'static str {
+        match self.format {
+            ExportFormat::Json if empty => "]\n",
+            ExportFormat::Json => "\n]\n",
+            _ => "",
+        }
+    }
+}
+
+impl State {
+    /// Fetch and format the next page, plus the header or footer when this
+    /// is the first or last one.
+    async fn next_chunk(&mut self) -> Result<String, String> {
+        let export = &self.export;
+        let mut out = String::new();
+        if !self.started {
+            out.push_str(&export.header());
+            self.started = true;
+        }
+
+        let want = export
+            .limit
+            .map_or(PAGE_SIZE, |l| l.saturating_sub(self.written).min(PAGE_SIZE));
+        let mut next = None;
+        if want > 0 {
+            let search = cursor::Search {
+                index: &export.index,
+                text: &export.text,
+                filters: export.filters.clone(),
+                facets: &[],
+                totals: false,
+            };
+            let page = search
+                .page(export.field, self.cursor.as_ref(), want)
+                .await
+                .map_err(|e| e.to_string())?;
+            for doc in &page.hits {
+                export.write(&mut out, doc, self.written == 0);
+                self.written += 1;
+            }
+            next = page.next_cursor;
+        }
+
+        match next.as_deref().map(Cursor::decode) {
+            Some(cursor) => self.cursor = Some(cursor?),
+            None => {
+                out.push_str(export.footer(self.written == 0));
+                self.done = true;
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => csv_cell(s),
+        // Nested objects and arrays stay as JSON in one cell
+        other => csv_cell(&other.to_string()),
+    }
+}
+
+fn csv_cell(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn csv_row(cells: impl Iterator<Item = String>) -> String {
+    let mut row = cells.collect::<Vec<_>>().join(",");
+    row.push_str("\r\n");
+    row
+}
@@ -0,0 +1,333 @@
+//! Field discovery: describe the schema observed in a sample of log entries.
+//!
+//! `GET /fields` and the MCP `list_fields` tool pull the most recent entries
+//! matching a project/time filter and report, for every field path, the
+//! JSON types seen, how many entries contain it, how many distinct values it
+//! takes and its most common values. Counts are exact within the sample and extrapolated to the
+//! whole match set. Distinct values of filterable fields are counted by
+//! Meilisearch facets over every match; the others are estimated from how
+//! often values repeat in the sample.
+
+use std::collections::{BTreeSet, HashMap};
+
+use meilisearch_sdk::client::Client;
+use meilisearch_sdk::search::Selectors;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::meili::{self, MAX_VALUES_PER_FACET};
+
+/// Default and maximum number of entries sampled.
+pub const DEFAULT_SAMPLE: usize = 1000;
+pub const MAX_SAMPLE: usize = 5000;
+
+const TOP_VALUES: usize = 5;
+/// Values longer than this are truncated in `topValues`.
+const MAX_VALUE_LEN: usize = 100;
+/// Distinct values tracked per field; `sampleDistinct` stops counting here.
+const MAX_DISTINCT: usize = 10_000;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldInfo {
+    /// Dotted path, e.g. `level`, `meta.http.status`, `error.type`
+    pub path: String,
+    pub types: BTreeSet<&'static str>,
+    /// Sampled entries containing the field
+    pub count: u64,
+    /// `count` scaled to all matching entries
+    pub estimated_count: u64,
+    /// Distinct values in the sample
+    pub sample_distinct: usize,
+    /// Distinct values among all matching entries
+    pub estimated_distinct: u64,
+    /// True when `estimatedDistinct` is a count rather than an estimate
+    pub distinct_exact: bool,
+    pub top_values: Vec<TopValue>,
+    /// Usable in `/search` filters
+    pub filterable: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopValue {
+    pub value: Value,
+    pub count: u64,
+}
+
+#[derive(Default)]
+struct FieldStats {
+    types: BTreeSet<&'static str>,
+    count: u64,
+    values: HashMap<String, (Value, u64)>,
+}
+
+impl FieldStats {
+    fn add_value(&mut self, value: &Value) {
+        let key = value.to_string();
+        if let Some((_, n)) = self.values.get_mut(&key) {
+            *n += 1;
+        } else if self.values.len() < MAX_DISTINCT {
+            self.values.insert(key, (truncate(value), 1));
+        }
+    }
+}
+
+/// Fetch the `limit` most recent entries matching `filter`, with the total
+/// number of matches.
+pub async fn sample(
+    client: &Client,
+    filter: Option<&str>,
+    limit: usize,
+) -> anyhow::Result<(Vec<Value>, usize)> {
+    let index = client.index(meili::INDEX_NAME);
+    let mut search = index.search();
+    search.with_sort(&["timestampMs:desc"]);
+    search.with_limit(limit.min(MAX_SAMPLE));
+    if let Some(f) = filter {
+        search.with_filter(f);
+    }
+    let results = search.execute::<Value>().await?;
+    let docs: Vec<Value> = results.hits.into_iter().map(|h| h.result).collect();
+    let total = results.estimated_total_hits.unwrap_or(docs.len());
+    Ok((docs, total))
+}
+
+/// Describe the fields of `docs`. `total` is the number of entries the
+/// sample was drawn from; `filterable` tells whether a path can be filtered.
+pub fn describe(docs: &[Value], total: usize, filterable: impl Fn(&str) -> bool) -> Vec<FieldInfo> {
+    let mut stats: HashMap<String, FieldStats> = HashMap::new();
+    for doc in docs {
+        if let Value::Object(map) = doc {
+            for (key, value) in map {
+                walk(&mut stats, key.clone(), value);
+            }
+        }
+    }
+
+    let scale = if docs.is_empty() {
+        0.0
+    } else {
+        total.max(docs.len()) as f64 / docs.len() as f64
+    };
+    let mut fields: Vec<FieldInfo> = stats
+        .into_iter()
+        .map(|(path, s)| {
+            let mut values: Vec<(Value, u64)> = s.values.into_values().collect();
+            let sample_distinct = values.len();
+            let (estimated_distinct, distinct_exact) =
+                estimate_distinct(values.iter().map(|(_, n)| *n), scale);
+            values.sort_by(|a, b| {
+                b.1.cmp(&a.1)
+                    .then_with(|| a.0.to_string().cmp(&b.0.to_string()))
+            });
+            FieldInfo {
+                filterable: filterable(&path),
+                path,
+                types: s.types,
+                count: s.count,
+                estimated_count: (s.count as f64 * scale).round() as u64,
+                sample_distinct,
+                estimated_distinct,
+                distinct_exact,
+                top_values: values
+                    .into_iter()
+                    .take(TOP_VALUES)
+                    .map(|(value, count)| TopValue { value, count })
+                    .collect(),
+            }
+        })
+        .collect();
+    fields.sort_by(|a, b| a.path.cmp(&b.path));
+    fields
+}
+
+/// Chao1 estimate of the distinct values among all matching entries, from
+/// the sampled count of each value and the sample's `scale`. Values seen
+/// once hint at more that weren't sampled; values seen twice that most were.
+/// Returns the estimate and whether it is exact.
+fn estimate_distinct(counts: impl Iterator<Item = u64>, scale: f64) -> (u64, bool) {
+    let (mut distinct, mut once, mut twice, mut values) = (0u64, 0u64, 0u64, 0u64);
+    for n in counts {
+        distinct += 1;
+        values += n;
+        match n {
+            1 => once += 1,
+            2 => twice += 1,
+            _ => {}
+        }
+    }
+    if scale <= 1.0 {
+        return (distinct, distinct < MAX_DISTINCT as u64);
+    }
+    let unseen = if twice > 0 {
+        (once * once) as f64 / (2 * twice) as f64
+    } else {
+        (once * once.saturating_sub(1)) as f64 / 2.0
+    };
+    // Every unsampled value could be new, but no more than that
+    let unsampled = values as f64 * (scale - 1.0);
+    (distinct + unseen.min(unsampled).round() as u64, false)
+}
+
+/// Replace the sample estimates of filterable fields with Meilisearch's
+/// count of their distinct values over every entry matching `filter`.
+/// Fields with more values than a facet returns keep the larger of the two.
+pub async fn count_distinct(
+    client: &Client,
+    filter: Option<&str>,
+    fields: &mut [FieldInfo],
+) -> anyhow::Result<()> {
+    let paths: Vec<&str> = fields
+        .iter()
+        .filter(|f| f.filterable)
+        .map(|f| f.path.as_str())
+        .collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let index = client.index(meili::INDEX_NAME);
+    let mut search = index.search();
+    search.with_limit(0);
+    search.with_facets(Selectors::Some(&paths));
+    if let Some(f) = filter {
+        search.with_filter(f);
+    }
+    let results = search.execute::<Value>().await?;
+    let distribution = results.facet_distribution.unwrap_or_default();
+    let counts: HashMap<&str, usize> = distribution
+        .iter()
+        .map(|(path, values)| (path.as_str(), values.len()))
+        .collect();
+    apply_facet_counts(fields, &counts);
+    Ok(())
+}
+
+fn apply_facet_counts(fields: &mut [FieldInfo], counts: &HashMap<&str, usize>) {
+    for field in fields.iter_mut().filter(|f| f.filterable) {
+        let counted = counts.get(field.path.as_str()).copied().unwrap_or(0);
+        if counted < MAX_VALUES_PER_FACET {
+            field.estimated_distinct = counted as u64;
+            field.distinct_exact = true;
+        } else {
+            field.estimated_distinct = field.estimated_distinct.max(counted as u64);
+            field.distinct_exact = false;
+        }
+    }
+}
+
+/// Record a value under `path`, descending into objects. Array elements
+/// count as values of the array's path.
+fn walk(stats: &mut HashMap<String, FieldStats>, path: String, value: &Value) {
+    if let Value::Object(map) = value {
+        if !map.is_empty() {
+            for (key, inner) in map {
+                walk(stats, format!("{}.{}", path, key), inner);
+            }
+            return;
+        }
+    }
+
+    let field = stats.entry(path).or_default();
+    field.count += 1;
+    field.types.insert(type_name(value));
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .filter(|v| !v.is_object() && !v.is_array())
+            .for_each(|v| field.add_value(v)),
+        v => field.add_value(v),
+    }
+}
+
+fn truncate(value: &Value) -> Value {
+    match value {
+        Value::String(s) if s.chars().count() > MAX_VALUE_LEN => Value::String(format!(
+            "{}…",
+            s.chars().take(MAX_VALUE_LEN).collect::<String>()
+        )),
+        v => v.clone(),
+    }
+}
+
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field<'a>(fields: &'a [FieldInfo], path: &str) -> &'a FieldInfo {
+        fields.iter().find(|f| f.path == path).unwrap()
+    }
+
+    #[test]
+    fn whole_match_set_is_counted_exactly() {
+        let docs: Vec<Value> = (0..10)
+            .map(|i| json!({ "level": "info", "meta": { "userId": i % 4 } }))
+            .collect();
+        let fields = describe(&docs, 10, |_| false);
+        let user = field(&fields, "meta.userId");
+        assert_eq!(user.sample_distinct, 4);
+        assert_eq!(user.estimated_distinct, 4);
+        assert!(user.distinct_exact);
+    }
+
+    #[test]
+    fn unique_values_scale_with_the_match_set() {
+        let docs: Vec<Value> = (0..100)
+            .map(|i| json!({ "level": "info", "requestId": format!("req-{}", i) }))
+            .collect();
+        let fields = describe(&docs, 1000, |_| false);
+        let request = field(&fields, "requestId");
+        assert_eq!(request.sample_distinct, 100);
+        assert_eq!(request.estimated_distinct, 1000);
+        assert!(!request.distinct_exact);
+        // Every sampled entry has the same level, so there are likely no others
+        let level = field(&fields, "level");
+        assert_eq!(level.estimated_distinct, 1);
+        assert!(!level.distinct_exact);
+    }
+
+    #[test]
+    fn chao1_adds_values_hinted_at_by_singletons() {
+        // 10 values seen once, 5 seen twice: 15 + 10² / (2 * 5)
+        let counts = std::iter::repeat_n(1, 10).chain(std::iter::repeat_n(2, 5));
+        assert_eq!(estimate_distinct(counts, 10.0), (25, false));
+        // Nothing seen once: what was sampled is likely all there is
+        let counts = std::iter::repeat_n(3, 4);
+        assert_eq!(estimate_distinct(counts, 10.0), (4, false));
+        assert_eq!(estimate_distinct(std::iter::empty(), 10.0), (0, false));
+    }
+
+    #[test]
+    fn facet_counts_replace_estimates_of_filterable_fields() {
+        let docs: Vec<Value> = (0..100)
+            .map(|i| json!({ "project": format!("p{}", i), "service": format!("s{}", i) }))
+            .collect();
+        let mut fields = describe(&docs, 1000, |p| p != "service");
+        let counts = HashMap::from([("project", 37), ("service", 12)]);
+        apply_facet_counts(&mut fields, &counts);
+        let project = field(&fields, "project");
+        assert_eq!(project.estimated_distinct, 37);
+        assert!(project.distinct_exact);
+        let service = field(&fields, "service");
+        assert_eq!(service.estimated_distinct, 1000);
+        assert!(!service.distinct_exact);
+
+        // A full facet is only a lower bound
+        let counts = HashMap::from([("project", MAX_VALUES_PER_FACET)]);
+        apply_facet_counts(&mut fields, &counts);
+        let project = field(&fields, "project");
+        assert_eq!(project.estimated_distinct, MAX_VALUES_PER_FACET as u64);
+        assert!(!project.distinct_exact);
+    }
+}
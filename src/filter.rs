@@ -0,0 +1,494 @@
+//! Typed Meilisearch filter expressions.
+//!
+//! Every filter sent to Meilisearch is built from these types instead of
+//! `format!`: string values are always quoted and escaped, field names are
+//! checked against a strict character set, and nested AND/OR groups are
+//! parenthesized, so user input can't change the shape of the expression.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl From<&String> for Value {
+    fn from(s: &String) -> Self {
+        Value::Str(s.clone())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Int(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Float(n)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Gt => ">",
+            Op::Gte => ">=",
+            Op::Lt => "<",
+            Op::Lte => "<=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Cmp { field: String, op: Op, value: Value },
+    In { field: String, values: Vec<Value> },
+    Exists(String),
+    Not(Box<Filter>),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    InvalidField(String),
+    NonFiniteNumber(String),
+    /// A backslash before a quote or at the end of a value, which
+    /// Meilisearch's filter syntax can't express
+    Unquotable(String),
+    Empty,
+}
+
+impl std::fmt::Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterError::InvalidField(name) => write!(f, "invalid field name {:?}", name),
+            FilterError::NonFiniteNumber(field) => write!(f, "{} needs a finite number", field),
+            FilterError::Unquotable(field) => write!(
+                f,
+                "{} values can't end with a backslash or have one before a quote",
+                field
+            ),
+            FilterError::Empty => write!(f, "empty filter group"),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl Filter {
+    pub fn cmp(field: &str, op: Op, value: impl Into<Value>) -> Self {
+        Filter::Cmp {
+            field: field.to_string(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(field: &str, value: impl Into<Value>) -> Self {
+        Self::cmp(field, Op::Eq, value)
+    }
+
+    pub fn gt(field: &str, value: impl Into<Value>) -> Self {
+        Self::cmp(field, Op::Gt, value)
+    }
+
+    pub fn is_in<V: Into<Value>>(field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        Filter::In {
+            field: field.to_string(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn exists(field: &str) -> Self {
+        Filter::Exists(field.to_string())
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Filter::Not(Box::new(self))
+    }
+
+    /// Render the expression. Fails on invalid field names, non-finite
+    /// numbers and empty groups.
+    pub fn render(&self) -> Result<String, FilterError> {
+        let mut out = String::new();
+        self.write(&mut out)?;
+        Ok(out)
+    }
+
+    fn write(&self, out: &mut String) -> Result<(), FilterError> {
+        match self {
+            Filter::Cmp { field, op, value } => {
+                out.push_str(check_field(field)?);
+                out.push(' ');
+                out.push_str(op.as_str());
+                out.push(' ');
+                write_value(out, field, value)?;
+            }
+            Filter::In { field, values } => {
+                if values.is_empty() {
+                    return Err(FilterError::Empty);
+                }
+                out.push_str(check_field(field)?);
+                out.push_str(" IN [");
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    write_value(out, field, value)?;
+                }
+                out.push(']');
+            }
+            Filter::Exists(field) => {
+                out.push_str(check_field(field)?);
+                out.push_str(" EXISTS");
+            }
+            Filter::Not(inner) => {
+                out.push_str("NOT (");
+                inner.write(out)?;
+                out.push(')');
+            }
+            Filter::And(items) | Filter::Or(items) => {
+                let sep = if matches!(self, Filter::And(_)) {
+                    " AND "
+                } else {
+                    " OR "
+                };
+                match items.as_slice() {
+                    [] => return Err(FilterError::Empty),
+                    [only] => only.write(out)?,
+                    _ => {
+                        out.push('(');
+                        for (i, item) in items.iter().enumerate() {
+                            if i > 0 {
+                                out.push_str(sep);
+                            }
+                            item.write(out)?;
+                        }
+                        out.push(')');
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// AND a list of filters together; `None` when the list is empty.
+pub fn render_all<I: IntoIterator<Item = Filter>>(
+    filters: I,
+) -> Result<Option<String>, FilterError> {
+    let filters: Vec<Filter> = filters.into_iter().collect();
+    let parts = filters
+        .iter()
+        .map(Filter::render)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((!parts.is_empty()).then(|| parts.join(" AND ")))
+}
+
+/// Meilisearch filter keywords, which can't be used as bare field names.
+const KEYWORDS: &[&str] = &[
+    "AND", "OR", "NOT", "IN", "TO", "EXISTS", "IS", "NULL", "EMPTY",
+];
+
+/// Field names are dotted identifiers: ASCII letters, digits, `_` and `-`
+/// separated by single dots.
+pub fn is_valid_field(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+        })
+        && !name.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+        && !KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(name))
+}
+
+fn check_field(name: &str) -> Result<&str, FilterError> {
+    if is_valid_field(name) {
+        Ok(name)
+    } else {
+        Err(FilterError::InvalidField(name.to_string()))
+    }
+}
+
+fn write_value(out: &mut String, field: &str, value: &Value) -> Result<(), FilterError> {
+    match value {
+        // Meilisearch only unescapes `\"`; other backslashes are literal
+        Value::Str(s) if s.ends_with('\\') || s.contains("\\\"") => {
+            return Err(FilterError::Unquotable(field.to_string()))
+        }
+        Value::Str(s) => {
+            out.push('"');
+            out.push_str(&s.replace('"', "\\\""));
+            out.push('"');
+        }
+        Value::Int(n) => out.push_str(&n.to_string()),
+        Value::Float(n) if n.is_finite() => out.push_str(&n.to_string()),
+        Value::Float(_) => return Err(FilterError::NonFiniteNumber(field.to_string())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Read a `"`-quoted value the way Meilisearch's filter parser does
+    /// (`quoted_by` and `unescape` in milli's `filter-parser/src/value.rs`):
+    /// a backslash skips the next character, and only `\"` is unescaped
+    /// afterwards. `input` starts after the opening quote; returns the value
+    /// and the rest after the closing quote.
+    fn meili_quoted(input: &str) -> Option<(String, &str)> {
+        let mut escaped = false;
+        let mut chars = input.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '"' {
+                let raw = &input[..i];
+                let value = if escaped {
+                    raw.replace("\\\"", "\"")
+                } else {
+                    raw.to_string()
+                };
+                return Some((value, &input[i + 1..]));
+            } else if c == '\\' {
+                escaped |= chars.next()?.1 == '"';
+            }
+        }
+        None
+    }
+
+    /// Tokens of a rendered filter, with each string literal read as
+    /// Meilisearch reads it and tagged, so the structure can be compared
+    /// independently of values.
+    fn tokens(rendered: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        let mut rest = rendered;
+        let flush = |word: &mut String, tokens: &mut Vec<String>| {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(word));
+            }
+        };
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '"' => {
+                    flush(&mut word, &mut tokens);
+                    let (literal, after) = meili_quoted(rest)
+                        .unwrap_or_else(|| panic!("unterminated literal in {}", rendered));
+                    rest = after;
+                    tokens.push(format!("\"{}", literal));
+                }
+                '(' | ')' | '[' | ']' | ',' => {
+                    flush(&mut word, &mut tokens);
+                    tokens.push(c.to_string());
+                }
+                c if c.is_whitespace() => flush(&mut word, &mut tokens),
+                c => word.push(c),
+            }
+        }
+        flush(&mut word, &mut tokens);
+        tokens
+    }
+
+    /// The same tokens with every literal replaced by a placeholder.
+    fn shape(rendered: &str) -> Vec<String> {
+        tokens(rendered)
+            .into_iter()
+            .map(|t| if t.starts_with('"') { "\"".into() } else { t })
+            .collect()
+    }
+
+    /// Strings made of filter syntax that must stay inside their literal.
+    fn hostile() -> impl Strategy<Value = String> + Clone {
+        prop::collection::vec(
+            prop_oneof![
+                Just("\"".to_string()),
+                Just("\\".to_string()),
+                Just(" OR ".to_string()),
+                Just(" AND ".to_string()),
+                Just(")".to_string()),
+                Just("(".to_string()),
+                Just("] OR x = 1".to_string()),
+                "[a-z ]{0,3}",
+            ],
+            0..8,
+        )
+        .prop_map(|parts| parts.concat())
+    }
+
+    fn filter(
+        values: impl Strategy<Value = String> + Clone + 'static,
+    ) -> impl Strategy<Value = Filter> {
+        let field = prop_oneof![Just("project"), Just("level"), Just("meta.user.id")];
+        let leaf = prop_oneof![
+            (field.clone(), values.clone()).prop_map(|(f, v)| Filter::eq(f, v)),
+            (field.clone(), values.clone()).prop_map(|(f, v)| Filter::cmp(f, Op::Ne, v)),
+            (field, prop::collection::vec(values, 1..4)).prop_map(|(f, vs)| Filter::is_in(f, vs)),
+        ];
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 1..4).prop_map(Filter::And),
+                prop::collection::vec(inner.clone(), 1..4).prop_map(Filter::Or),
+                inner.prop_map(Filter::not),
+            ]
+        })
+    }
+
+    /// The filter with every string value replaced by `x`.
+    fn skeleton(filter: &Filter) -> Filter {
+        let plain = |v: &Value| match v {
+            Value::Str(_) => Value::Str("x".into()),
+            v => v.clone(),
+        };
+        match filter {
+            Filter::Cmp { field, op, value } => Filter::cmp(field, *op, plain(value)),
+            Filter::In { field, values } => Filter::is_in(field, values.iter().map(plain)),
+            Filter::Exists(field) => Filter::exists(field),
+            Filter::Not(inner) => skeleton(inner).not(),
+            Filter::And(items) => Filter::And(items.iter().map(skeleton).collect()),
+            Filter::Or(items) => Filter::Or(items.iter().map(skeleton).collect()),
+        }
+    }
+
+    /// Check that `value` reads back unchanged, or can't be rendered at all.
+    fn round_trips(value: &str) -> Result<(), TestCaseError> {
+        let unquotable = value.ends_with('\\') || value.contains("\\\"");
+        match Filter::eq("project", value).render() {
+            Ok(rendered) => {
+                prop_assert!(!unquotable, "rendered {}", rendered);
+                prop_assert_eq!(
+                    tokens(&rendered),
+                    vec!["project".to_string(), "=".into(), format!("\"{}", value)]
+                );
+            }
+            Err(e) => {
+                prop_assert!(unquotable, "{}", e);
+                prop_assert_eq!(e, FilterError::Unquotable("project".into()));
+            }
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn string_values_render_as_one_literal(value in any::<String>()) {
+            round_trips(&value)?;
+        }
+
+        #[test]
+        fn hostile_values_render_as_one_literal(value in hostile()) {
+            round_trips(&value)?;
+        }
+
+        #[test]
+        fn valid_fields_match_the_grammar(name in any::<String>()) {
+            let grammar = regex::Regex::new(r"^[A-Za-z_][A-Za-z0-9_-]*(\.[A-Za-z0-9_-]+)*$").unwrap();
+            let keyword = KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(&name));
+            prop_assert_eq!(is_valid_field(&name), grammar.is_match(&name) && !keyword);
+        }
+
+        #[test]
+        fn fields_from_the_grammar_are_valid(name in r"[A-Za-z_][A-Za-z0-9_-]{0,8}(\.[A-Za-z0-9_-]{1,8}){0,3}") {
+            let keyword = KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(&name));
+            prop_assert_eq!(is_valid_field(&name), !keyword);
+        }
+
+        #[test]
+        fn near_misses_are_invalid(
+            name in r"[A-Za-z_][A-Za-z0-9_]{0,6}",
+            junk in prop_oneof![r#"[ "'()\[\]=!<>,:/\\]"#, Just(".".to_string()), Just("..x".to_string())],
+        ) {
+            let field = format!("{}{}", name, junk);
+            prop_assert!(!is_valid_field(&field));
+            prop_assert!(Filter::exists(&field).render().is_err());
+        }
+
+        #[test]
+        fn values_cannot_change_the_structure(filters in prop::collection::vec(filter(hostile()), 1..4)) {
+            let Ok(rendered) = render_all(filters.clone()) else {
+                return Ok(());
+            };
+            let rendered = rendered.unwrap();
+            let plain = render_all(filters.iter().map(skeleton)).unwrap().unwrap();
+            prop_assert_eq!(shape(&rendered), shape(&plain));
+        }
+    }
+
+    #[test]
+    fn escapes_only_quotes() {
+        for (value, rendered) in [
+            (r#"a"b"#, r#"project = "a\"b""#),
+            (r"C:\Users\app.log", r#"project = "C:\Users\app.log""#),
+            (r"\d+\.\d+", r#"project = "\d+\.\d+""#),
+            (r#""quoted""#, r#"project = "\"quoted\"""#),
+            (r"a\\b", r#"project = "a\\b""#),
+        ] {
+            let out = Filter::eq("project", value).render().unwrap();
+            assert_eq!(out, rendered, "{value:?}");
+            assert_eq!(
+                meili_quoted(&out["project = \"".len()..]),
+                Some((value.to_string(), "")),
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_values_meilisearch_cannot_read() {
+        for value in [r"C:\Users\", r#"say \"hi\""#, "\\"] {
+            assert_eq!(
+                Filter::eq("source", value).render(),
+                Err(FilterError::Unquotable("source".into())),
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn groups_are_parenthesized() {
+        let rendered = render_all([
+            Filter::Or(vec![
+                Filter::eq("level", "error"),
+                Filter::eq("level", "fatal"),
+            ]),
+            Filter::eq("project", "api").not(),
+        ])
+        .unwrap();
+        assert_eq!(
+            rendered.as_deref(),
+            Some(r#"(level = "error" OR level = "fatal") AND NOT (project = "api")"#)
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Log volume over time for `/histogram`.
+//!
+//! Each bucket is a range query on `timestampMs` with `limit=0`; counts come
+//! from the `level` facet, which is exact where `estimatedTotalHits` isn't,
+//! and the `groupBy` facet splits them further.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::{StreamExt, TryStreamExt};
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::filter::{self, Filter, Op};
+use crate::timerange::{self, TimeRange};
+
+/// Facets a histogram can be split by.
+pub const GROUPS: [&str; 2] = ["level", "project"];
+
+/// Span covered when the range has no start.
+const DEFAULT_SPAN_MS: i64 = 3_600_000;
+/// Bucket count an automatic interval aims for.
+const TARGET_BUCKETS: i64 = 60;
+const MAX_BUCKETS: i64 = 500;
+/// Bucket queries in flight at once.
+const CONCURRENCY: usize = 8;
+
+/// Intervals picked automatically, smallest first.
+const NICE_INTERVALS: &[i64] = &[
+    1_000,
+    5_000,
+    10_000,
+    30_000,
+    60_000,
+    300_000,
+    600_000,
+    900_000,
+    1_800_000,
+    3_600_000,
+    10_800_000,
+    21_600_000,
+    43_200_000,
+    86_400_000,
+    604_800_000,
+];
+
+/// Resolved range and bucket width.
+#[derive(Debug, Clone, Copy)]
+pub struct Plan {
+    pub from: i64,
+    pub to: i64,
+    pub interval: i64,
+}
+
+impl Plan {
+    /// Close an open range (ending now, one hour long) and pick an interval
+    /// if none is given.
+    pub fn new(range: &TimeRange, interval: Option<&str>) -> Result<Self, String> {
+        let to = range.to.unwrap_or_else(|| Utc::now().timestamp_millis());
+        let from = range.from.unwrap_or(to - DEFAULT_SPAN_MS);
+        let span = (to - from).max(1);
+        let interval = match interval {
+            Some(s) => match timerange::parse_duration(s) {
+                Some(ms) if ms > 0 => ms,
+                _ => return Err(format!("invalid interval {:?}", s)),
+            },
+            None => NICE_INTERVALS
+                .iter()
+                .copied()
+                .find(|iv| span / iv <= TARGET_BUCKETS)
+                .unwrap_or_else(|| (span / TARGET_BUCKETS / 86_400_000 + 1) * 86_400_000),
+        };
+        let plan = Self { from, to, interval };
+        let buckets = plan.buckets();
+        if buckets > MAX_BUCKETS {
+            return Err(format!(
+                "interval {} gives {} buckets; at most {} are allowed",
+                timerange::format_duration(interval),
+                buckets,
+                MAX_BUCKETS
+            ));
+        }
+        Ok(plan)
+    }
+
+    /// Start of the first bucket, aligned to the interval.
+    fn first(&self) -> i64 {
+        self.from.div_euclid(self.interval) * self.interval
+    }
+
+    /// Number of buckets, computed without walking them.
+    fn buckets(&self) -> i64 {
+        let first = self.first();
+        if self.to < first {
+            return 0;
+        }
+        (self.to - first) / self.interval + 1
+    }
+
+    /// Bucket starts, aligned to the interval.
+    fn starts(&self) -> impl Iterator<Item = i64> {
+        let interval = self.interval;
+        let first = self.first();
+        let to = self.to;
+        (0..)
+            .map(move |i| first + i * interval)
+            .take_while(move |start| *start <= to)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bucket {
+    /// Epoch millis of the bucket start
+    pub start: i64,
+    pub time: String,
+    pub count: usize,
+    /// Counts per `groupBy` value, when grouping
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<BTreeMap<String, usize>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Histogram {
+    pub from: i64,
+    pub to: i64,
+    /// Bucket width, e.g. `5m`
+    pub interval: String,
+    pub interval_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<String>,
+    pub total: usize,
+    pub buckets: Vec<Bucket>,
+}
+
+/// Count matches per bucket. `filter` already restricts the search to the
+/// plan's range; `group_by` must be one of [`GROUPS`].
+pub async fn compute(
+    index: &Index,
+    text: &str,
+    filter: Option<&str>,
+    plan: &Plan,
+    group_by: Option<&str>,
+) -> anyhow::Result<Histogram> {
+    let facets: Vec<&str> = match group_by {
+        Some(g) if g != "level" => vec!["level", g],
+        _ => vec!["level"],
+    };
+    let buckets: Vec<Bucket> = futures::stream::iter(plan.starts())
+        .map(|start| {
+            let facets = &facets;
+            async move {
+                let range = filter::render_all([
+                    Filter::cmp("timestampMs", Op::Gte, start),
+                    Filter::cmp("timestampMs", Op::Lt, start + plan.interval),
+                ])?
+                .unwrap_or_default();
+                let bucket_filter = match filter {
+                    Some(f) => format!("{} AND {}", f, range),
+                    None => range,
+                };
+
+                let mut search = index.search();
+                search.with_query(text);
+                search.with_limit(0);
+                search.with_filter(&bucket_filter);
+                search.with_facets(Selectors::Some(facets));
+                let results = search.execute::<Value>().await?;
+
+                let mut distribution = results.facet_distribution.unwrap_or_default();
+                let count = distribution
+                    .get("level")
+                    .map_or(0, |levels| levels.values().sum());
+                let groups = group_by.map(|g| {
+                    distribution
+                        .remove(g)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect()
+                });
+                anyhow::Ok(Bucket {
+                    start,
+                    time: DateTime::<Utc>::from_timestamp_millis(start)
+                        .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+                        .unwrap_or_default(),
+                    count,
+                    groups,
+                })
+            }
+        })
+        .buffered(CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    Ok(Histogram {
+        from: plan.from,
+        to: plan.to,
+        interval: timerange::format_duration(plan.interval),
+        interval_ms: plan.interval,
+        group_by: group_by.map(String::from),
+        total: buckets.iter().map(|b| b.count).sum(),
+        buckets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_count_matches_starts() {
+        for (from, to, interval) in [
+            (0, 0, 10),
+            (5, 5, 10),
+            (5, 25, 10),
+            (-15, 15, 10),
+            (0, 59_999, 60_000),
+        ] {
+            let plan = Plan { from, to, interval };
+            assert_eq!(plan.buckets(), plan.starts().count() as i64, "{:?}", plan);
+        }
+    }
+
+    #[test]
+    fn rejects_too_many_buckets_without_walking_them() {
+        let to = 30 * 86_400_000;
+        let range = TimeRange {
+            from: Some(0),
+            to: Some(to),
+        };
+        let err = Plan::new(&range, Some("1ms")).unwrap_err();
+        assert!(err.contains(&format!("{} buckets", to + 1)), "{}", err);
+    }
+}
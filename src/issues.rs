@@ -0,0 +1,558 @@
+//! Error fingerprinting and grouping into issues.
+//!
+//! Every error/fatal entry gets a `fingerprint` derived from its project, its
+//! message with variable parts (numbers, UUIDs, hex, quoted values) stripped,
+//! and, when present, its exception type and top stack frame. Entries sharing a fingerprint
+//! are one issue. Issues are kept in memory for ingest and the HTTP API, and
+//! persisted to the `issues` Meilisearch index.
+//!
+//! Issues can be resolved (optionally "in version X") or ignored (optionally
+//! "for 24h" or "until version X"). A new occurrence of a resolved issue is a
+//! regression: the issue reopens and a `regression` event goes out to live
+//! subscribers. An ignore reopens silently once it lapses.
+
+use std::sync::{LazyLock, Mutex};
+
+use dashmap::{DashMap, DashSet};
+use meilisearch_sdk::client::Client;
+use meilisearch_sdk::documents::DocumentsQuery;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::meili::ISSUES_INDEX;
+use crate::models::{LogEntry, LogLevel};
+use crate::stacktrace::{self, StackFrame};
+use crate::timerange::TimeRange;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueStatus {
+    #[default]
+    Unresolved,
+    Resolved,
+    Ignored,
+}
+
+/// Requested status change, as sent to `POST /issues/:fingerprint/status`.
+#[derive(Debug, Deserialize)]
+pub struct StatusChange {
+    pub status: IssueStatus,
+    /// Ignore duration (e.g. 24h); only valid with `ignored`
+    #[serde(rename = "for")]
+    pub duration: Option<String>,
+    /// Resolved in / ignored until this release
+    pub version: Option<String>,
+}
+
+/// A group of error entries sharing a fingerprint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    pub fingerprint: String,
+    pub project: String,
+    /// Highest level seen (error or fatal)
+    pub level: LogLevel,
+    /// Normalized message
+    pub title: String,
+    pub count: u64,
+    pub first_seen: String,
+    pub first_seen_ms: i64,
+    pub last_seen: String,
+    pub last_seen_ms: i64,
+    /// Most recent entry
+    pub sample: LogEntry,
+
+    // --- Lifecycle ---
+    #[serde(default)]
+    pub status: IssueStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_changed_at: Option<String>,
+    /// Ignore lapses at this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignored_until_ms: Option<i64>,
+    /// Resolved in / ignored until this release
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_version: Option<String>,
+    #[serde(default)]
+    pub regression_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_regression: Option<String>,
+}
+
+impl Issue {
+    /// Apply lifecycle rules for a new occurrence. Returns `true` if it is a
+    /// regression of a resolved issue.
+    fn on_occurrence(&mut self, entry: &LogEntry) -> bool {
+        let version = release_of(entry);
+        let at_or_after = |target: &Option<String>| match (target, &version) {
+            (Some(t), Some(v)) => compare_versions(v, t) != std::cmp::Ordering::Less,
+            // Without a target any occurrence counts; without a version we can't tell
+            (None, _) => true,
+            (Some(_), None) => false,
+        };
+        match self.status {
+            IssueStatus::Unresolved => false,
+            IssueStatus::Resolved => {
+                if !at_or_after(&self.status_version) {
+                    return false;
+                }
+                self.reopen();
+                self.regression_count += 1;
+                self.last_regression = Some(entry.timestamp.clone());
+                true
+            }
+            IssueStatus::Ignored => {
+                let expired = self
+                    .ignored_until_ms
+                    .is_some_and(|until| entry.received_at_ms >= until);
+                let released = self.status_version.is_some() && at_or_after(&self.status_version);
+                if expired || released {
+                    self.reopen();
+                }
+                false
+            }
+        }
+    }
+
+    fn reopen(&mut self) {
+        self.status = IssueStatus::Unresolved;
+        self.status_changed_at = Some(chrono::Utc::now().to_rfc3339());
+        self.ignored_until_ms = None;
+        self.status_version = None;
+    }
+}
+
+/// Release an entry came from: `meta.release`, `meta.version` or `meta.appVersion`.
+fn release_of(entry: &LogEntry) -> Option<String> {
+    let meta = entry.meta.as_ref()?;
+    ["release", "version", "appVersion"]
+        .iter()
+        .find_map(|k| meta.get(*k))
+        .map(|v| match v {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+}
+
+/// Compare dotted versions numerically segment by segment (`1.10` > `1.9`),
+/// falling back to string order for non-numeric segments.
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let a = a.trim_start_matches('v');
+    let b = b.trim_start_matches('v');
+    let mut left = a.split(['.', '-', '+']);
+    let mut right = b.split(['.', '-', '+']);
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(x), Some(y)) => {
+                let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y),
+                };
+                if ord != std::cmp::Ordering::Equal {
+                    return ord;
+                }
+            }
+        }
+    }
+}
+
+static UUID_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").unwrap()
+});
+static HEX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b0x[0-9a-f]+\b|\b[0-9a-f]{8,}\b").unwrap());
+static QUOTED_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""[^"]*"|'[^']*'|`[^`]*`"#).unwrap());
+static NUMBER_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)?").unwrap());
+
+/// Strip the variable parts of a message so that occurrences of the same
+/// problem normalize to the same text.
+pub fn normalize_message(message: &str) -> String {
+    let s = UUID_RE.replace_all(message, "<uuid>");
+    let s = QUOTED_RE.replace_all(&s, "<str>");
+    let s = HEX_RE.replace_all(&s, "<hex>");
+    let s = NUMBER_RE.replace_all(&s, "<num>");
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Innermost application frame from `error.frames`, falling back to a stack
+/// left in `meta.stack` or `meta.error.stack` by older SDKs. Line numbers are
+/// left out so redeploys don't split the issue.
+fn top_frame(entry: &LogEntry) -> Option<String> {
+    if let Some(frames) = entry.error.as_ref().map(|e| &e.frames) {
+        if !frames.is_empty() {
+            return Some(app_frame(frames).signature());
+        }
+    }
+    let meta = entry.meta.as_ref()?;
+    let stack = meta
+        .get("stack")
+        .or_else(|| meta.get("error").and_then(|e| e.get("stack")))?
+        .as_str()?;
+    let frames = stacktrace::parse_stack(stack);
+    (!frames.is_empty()).then(|| app_frame(&frames).signature())
+}
+
+/// First non-library frame, or the innermost one if all are library frames.
+fn app_frame(frames: &[StackFrame]) -> &StackFrame {
+    frames
+        .iter()
+        .find(|f| !f.is_library())
+        .unwrap_or(&frames[0])
+}
+
+/// Whether `s` looks like a fingerprint: 16 lowercase hex digits.
+pub fn is_fingerprint(s: &str) -> bool {
+    s.len() == 16 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Compute the fingerprint of an error entry.
+pub fn fingerprint(entry: &LogEntry) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(entry.project.as_bytes());
+    hasher.update([0]);
+    hasher.update(normalize_message(&entry.message).as_bytes());
+    if let Some(error_type) = entry.error.as_ref().and_then(|e| e.error_type.as_deref()) {
+        hasher.update([0]);
+        hasher.update(error_type.as_bytes());
+    }
+    if let Some(frame) = top_frame(entry) {
+        hasher.update([0]);
+        hasher.update(frame.as_bytes());
+    }
+    let digest = hasher.finalize();
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Issue title: normalized message, prefixed with the exception type.
+fn title(entry: &LogEntry) -> String {
+    let message = normalize_message(&entry.message);
+    match entry.error.as_ref().and_then(|e| e.error_type.as_deref()) {
+        Some(error_type) if !message.starts_with(error_type) => {
+            format!("{}: {}", error_type, message)
+        }
+        _ => message,
+    }
+}
+
+fn is_error(level: &LogLevel) -> bool {
+    matches!(level, LogLevel::Error | LogLevel::Fatal)
+}
+
+pub struct IssueTracker {
+    issues: DashMap<String, Issue>,
+    /// Fingerprints changed since the last flush to Meilisearch
+    dirty: DashSet<String>,
+    /// Regressions detected since the last `take_regressions`
+    regressions: Mutex<Vec<Issue>>,
+}
+
+impl IssueTracker {
+    pub fn new() -> Self {
+        Self {
+            issues: DashMap::new(),
+            dirty: DashSet::new(),
+            regressions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Load persisted issues from Meilisearch.
+    pub async fn load(&self, client: &Client) -> anyhow::Result<()> {
+        let index = client.index(ISSUES_INDEX);
+        let mut offset = 0;
+        loop {
+            let page = DocumentsQuery::new(&index)
+                .with_offset(offset)
+                .with_limit(1000)
+                .execute::<Issue>()
+                .await?;
+            let fetched = page.results.len();
+            for issue in page.results {
+                self.issues.insert(issue.fingerprint.clone(), issue);
+            }
+            offset += fetched;
+            if fetched == 0 || offset >= page.total as usize {
+                break;
+            }
+        }
+        tracing::info!("Loaded {} issues", self.issues.len());
+        Ok(())
+    }
+
+    /// Fingerprint an error/fatal entry and fold it into its issue.
+    pub fn record(&self, entry: &mut LogEntry) {
+        if !is_error(&entry.level) {
+            return;
+        }
+        let fp = fingerprint(entry);
+        entry.fingerprint = Some(fp.clone());
+
+        let mut regressed = false;
+        self.issues
+            .entry(fp.clone())
+            .and_modify(|issue| {
+                issue.count += 1;
+                regressed = issue.on_occurrence(entry);
+                if entry.timestamp_ms >= issue.last_seen_ms {
+                    issue.last_seen = entry.timestamp.clone();
+                    issue.last_seen_ms = entry.timestamp_ms;
+                    issue.sample = entry.clone();
+                }
+                if entry.timestamp_ms < issue.first_seen_ms {
+                    issue.first_seen = entry.timestamp.clone();
+                    issue.first_seen_ms = entry.timestamp_ms;
+                }
+                if entry.level == LogLevel::Fatal {
+                    issue.level = LogLevel::Fatal;
+                }
+            })
+            .or_insert_with(|| Issue {
+                fingerprint: fp.clone(),
+                project: entry.project.clone(),
+                level: entry.level.clone(),
+                title: title(entry),
+                count: 1,
+                first_seen: entry.timestamp.clone(),
+                first_seen_ms: entry.timestamp_ms,
+                last_seen: entry.timestamp.clone(),
+                last_seen_ms: entry.timestamp_ms,
+                sample: entry.clone(),
+                status: IssueStatus::Unresolved,
+                status_changed_at: None,
+                ignored_until_ms: None,
+                status_version: None,
+                regression_count: 0,
+                last_regression: None,
+            });
+        if regressed {
+            if let Some(issue) = self.get(&fp) {
+                tracing::warn!(fingerprint = %fp, "Regression: {}", issue.title);
+                self.regressions.lock().unwrap().push(issue);
+            }
+        }
+        self.dirty.insert(fp);
+    }
+
+    /// Regressions detected since the last call.
+    pub fn take_regressions(&self) -> Vec<Issue> {
+        std::mem::take(&mut *self.regressions.lock().unwrap())
+    }
+
+    /// Resolve, ignore or reopen an issue. `ignore_for_ms` only applies to
+    /// `ignored`. Returns the updated issue, or `None` if it doesn't exist.
+    pub fn set_status(
+        &self,
+        fingerprint: &str,
+        status: IssueStatus,
+        ignore_for_ms: Option<i64>,
+        version: Option<String>,
+    ) -> Option<Issue> {
+        let mut issue = self.issues.get_mut(fingerprint)?;
+        let now = chrono::Utc::now();
+        issue.status = status;
+        issue.status_changed_at = Some(now.to_rfc3339());
+        issue.ignored_until_ms = match status {
+            IssueStatus::Ignored => {
+                ignore_for_ms.map(|ms| now.timestamp_millis().saturating_add(ms))
+            }
+            _ => None,
+        };
+        issue.status_version = match status {
+            IssueStatus::Unresolved => None,
+            _ => version,
+        };
+        let updated = issue.clone();
+        drop(issue);
+        self.dirty.insert(fingerprint.to_string());
+        Some(updated)
+    }
+
+    /// Issues changed since the last call.
+    pub fn take_dirty(&self) -> Vec<Issue> {
+        let keys: Vec<String> = self.dirty.iter().map(|k| k.clone()).collect();
+        keys.into_iter()
+            .filter_map(|k| {
+                self.dirty.remove(&k);
+                self.issues.get(&k).map(|i| i.clone())
+            })
+            .collect()
+    }
+
+    /// Mark issues changed again, e.g. after a failed persist.
+    pub fn mark_dirty(&self, issues: &[Issue]) {
+        for issue in issues {
+            self.dirty.insert(issue.fingerprint.clone());
+        }
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<Issue> {
+        self.issues.get(fingerprint).map(|i| i.clone())
+    }
+
+    /// Issues for a project last seen within `range`, ordered by `sort`
+    /// (`lastSeen` default, `firstSeen` or `count`), newest/largest first.
+    pub fn list(
+        &self,
+        project: Option<&str>,
+        status: Option<IssueStatus>,
+        range: &TimeRange,
+        sort: Option<&str>,
+    ) -> Vec<Issue> {
+        let mut issues: Vec<Issue> = self
+            .issues
+            .iter()
+            .filter(|i| project.is_none_or(|p| i.project == p))
+            .filter(|i| status.is_none_or(|s| i.status == s))
+            .filter(|i| range.contains(i.last_seen_ms))
+            .map(|i| i.clone())
+            .collect();
+        match sort {
+            Some("count") => issues.sort_by_key(|i| std::cmp::Reverse(i.count)),
+            Some("firstSeen") => issues.sort_by_key(|i| std::cmp::Reverse(i.first_seen_ms)),
+            _ => issues.sort_by_key(|i| std::cmp::Reverse(i.last_seen_ms)),
+        }
+        issues
+    }
+}
+
+/// Write changed issues to Meilisearch. Returns `false` if the write failed.
+pub async fn persist(client: &Client, issues: &[Issue]) -> bool {
+    if issues.is_empty() {
+        return true;
+    }
+    let index = client.index(ISSUES_INDEX);
+    match index.add_documents(issues, Some("fingerprint")).await {
+        Ok(_task) => {
+            tracing::debug!("Persisted {} issues", issues.len());
+            true
+        }
+        Err(e) => {
+            tracing::error!("Issue persist error: {:?}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(release: &str, received_at_ms: i64) -> LogEntry {
+        let mut entry: LogEntry = serde_json::from_value(serde_json::json!({
+            "project": "api",
+            "level": "error",
+            "message": "Payment failed for order 1234",
+            "meta": { "release": release },
+        }))
+        .unwrap();
+        entry.received_at_ms = received_at_ms;
+        entry
+    }
+
+    /// Record `entry` and return its issue.
+    fn record(tracker: &IssueTracker, mut entry: LogEntry) -> Issue {
+        tracker.record(&mut entry);
+        tracker.get(&entry.fingerprint.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn resolved_issue_regresses_on_a_newer_release() {
+        let tracker = IssueTracker::new();
+        let issue = record(&tracker, error("1.9.0", 0));
+        tracker.set_status(
+            &issue.fingerprint,
+            IssueStatus::Resolved,
+            None,
+            Some("1.10.0".into()),
+        );
+
+        let issue = record(&tracker, error("1.9.3", 0));
+        assert_eq!(issue.status, IssueStatus::Resolved);
+        assert!(tracker.take_regressions().is_empty());
+
+        let issue = record(&tracker, error("v1.10.0", 0));
+        assert_eq!(issue.status, IssueStatus::Unresolved);
+        assert_eq!(issue.status_version, None);
+        assert_eq!(issue.regression_count, 1);
+        assert!(issue.last_regression.is_some());
+        let regressions = tracker.take_regressions();
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].fingerprint, issue.fingerprint);
+    }
+
+    #[test]
+    fn ignored_issue_reopens_at_its_version_without_a_regression() {
+        let tracker = IssueTracker::new();
+        let issue = record(&tracker, error("2.0.0", 0));
+        tracker.set_status(
+            &issue.fingerprint,
+            IssueStatus::Ignored,
+            None,
+            Some("2.1".into()),
+        );
+
+        assert_eq!(
+            record(&tracker, error("2.0.9", 0)).status,
+            IssueStatus::Ignored
+        );
+        let issue = record(&tracker, error("2.1.0", 0));
+        assert_eq!(issue.status, IssueStatus::Unresolved);
+        assert_eq!(issue.regression_count, 0);
+        assert!(tracker.take_regressions().is_empty());
+    }
+
+    #[test]
+    fn ignore_lapses_after_its_duration() {
+        let tracker = IssueTracker::new();
+        let issue = record(&tracker, error("1.0.0", 0));
+        let issue = tracker
+            .set_status(&issue.fingerprint, IssueStatus::Ignored, Some(60_000), None)
+            .unwrap();
+        let until = issue.ignored_until_ms.unwrap();
+
+        assert_eq!(
+            record(&tracker, error("1.0.0", until - 1)).status,
+            IssueStatus::Ignored
+        );
+        let issue = record(&tracker, error("1.0.0", until));
+        assert_eq!(issue.status, IssueStatus::Unresolved);
+        assert_eq!(issue.ignored_until_ms, None);
+    }
+
+    #[test]
+    fn huge_ignore_durations_saturate() {
+        let tracker = IssueTracker::new();
+        let issue = record(&tracker, error("1.0.0", 0));
+        let issue = tracker
+            .set_status(
+                &issue.fingerprint,
+                IssueStatus::Ignored,
+                Some(i64::MAX),
+                None,
+            )
+            .unwrap();
+        assert_eq!(issue.ignored_until_ms, Some(i64::MAX));
+    }
+
+    #[test]
+    fn dirty_issues_can_be_marked_again() {
+        let tracker = IssueTracker::new();
+        let issue = record(&tracker, error("1.0.0", 0));
+        let dirty = tracker.take_dirty();
+        assert_eq!(dirty.len(), 1);
+        assert!(tracker.take_dirty().is_empty());
+
+        tracker.set_status(&issue.fingerprint, IssueStatus::Resolved, None, None);
+        let dirty = tracker.take_dirty();
+        assert_eq!(dirty[0].status, IssueStatus::Resolved);
+        // A failed persist puts them back for the next one
+        tracker.mark_dirty(&dirty);
+        let retried = tracker.take_dirty();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].status, IssueStatus::Resolved);
+    }
+}
@@ -1,10 +1,33 @@
 mod collector;
 mod config;
+mod context;
+mod cursor;
+mod dedup;
+mod diff;
+mod export;
+mod fields;
+mod filter;
+mod histogram;
+mod issues;
+mod mcp;
 mod meili;
+mod meta;
 mod models;
-mod mcp;
+mod patterns;
+mod pipeline;
+mod query;
+mod redact;
 mod routes;
+mod sampling;
+mod saved;
+mod spans;
+mod stacktrace;
+mod stats;
 mod subscribers;
+mod timerange;
+mod traces;
+
+use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use tracing_subscriber::EnvFilter;
@@ -35,6 +58,10 @@ enum Commands {
         /// Start MCP server for AI integration
         #[arg(long, default_value = "false")]
         with_mcp: bool,
+
+        /// JSON config file for the ingest pipeline (redaction rules, ...)
+        #[arg(long, env = "LOGSTREAM_CONFIG")]
+        config: Option<PathBuf>,
     },
 
     /// Initialize Meilisearch index with proper settings
@@ -63,19 +90,22 @@ async fn main() -> anyhow::Result<()> {
             meili_host,
             meili_key,
             with_mcp,
+            config,
         } => {
             let cfg = config::Config {
                 port,
                 meili_host: meili_host.clone(),
                 meili_key: meili_key.clone(),
                 with_mcp,
+                pipeline: config::PipelineConfig::load(config.as_deref())?,
             };
-            
+
             // Start MCP server if requested
             if with_mcp {
                 let mcp_cfg = mcp::McpConfig {
                     meili_host,
                     meili_key,
+                    server_url: format!("http://127.0.0.1:{}", port),
                 };
                 tokio::spawn(async move {
                     if let Err(e) = mcp::run_mcp_server(mcp_cfg).await {
@@ -7,15 +7,22 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::io::AsyncBufReadExt;
 
+use crate::cursor::Cursor;
+use crate::filter::{self, Filter};
+use crate::timerange::TimeRange;
+
 #[derive(Clone)]
 pub struct McpConfig {
     pub meili_host: String,
     pub meili_key: String,
+    /// Collector HTTP API, used for tools that change server state
+    pub server_url: String,
 }
 
 /// MCP JSON-RPC request
 #[derive(Debug, Deserialize)]
 pub struct JsonRpcRequest {
+    #[allow(dead_code)]
     pub jsonrpc: String,
     pub id: Option<serde_json::Value>,
     pub method: String,
@@ -47,7 +54,6 @@ pub async fn run_mcp_server(cfg: McpConfig) -> anyhow::Result<()> {
     use meilisearch_sdk::client::Client;
     
     let meili = Client::new(&cfg.meili_host, Some(&cfg.meili_key))?;
-    let index = meili.index("logs");
 
     // Read lines from stdin
     let stdin = tokio::io::stdin();
@@ -72,8 +78,8 @@ pub async fn run_mcp_server(cfg: McpConfig) -> anyhow::Result<()> {
                     }
                 };
 
-                let response = handle_request(&index, request).await;
-                
+                let response = handle_request(&meili, &cfg.server_url, request).await;
+
                 let response_json = serde_json::to_string(&response)?;
                 println!("{}", response_json);
             }
@@ -84,9 +90,12 @@ pub async fn run_mcp_server(cfg: McpConfig) -> anyhow::Result<()> {
 }
 
 async fn handle_request(
-    index: &meilisearch_sdk::indexes::Index,
+    meili: &meilisearch_sdk::client::Client,
+    server_url: &str,
     request: JsonRpcRequest,
 ) -> JsonRpcResponse {
+    let index = &meili.index(crate::meili::INDEX_NAME);
+
     match request.method.as_str() {
         "initialize" => JsonRpcResponse {
             jsonrpc: "2.0".to_string(),
@@ -111,38 +120,61 @@ async fn handle_request(
                 "tools": [
                     {
                         "name": "search_logs",
-                        "description": "Search logs with full-text search across all projects. Supports filtering by project/level/trace/time.",
+                        "description": "Search logs with the logstream query language (full-text terms plus field filters) across all projects. Supports filtering by project/level/trace/time.",
                         "inputSchema": {
                             "type": "object",
                             "properties": {
-                                "query": { "type": "string", "description": "Full-text search query" },
+                                "saved": { "type": "string", "description": "Name of a saved search to run; other arguments narrow or override it (see list_saved_searches)" },
+                                "query": { "type": "string", "description": "Query language: field filters (project:api level:>=warn meta.status:>=500 trace:*), AND/OR/NOT, parentheses, and full-text words or \"quoted phrases\". Call list_fields to see available fields." },
                                 "project": { "type": "string", "description": "Filter by project" },
-                                "level": { "type": "string", "enum": ["debug", "info", "warn", "error", "fatal"] },
+                                "level": { "type": "string", "description": "Level or comma-separated levels, e.g. warn,error" },
+                                "minLevel": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error", "fatal"], "description": "Only logs at least this severe" },
                                 "traceId": { "type": "string", "description": "Filter by trace ID" },
-                                "since": { "type": "string", "description": "Time range: 5m, 1h, 2d" },
-                                "limit": { "type": "number", "description": "Max results (default 20)" }
+                                "since": { "type": "string", "description": "Time range: 5m, 1h30m, 2d" },
+                                "from": { "type": "string", "description": "Range start: RFC 3339, epoch millis, now-2h, yesterday" },
+                                "to": { "type": "string", "description": "Range end, same syntax as from" },
+                                "limit": { "type": "number", "description": "Max results (default 20)" },
+                                "sortBy": { "type": "string", "enum": ["timestamp", "received"], "description": "Order by client timestamp (default) or server receive time" },
+                                "cursor": { "type": "string", "description": "nextCursor (older logs) or prevCursor (newer logs) from a previous search_logs result" }
                             }
                         }
                     },
                     {
                         "name": "get_trace",
-                        "description": "Get all log entries for a trace ID, ordered chronologically.",
+                        "description": "Get all log entries for a trace ID, ordered chronologically, or with view=tree the span hierarchy: each span's timing, project, error status and children, plus orphan spans.",
                         "inputSchema": {
                             "type": "object",
                             "properties": {
-                                "traceId": { "type": "string" }
+                                "traceId": { "type": "string" },
+                                "view": { "type": "string", "enum": ["timeline", "tree"], "description": "timeline (default) or tree" }
                             },
                             "required": ["traceId"]
                         }
                     },
+                    {
+                        "name": "get_log_context",
+                        "description": "Get the log entries just before and after one entry (by id), from the same project, request, trace or source. The anchor entry is marked with \"anchor\": true.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "id": { "type": "string", "description": "Log entry id, e.g. from search_logs" },
+                                "before": { "type": "number", "description": "Entries before the anchor (default 50, max 500)" },
+                                "after": { "type": "number", "description": "Entries after the anchor (default 50, max 500)" },
+                                "scope": { "type": "string", "enum": ["project", "request", "trace", "source"], "description": "Which entries count as neighbors (default project)" }
+                            },
+                            "required": ["id"]
+                        }
+                    },
                     {
                         "name": "tail_logs",
                         "description": "Get the most recent N logs, optionally filtered.",
                         "inputSchema": {
                             "type": "object",
                             "properties": {
+                                "query": { "type": "string", "description": "Query language: field filters (project:api level:>=warn meta.status:>=500 trace:*), AND/OR/NOT, parentheses, and full-text words or \"quoted phrases\". Call list_fields to see available fields." },
                                 "project": { "type": "string" },
-                                "level": { "type": "string" },
+                                "level": { "type": "string", "description": "Level or comma-separated levels" },
+                                "minLevel": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error", "fatal"], "description": "Only logs at least this severe" },
                                 "limit": { "type": "number", "description": "Number of recent logs (default 30)" }
                             }
                         }
@@ -154,12 +186,126 @@ async fn handle_request(
                     },
                     {
                         "name": "error_summary",
-                        "description": "Get recent errors with full context.",
+                        "description": "Get recent errors grouped into issues (by fingerprint), plus the latest errors with full context.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "since": { "type": "string", "description": "Time range (default 1h unless from is given)" },
+                                "from": { "type": "string", "description": "Range start: RFC 3339, epoch millis, now-2h, yesterday" },
+                                "to": { "type": "string", "description": "Range end, same syntax as from" },
+                                "project": { "type": "string" },
+                                "minLevel": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error", "fatal"], "description": "Include levels at least this severe (default: error)" }
+                            }
+                        }
+                    },
+                    {
+                        "name": "log_histogram",
+                        "description": "Count matching logs per time bucket to see when volume or errors spiked. Optionally split each bucket by level or project.",
                         "inputSchema": {
                             "type": "object",
                             "properties": {
-                                "since": { "type": "string", "description": "Time range (default 1h)" },
-                                "project": { "type": "string" }
+                                "saved": { "type": "string", "description": "Name of a saved search to run; other arguments narrow or override it (see list_saved_searches)" },
+                                "query": { "type": "string", "description": "Query language, same as search_logs" },
+                                "project": { "type": "string" },
+                                "level": { "type": "string", "description": "Level or comma-separated levels, e.g. warn,error" },
+                                "minLevel": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error", "fatal"], "description": "Only logs at least this severe" },
+                                "since": { "type": "string", "description": "Time range (default 1h unless from is given)" },
+                                "from": { "type": "string", "description": "Range start: RFC 3339, epoch millis, now-2h, yesterday" },
+                                "to": { "type": "string", "description": "Range end, same syntax as from" },
+                                "interval": { "type": "string", "description": "Bucket width, e.g. 1m, 1h (picked automatically by default)" },
+                                "groupBy": { "type": "string", "enum": ["level", "project"], "description": "Split counts by level or project" }
+                            }
+                        }
+                    },
+                    {
+                        "name": "field_stats",
+                        "description": "Aggregate one field over matching logs: most common values (top), distinct count, or min/max/avg/p50/p95/p99 for numeric fields such as meta.durationMs. Call list_fields to find field paths.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "saved": { "type": "string", "description": "Name of a saved search to run; other arguments narrow or override it (see list_saved_searches)" },
+                                "field": { "type": "string", "description": "Field path, e.g. meta.path or meta.durationMs" },
+                                "agg": { "type": "string", "description": "Comma-separated: top (default), count_distinct, min, max, avg, p50, p95, p99" },
+                                "limit": { "type": "number", "description": "Values returned by top (default 10)" },
+                                "query": { "type": "string", "description": "Query language, same as search_logs" },
+                                "project": { "type": "string" },
+                                "level": { "type": "string", "description": "Level or comma-separated levels, e.g. warn,error" },
+                                "minLevel": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error", "fatal"], "description": "Only logs at least this severe" },
+                                "since": { "type": "string", "description": "Time range: 5m, 1h30m, 2d" },
+                                "from": { "type": "string", "description": "Range start: RFC 3339, epoch millis, now-2h, yesterday" },
+                                "to": { "type": "string", "description": "Range end, same syntax as from" }
+                            },
+                            "required": ["field"]
+                        }
+                    },
+                    {
+                        "name": "compare_windows",
+                        "description": "Compare two time windows to see what changed, e.g. now vs an hour ago during an incident: count changes per project, level and error fingerprint, plus message patterns that are new, gone or changed significantly, ranked by change. Baseline counts are scaled to the target window's length.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "target": { "type": "string", "description": "Window under investigation as from..to (default now-1h..now)" },
+                                "baseline": { "type": "string", "description": "Window to compare against, e.g. now-2h..now-1h (default: the same length just before target)" },
+                                "saved": { "type": "string", "description": "Name of a saved search to compare (see list_saved_searches)" },
+                                "query": { "type": "string", "description": "Query language, same as search_logs" },
+                                "project": { "type": "string" },
+                                "level": { "type": "string", "description": "Level or comma-separated levels, e.g. warn,error" },
+                                "minLevel": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error", "fatal"], "description": "Only logs at least this severe" },
+                                "limit": { "type": "number", "description": "Entries per list (default 20)" }
+                            }
+                        }
+                    },
+                    {
+                        "name": "list_fields",
+                        "description": "Describe the fields present in recent logs: every field path (including flattened meta.* keys) with its types, how many logs contain it, how many distinct values it takes, top values and whether it can be filtered on. Use this before filtering on a field you haven't seen.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "project": { "type": "string" },
+                                "since": { "type": "string", "description": "Time range, e.g. 1h (default: all)" },
+                                "from": { "type": "string", "description": "Range start: RFC 3339, epoch millis, now-2h, yesterday" },
+                                "to": { "type": "string", "description": "Range end, same syntax as from" },
+                                "sample": { "type": "number", "description": "Recent logs to sample (default 1000, max 5000)" }
+                            }
+                        }
+                    },
+                    {
+                        "name": "list_issues",
+                        "description": "List error issues (errors grouped by fingerprint) with count, first/last seen and status.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "project": { "type": "string" },
+                                "status": { "type": "string", "enum": ["unresolved", "resolved", "ignored"] },
+                                "since": { "type": "string", "description": "Only issues seen within this range, e.g. 1h" },
+                                "from": { "type": "string", "description": "Range start: RFC 3339, epoch millis, now-2h, yesterday" },
+                                "to": { "type": "string", "description": "Range end, same syntax as from" },
+                                "sort": { "type": "string", "enum": ["lastSeen", "firstSeen", "count"] },
+                                "limit": { "type": "number", "description": "Max issues (default 20)" }
+                            }
+                        }
+                    },
+                    {
+                        "name": "set_issue_status",
+                        "description": "Resolve, ignore or reopen an issue. A resolved issue that occurs again is flagged as a regression.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "fingerprint": { "type": "string" },
+                                "status": { "type": "string", "enum": ["unresolved", "resolved", "ignored"] },
+                                "for": { "type": "string", "description": "Ignore duration, e.g. 24h (ignored only)" },
+                                "version": { "type": "string", "description": "Resolved in / ignored until this release" }
+                            },
+                            "required": ["fingerprint", "status"]
+                        }
+                    },
+                    {
+                        "name": "list_saved_searches",
+                        "description": "List the team's saved searches (name, owner, query, filters and default time range). Run one by passing its name as `saved` to search_logs, log_histogram or field_stats.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": {
+                                "owner": { "type": "string", "description": "Only searches saved by this owner" }
                             }
                         }
                     },
@@ -197,18 +343,24 @@ async fn handle_request(
 
             let result = match tool_name {
                 "search_logs" => {
-                    let result = search_logs(index, &arguments).await;
+                    let result = search_logs(meili, &arguments).await;
                     json!({ "content": [{ "type": "text", "text": result }] })
                 }
                 "get_trace" => {
-                    let trace_id = arguments.get("traceId")
+                    let trace_id = arguments
+                        .get("traceId")
                         .and_then(|v| v.as_str())
                         .unwrap_or("");
-                    let result = get_trace(index, trace_id).await;
+                    let tree = arguments.get("view").and_then(|v| v.as_str()) == Some("tree");
+                    let result = get_trace(index, trace_id, tree).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "get_log_context" => {
+                    let result = get_log_context(index, &arguments).await;
                     json!({ "content": [{ "type": "text", "text": result }] })
                 }
                 "tail_logs" => {
-                    let result = tail_logs(index, &arguments).await;
+                    let result = tail_logs(meili, &arguments).await;
                     json!({ "content": [{ "type": "text", "text": result }] })
                 }
                 "list_projects" => {
@@ -216,11 +368,47 @@ async fn handle_request(
                     json!({ "content": [{ "type": "text", "text": result }] })
                 }
                 "error_summary" => {
-                    let result = error_summary(index, &arguments).await;
+                    let result = error_summary(meili, &arguments).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "log_histogram" => {
+                    let result = log_histogram(meili, &arguments).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "field_stats" => {
+                    let result = field_stats(meili, &arguments).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "list_fields" => {
+                    let result = list_fields(meili, &arguments).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "compare_windows" => {
+                    let result = compare_windows(server_url, &arguments).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "list_issues" => {
+                    let result = list_issues(server_url, &arguments).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "set_issue_status" => {
+                    let result = set_issue_status(server_url, &arguments).await;
+                    json!({ "content": [{ "type": "text", "text": result }] })
+                }
+                "list_saved_searches" => {
+                    let owner = arguments.get("owner").and_then(|v| v.as_str());
+                    let result = match crate::saved::list(meili, owner).await {
+                        Ok(searches) => {
+                            serde_json::to_string_pretty(&json!({ "savedSearches": searches }))
+                                .unwrap_or_default()
+                        }
+                        Err(e) => format!("Error: {}", e),
+                    };
                     json!({ "content": [{ "type": "text", "text": result }] })
                 }
                 "find_similar" => {
-                    let message = arguments.get("message")
+                    let message = arguments
+                        .get("message")
                         .and_then(|v| v.as_str())
                         .unwrap_or("");
                     let result = find_similar(index, message, &arguments).await;
@@ -252,74 +440,190 @@ async fn handle_request(
 
 // --- Tool Implementations ---
 
-fn parse_duration(s: &str) -> i64 {
-    let s = s.trim();
-    if s.len() < 2 { return 3600000; }
-    let (num_str, unit) = s.split_at(s.len() - 1);
-    let num: i64 = num_str.parse().unwrap_or(1);
-    match unit {
-        "s" => num * 1000,
-        "m" => num * 60000,
-        "h" => num * 3600000,
-        "d" => num * 86400000,
-        _ => 3600000,
+fn build_filter(
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> Result<Vec<Filter>, String> {
+    let mut parts = crate::meili::level_filter(
+        args.get("level").and_then(|v| v.as_str()),
+        args.get("minLevel").and_then(|v| v.as_str()),
+    )?;
+
+    if let Some(p) = args.get("project").and_then(|v| v.as_str()) {
+        parts.push(Filter::eq("project", p));
     }
+    if let Some(t) = args.get("traceId").and_then(|v| v.as_str()) {
+        parts.push(Filter::eq("traceId", t));
+    }
+    parts.extend(time_range(args, None)?.filters("timestampMs"));
+
+    Ok(parts)
 }
 
-fn build_filter(args: &std::collections::HashMap<String, serde_json::Value>) -> Option<String> {
-    let mut parts = Vec::new();
-    
-    if let Some(p) = args.get("project").and_then(|v| v.as_str()) {
-        parts.push(format!("project = \"{}\"", p));
+/// Parse the `since`/`from`/`to` arguments; `default_since` applies when
+/// neither `since` nor `from` is given.
+fn time_range(
+    args: &std::collections::HashMap<String, serde_json::Value>,
+    default_since: Option<&str>,
+) -> Result<TimeRange, String> {
+    let arg = |k: &str| args.get(k).and_then(|v| v.as_str());
+    let since = match (arg("since"), arg("from")) {
+        (None, None) => default_since,
+        (since, _) => since,
+    };
+    TimeRange::parse(since, arg("from"), arg("to"))
+}
+
+/// Replace parsed stack frames with one-line strings and drop the raw stack,
+/// which is much easier for a model to read than nested frame objects.
+fn readable(mut hit: serde_json::Value) -> serde_json::Value {
+    let Some(error) = hit.get_mut("error").and_then(|e| e.as_object_mut()) else {
+        return hit;
+    };
+    let frames: Vec<crate::stacktrace::StackFrame> = error
+        .get("frames")
+        .and_then(|f| serde_json::from_value(f.clone()).ok())
+        .unwrap_or_default();
+    if !frames.is_empty() {
+        error.remove("stack");
+        error.insert(
+            "frames".into(),
+            frames.iter().map(|f| f.to_string()).collect(),
+        );
     }
-    if let Some(l) = args.get("level").and_then(|v| v.as_str()) {
-        parts.push(format!("level = \"{}\"", l));
+    hit
+}
+
+/// Merge the saved search named by the `saved` argument into the other
+/// arguments. Explicit arguments win, and a `query` narrows the saved one.
+async fn with_saved(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> Result<std::collections::HashMap<String, serde_json::Value>, String> {
+    let mut merged = args.clone();
+    let Some(name) = args.get("saved").and_then(|v| v.as_str()) else {
+        return Ok(merged);
+    };
+    let search = crate::saved::get(meili, name)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("saved search {:?} not found", name))?;
+
+    let query = crate::saved::and_queries(
+        search.subscription_query().as_deref(),
+        args.get("query").and_then(|v| v.as_str()),
+    );
+    if let Some(q) = query {
+        merged.insert("query".into(), json!(q));
     }
-    if let Some(t) = args.get("traceId").and_then(|v| v.as_str()) {
-        parts.push(format!("traceId = \"{}\"", t));
+    let f = search.filters;
+    for (key, value) in [
+        ("project", f.project),
+        ("level", f.level),
+        ("minLevel", f.min_level),
+        ("traceId", f.trace_id),
+    ] {
+        if let Some(v) = value {
+            merged.entry(key.into()).or_insert(json!(v));
+        }
     }
-    if let Some(s) = args.get("since").and_then(|v| v.as_str()) {
-        let cutoff = chrono::Utc::now().timestamp_millis() - parse_duration(s);
-        parts.push(format!("timestampMs > {}", cutoff));
+    if !["since", "from", "to"]
+        .iter()
+        .any(|k| args.contains_key(*k))
+    {
+        for (key, value) in [
+            ("since", search.since),
+            ("from", search.from),
+            ("to", search.to),
+        ] {
+            if let Some(v) = value {
+                merged.insert(key.into(), json!(v));
+            }
+        }
     }
-    
-    if parts.is_empty() { None } else { Some(parts.join(" AND ")) }
+    Ok(merged)
 }
 
-async fn search_logs(index: &meilisearch_sdk::indexes::Index, args: &std::collections::HashMap<String, serde_json::Value>) -> String {
-    let query = args.get("query").and_then(|v| v.as_str()).unwrap_or("");
+/// Compile the `query` argument with the query language, returning the
+/// filter clauses (with the other filter arguments) and full-text query.
+async fn compile_query(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> Result<(Vec<Filter>, String), String> {
+    let compiled = match args.get("query").and_then(|v| v.as_str()) {
+        Some(q) => {
+            let expr = crate::query::parse(q).map_err(|e| format!("invalid query: {}", e))?;
+            let promoted = crate::meili::meta_filterable(meili)
+                .await
+                .unwrap_or_default();
+            crate::query::compile(&expr, |f| crate::meili::is_filterable(f, &promoted))
+                .map_err(|e| format!("invalid query: {}", e))?
+        }
+        None => crate::query::Compiled::default(),
+    };
+    let filters = build_filter(args)?
+        .into_iter()
+        .chain(compiled.filter)
+        .collect();
+    Ok((filters, compiled.text))
+}
+
+async fn search_logs(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let args = &match with_saved(meili, args).await {
+        Ok(a) => a,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let (filters, query) = match compile_query(meili, args).await {
+        Ok(q) => q,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let cursor = match args
+        .get("cursor")
+        .and_then(|v| v.as_str())
+        .map(Cursor::decode)
+        .transpose()
+    {
+        Ok(c) => c,
+        Err(e) => return format!("Error: {}", e),
+    };
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
-    let filter = build_filter(args);
+    let field = crate::cursor::sort_field(args.get("sortBy").and_then(|v| v.as_str()));
 
-    let mut search = index.search();
-    search.with_query(query);
-    search.with_limit(limit);
-    search.with_sort(&["timestamp:desc"]);
-    search.with_facets(meilisearch_sdk::search::Selectors::Some(&["project", "level"]));
-    
-    if let Some(ref f) = filter {
-        search.with_filter(f.as_str());
-    }
+    let index = meili.index(crate::meili::INDEX_NAME);
+    let search = crate::cursor::Search {
+        index: &index,
+        text: &query,
+        filters,
+        facets: &["project", "level"],
+        totals: true,
+    };
 
-    match search.execute::<serde_json::Value>().await {
-        Ok(r) => {
-            let hits: Vec<serde_json::Value> = r.hits.into_iter().map(|h| h.result).collect();
-            serde_json::to_string_pretty(&json!({
-                "totalHits": r.estimated_total_hits,
-                "facets": r.facet_distribution,
-                "hits": hits
-            })).unwrap_or_default()
+    match search.page(field, cursor.as_ref(), limit).await {
+        Ok(mut page) => {
+            page.hits = page.hits.into_iter().map(readable).collect();
+            serde_json::to_string_pretty(&page).unwrap_or_default()
         }
         Err(e) => format!("Error: {}", e),
     }
 }
 
-async fn get_trace(index: &meilisearch_sdk::indexes::Index, trace_id: &str) -> String {
+async fn get_trace(index: &meilisearch_sdk::indexes::Index, trace_id: &str, tree: bool) -> String {
     if trace_id.is_empty() {
         return "Error: traceId is required".to_string();
     }
-    
-    let filter = format!("traceId = \"{}\"", trace_id);
+    if tree {
+        return match crate::spans::fetch(index, trace_id, None).await {
+            Ok(tree) => serde_json::to_string_pretty(&tree).unwrap_or_default(),
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    let filter = match Filter::eq("traceId", trace_id).render() {
+        Ok(f) => f,
+        Err(e) => return format!("Error: {}", e),
+    };
     let mut search = index.search();
     search.with_filter(&filter);
     search.with_sort(&["timestamp:asc"]);
@@ -327,37 +631,86 @@ async fn get_trace(index: &meilisearch_sdk::indexes::Index, trace_id: &str) -> S
 
     match search.execute::<serde_json::Value>().await {
         Ok(r) => {
-            let hits: Vec<serde_json::Value> = r.hits.into_iter().map(|h| h.result).collect();
-            let projects: Vec<&str> = hits.iter()
+            let hits: Vec<serde_json::Value> =
+                r.hits.into_iter().map(|h| readable(h.result)).collect();
+            let projects: Vec<&str> = hits
+                .iter()
                 .filter_map(|h| h.get("project").and_then(|p| p.as_str()))
                 .collect();
-            
+
             serde_json::to_string_pretty(&json!({
                 "traceId": trace_id,
                 "eventCount": hits.len(),
                 "projects": projects,
                 "timeline": hits
-            })).unwrap_or_default()
+            }))
+            .unwrap_or_default()
+        }
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+async fn get_log_context(
+    index: &meilisearch_sdk::indexes::Index,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let id = args.get("id").and_then(|v| v.as_str()).unwrap_or("");
+    if id.is_empty() {
+        return "Error: id is required".to_string();
+    }
+    let scope = match args
+        .get("scope")
+        .cloned()
+        .map(serde_json::from_value::<crate::context::Scope>)
+        .transpose()
+    {
+        Ok(s) => s.unwrap_or_default(),
+        Err(_) => return "Error: scope must be project, request, trace or source".to_string(),
+    };
+    let lines = |key: &str| {
+        args.get(key)
+            .and_then(|v| v.as_u64())
+            .map_or(crate::context::DEFAULT_LINES, |n| n as usize)
+            .min(crate::context::MAX_LINES)
+    };
+
+    match crate::context::around(index, id, scope, lines("before"), lines("after")).await {
+        Ok(mut ctx) => {
+            ctx.entries = ctx.entries.into_iter().map(readable).collect();
+            serde_json::to_string_pretty(&ctx).unwrap_or_default()
         }
         Err(e) => format!("Error: {}", e),
     }
 }
 
-async fn tail_logs(index: &meilisearch_sdk::indexes::Index, args: &std::collections::HashMap<String, serde_json::Value>) -> String {
+async fn tail_logs(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let (filters, query) = match compile_query(meili, args).await {
+        Ok(q) => q,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let filter = match filter::render_all(filters) {
+        Ok(f) => f,
+        Err(e) => return format!("Error: {}", e),
+    };
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(30) as usize;
-    let filter = build_filter(args);
 
+    let index = meili.index(crate::meili::INDEX_NAME);
     let mut search = index.search();
+    search.with_query(&query);
     search.with_sort(&["timestamp:desc"]);
     search.with_limit(limit);
-    
+
     if let Some(ref f) = filter {
         search.with_filter(f.as_str());
     }
 
     match search.execute::<serde_json::Value>().await {
         Ok(r) => {
-            let mut hits: Vec<serde_json::Value> = r.hits.into_iter().map(|h| h.result).collect();
+            let mut hits: Vec<serde_json::Value> =
+                r.hits.into_iter().map(|h| readable(h.result)).collect();
             hits.reverse();
             serde_json::to_string_pretty(&json!({
                 "count": hits.len(),
@@ -384,47 +737,129 @@ async fn list_projects(index: &meilisearch_sdk::indexes::Index) -> String {
     }
 }
 
-async fn error_summary(index: &meilisearch_sdk::indexes::Index, args: &std::collections::HashMap<String, serde_json::Value>) -> String {
-    let since = args.get("since").and_then(|v| v.as_str()).unwrap_or("1h");
+async fn error_summary(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let index = meili.index(crate::meili::INDEX_NAME);
     let project = args.get("project").and_then(|v| v.as_str());
-    
-    let mut parts = vec!["level = \"error\" OR level = \"fatal\"".to_string()];
-    
+
+    let mut parts =
+        match crate::meili::level_filter(None, args.get("minLevel").and_then(|v| v.as_str())) {
+            Ok(p) if p.is_empty() => vec![Filter::is_in("level", ["error", "fatal"])],
+            Ok(p) => p,
+            Err(e) => return format!("Error: {}", e),
+        };
+
     if let Some(p) = project {
-        parts.push(format!("project = \"{}\"", p));
+        parts.push(Filter::eq("project", p));
     }
-    
-    let cutoff = chrono::Utc::now().timestamp_millis() - parse_duration(since);
-    parts.push(format!("timestampMs > {}", cutoff));
-    
-    let filter = parts.join(" AND ");
-    
+
+    match time_range(args, Some("1h")) {
+        Ok(range) => parts.extend(range.filters("timestampMs")),
+        Err(e) => return format!("Error: {}", e),
+    }
+
+    let filter = match filter::render_all(parts) {
+        Ok(f) => f.unwrap_or_default(),
+        Err(e) => return format!("Error: {}", e),
+    };
+
     let mut search = index.search();
     search.with_filter(&filter);
     search.with_sort(&["timestamp:desc"]);
     search.with_limit(30);
-    search.with_facets(meilisearch_sdk::search::Selectors::Some(&["project"]));
+    search.with_facets(meilisearch_sdk::search::Selectors::Some(&[
+        "project",
+        "fingerprint",
+    ]));
 
     match search.execute::<serde_json::Value>().await {
         Ok(r) => {
-            let errors: Vec<serde_json::Value> = r.hits.into_iter().map(|h| h.result).collect();
+            let errors: Vec<serde_json::Value> =
+                r.hits.into_iter().map(|h| readable(h.result)).collect();
+            let counts = r
+                .facet_distribution
+                .as_ref()
+                .and_then(|f| f.get("fingerprint"));
+            let issues = match counts {
+                Some(counts) => issues_for(meili, counts).await,
+                None => Vec::new(),
+            };
             serde_json::to_string_pretty(&json!({
                 "totalErrors": r.estimated_total_hits,
+                "distinctIssues": issues.len(),
                 "byProject": r.facet_distribution.as_ref().and_then(|f| f.get("project")),
+                "issues": issues,
                 "recentErrors": errors
-            })).unwrap_or_default()
+            }))
+            .unwrap_or_default()
         }
         Err(e) => format!("Error: {}", e),
     }
 }
 
-async fn find_similar(index: &meilisearch_sdk::indexes::Index, message: &str, args: &std::collections::HashMap<String, serde_json::Value>) -> String {
+/// Look up issue documents for fingerprint facet counts, largest count first.
+async fn issues_for(
+    meili: &meilisearch_sdk::client::Client,
+    counts: &std::collections::HashMap<String, usize>,
+) -> Vec<serde_json::Value> {
+    let mut counts: Vec<(&String, &usize)> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1));
+
+    let Ok(filter) = Filter::is_in("fingerprint", counts.iter().map(|(fp, _)| *fp)).render() else {
+        return Vec::new();
+    };
+
+    let index = meili.index(crate::meili::ISSUES_INDEX);
+    let mut search = index.search();
+    search.with_filter(&filter);
+    search.with_limit(counts.len());
+    let docs: std::collections::HashMap<String, serde_json::Value> =
+        match search.execute::<serde_json::Value>().await {
+            Ok(r) => r
+                .hits
+                .into_iter()
+                .filter_map(|h| {
+                    let fp = h.result.get("fingerprint")?.as_str()?.to_string();
+                    Some((fp, h.result))
+                })
+                .collect(),
+            Err(_) => std::collections::HashMap::new(),
+        };
+
+    counts
+        .into_iter()
+        .map(|(fp, count)| {
+            let doc = docs.get(fp);
+            json!({
+                "fingerprint": fp,
+                "count": count,
+                "title": doc.and_then(|d| d.get("title")),
+                "project": doc.and_then(|d| d.get("project")),
+                "totalCount": doc.and_then(|d| d.get("count")),
+                "firstSeen": doc.and_then(|d| d.get("firstSeen")),
+                "lastSeen": doc.and_then(|d| d.get("lastSeen")),
+            })
+        })
+        .collect()
+}
+
+async fn find_similar(
+    index: &meilisearch_sdk::indexes::Index,
+    message: &str,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
     if message.is_empty() {
         return "Error: message is required".to_string();
     }
-    
+
     let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
-    let filter = build_filter(args);
+    let filter =
+        match build_filter(args).and_then(|f| filter::render_all(f).map_err(|e| e.to_string())) {
+            Ok(f) => f,
+            Err(e) => return format!("Error: {}", e),
+        };
 
     let mut search = index.search();
     search.with_query(message);
@@ -436,7 +871,8 @@ async fn find_similar(index: &meilisearch_sdk::indexes::Index, message: &str, ar
 
     match search.execute::<serde_json::Value>().await {
         Ok(r) => {
-            let matches: Vec<serde_json::Value> = r.hits.into_iter().map(|h| h.result).collect();
+            let matches: Vec<serde_json::Value> =
+                r.hits.into_iter().map(|h| readable(h.result)).collect();
             serde_json::to_string_pretty(&json!({
                 "query": message,
                 "matches": matches
@@ -445,3 +881,226 @@ async fn find_similar(index: &meilisearch_sdk::indexes::Index, message: &str, ar
         Err(e) => format!("Error: {}", e),
     }
 }
+
+async fn log_histogram(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let args = &match with_saved(meili, args).await {
+        Ok(a) => a,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let group_by = args.get("groupBy").and_then(|v| v.as_str());
+    if group_by.is_some_and(|g| !crate::histogram::GROUPS.contains(&g)) {
+        return format!(
+            "Error: groupBy must be one of: {}",
+            crate::histogram::GROUPS.join(", ")
+        );
+    }
+    let plan = match time_range(args, None).and_then(|range| {
+        crate::histogram::Plan::new(&range, args.get("interval").and_then(|v| v.as_str()))
+    }) {
+        Ok(p) => p,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let (filters, query) = match compile_query(meili, args).await {
+        Ok(q) => q,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let filter = match filter::render_all(filters) {
+        Ok(f) => f,
+        Err(e) => return format!("Error: {}", e),
+    };
+
+    let index = meili.index(crate::meili::INDEX_NAME);
+    match crate::histogram::compute(&index, &query, filter.as_deref(), &plan, group_by).await {
+        Ok(h) => serde_json::to_string_pretty(&h).unwrap_or_default(),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+async fn field_stats(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let args = &match with_saved(meili, args).await {
+        Ok(a) => a,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let field = args.get("field").and_then(|v| v.as_str()).unwrap_or("");
+    if !filter::is_valid_field(field) {
+        return format!("Error: invalid field {:?}", field);
+    }
+    let aggs = match crate::stats::Agg::parse_list(
+        args.get("agg").and_then(|v| v.as_str()).unwrap_or("top"),
+    ) {
+        Ok(a) if a.is_empty() => return "Error: agg must not be empty".to_string(),
+        Ok(a) => a,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let (filters, query) = match compile_query(meili, args).await {
+        Ok(q) => q,
+        Err(e) => return format!("Error: {}", e),
+    };
+
+    let index = meili.index(crate::meili::INDEX_NAME);
+    let promoted = crate::meili::meta_filterable(meili)
+        .await
+        .unwrap_or_default();
+    let request = crate::stats::Request {
+        index: &index,
+        text: &query,
+        filters,
+        field,
+        aggs: &aggs,
+        limit: args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(10)
+            .min(1000) as usize,
+        filterable: crate::meili::is_filterable(field, &promoted),
+    };
+    match request.run().await {
+        Ok(stats) => serde_json::to_string_pretty(&stats).unwrap_or_default(),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+async fn list_fields(
+    meili: &meilisearch_sdk::client::Client,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let range = match time_range(args, None) {
+        Ok(r) => r,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let filter = crate::meili::build_filter(
+        args.get("project").and_then(|v| v.as_str()),
+        None,
+        None,
+        None,
+        &range,
+    );
+    let sample = args
+        .get("sample")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize)
+        .unwrap_or(crate::fields::DEFAULT_SAMPLE);
+    let filter = match filter::render_all(filter) {
+        Ok(f) => f,
+        Err(e) => return format!("Error: {}", e),
+    };
+
+    match crate::fields::sample(meili, filter.as_deref(), sample).await {
+        Ok((docs, total)) => {
+            let promoted = crate::meili::meta_filterable(meili)
+                .await
+                .unwrap_or_default();
+            let mut fields = crate::fields::describe(&docs, total, |p| {
+                crate::meili::is_filterable(p, &promoted)
+            });
+            // Fall back to the sample estimates
+            if let Err(e) =
+                crate::fields::count_distinct(meili, filter.as_deref(), &mut fields).await
+            {
+                tracing::warn!("Field facet error: {:?}", e);
+            }
+            serde_json::to_string_pretty(&json!({
+                "sampled": docs.len(),
+                "totalHits": total,
+                "fields": fields
+            }))
+            .unwrap_or_default()
+        }
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+async fn compare_windows(
+    server_url: &str,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let mut params: Vec<(&str, String)> = vec![];
+    for key in [
+        "target", "baseline", "saved", "query", "project", "level", "minLevel",
+    ] {
+        if let Some(v) = args.get(key).and_then(|v| v.as_str()) {
+            params.push((key, v.to_string()));
+        }
+    }
+    if let Some(limit) = args.get("limit").and_then(|v| v.as_u64()) {
+        params.push(("limit", limit.to_string()));
+    }
+
+    let url = match reqwest::Url::parse_with_params(&format!("{}/diff", server_url), &params) {
+        Ok(u) => u,
+        Err(e) => return format!("Error: {}", e),
+    };
+    match reqwest::get(url).await {
+        Ok(resp) => match resp.json::<serde_json::Value>().await {
+            Ok(body) => serde_json::to_string_pretty(&body).unwrap_or_default(),
+            Err(e) => format!("Error: {}", e),
+        },
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+async fn list_issues(
+    server_url: &str,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let mut params: Vec<(&str, String)> = vec![];
+    for key in ["project", "status", "since", "from", "to", "sort"] {
+        if let Some(v) = args.get(key).and_then(|v| v.as_str()) {
+            params.push((key, v.to_string()));
+        }
+    }
+    let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(20);
+    params.push(("limit", limit.to_string()));
+
+    let url = match reqwest::Url::parse_with_params(&format!("{}/issues", server_url), &params) {
+        Ok(u) => u,
+        Err(e) => return format!("Error: {}", e),
+    };
+    match reqwest::get(url).await {
+        Ok(resp) => match resp.json::<serde_json::Value>().await {
+            Ok(body) => serde_json::to_string_pretty(&body).unwrap_or_default(),
+            Err(e) => format!("Error: {}", e),
+        },
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+async fn set_issue_status(
+    server_url: &str,
+    args: &std::collections::HashMap<String, serde_json::Value>,
+) -> String {
+    let fingerprint = args
+        .get("fingerprint")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    if fingerprint.is_empty() {
+        return "Error: fingerprint is required".to_string();
+    }
+    if !crate::issues::is_fingerprint(fingerprint) {
+        return format!(
+            "Error: invalid fingerprint {:?}: expected 16 hex digits",
+            fingerprint
+        );
+    }
+    let body = json!({
+        "status": args.get("status"),
+        "for": args.get("for"),
+        "version": args.get("version"),
+    });
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/issues/{}/status", server_url, fingerprint);
+    match client.post(&url).json(&body).send().await {
+        Ok(resp) => match resp.json::<serde_json::Value>().await {
+            Ok(body) => serde_json::to_string_pretty(&body).unwrap_or_default(),
+            Err(e) => format!("Error: {}", e),
+        },
+        Err(e) => format!("Error: {}", e),
+    }
+}
@@ -1,13 +1,55 @@
+use std::collections::{BTreeSet, HashMap};
 use std::time::Duration;
 
 use meilisearch_sdk::client::Client;
+use meilisearch_sdk::documents::DocumentsQuery;
+use meilisearch_sdk::task_info::TaskInfo;
 use tokio::sync::mpsc;
 use tokio::time;
 
-use crate::models::LogEntry;
+use crate::filter::{Filter, Op};
+use crate::models::{LogEntry, LogLevel};
+use crate::timerange::TimeRange;
 
 pub const INDEX_NAME: &str = "logs";
 
+/// Built-in filterable attributes of the logs index. Promoted meta keys are
+/// added on top as `meta.<key>`.
+const FILTERABLE: &[&str] = &[
+    "project",
+    "level",
+    "severity",
+    "traceId",
+    "requestId",
+    "spanId",
+    "parentSpanId",
+    "environment",
+    "timestampMs",
+    "receivedAtMs",
+    "clockSkewMs",
+    "fingerprint",
+    "error.type",
+    "source",
+    "patternId",
+    "id",
+];
+
+/// Index holding one document per error issue (see `issues`).
+pub const ISSUES_INDEX: &str = "issues";
+
+/// Index holding one document per message template (see `patterns`).
+pub const PATTERNS_INDEX: &str = "patterns";
+
+/// `maxValuesPerFacet` set on the logs index, so `/patterns` can count
+/// templates from one facet; a facet this long may be cut off.
+pub const MAX_VALUES_PER_FACET: usize = 10_000;
+
+/// Index holding one summary per trace (see `traces`).
+pub const TRACES_INDEX: &str = "traces";
+
+/// Index holding saved searches, keyed by name (see `saved`).
+pub const SAVED_SEARCHES_INDEX: &str = "saved_searches";
+
 /// Initialize the Meilisearch index with the correct settings.
 pub async fn init_index(host: &str, key: &str) -> anyhow::Result<()> {
     let client = Client::new(host, Some(key))?;
@@ -20,28 +62,26 @@ pub async fn init_index(host: &str, key: &str) -> anyhow::Result<()> {
 
     // Searchable attributes — what full-text search looks at
     let task = index
-        .set_searchable_attributes(["message", "source", "meta"])
+        .set_searchable_attributes([
+            "message",
+            "error.type",
+            "error.message",
+            "error.stack",
+            "source",
+            "meta",
+        ])
         .await?;
     let _ = client.wait_for_task(task, None, None).await;
 
-    // Filterable attributes — used in filter expressions
-    let task = index
-        .set_filterable_attributes([
-            "project",
-            "level",
-            "traceId",
-            "requestId",
-            "spanId",
-            "parentSpanId",
-            "environment",
-            "timestampMs",
-        ])
-        .await?;
+    // Filterable attributes — used in filter expressions. Keep meta keys
+    // promoted on a running collector.
+    let promoted = meta_filterable(&client).await.unwrap_or_default();
+    let task = set_meta_filterable(&client, &promoted).await?;
     let _ = client.wait_for_task(task, None, None).await;
 
     // Sortable attributes
     let task = index
-        .set_sortable_attributes(["timestamp", "timestampMs"])
+        .set_sortable_attributes(["timestamp", "timestampMs", "receivedAtMs", "id"])
         .await?;
     let _ = client.wait_for_task(task, None, None).await;
 
@@ -66,7 +106,144 @@ pub async fn init_index(host: &str, key: &str) -> anyhow::Result<()> {
         .await?;
     let _ = client.wait_for_task(task, None, None).await;
 
+    // Faceting
+    let task = index
+        .set_faceting(&meilisearch_sdk::settings::FacetingSettings {
+            max_values_per_facet: MAX_VALUES_PER_FACET,
+        })
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
     tracing::info!("Meilisearch index '{}' configured", INDEX_NAME);
+
+    init_issues_index(&client).await?;
+    init_patterns_index(&client).await?;
+    init_traces_index(&client).await?;
+
+    let task = client
+        .create_index(SAVED_SEARCHES_INDEX, Some("name"))
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+    tracing::info!("Meilisearch index '{}' configured", SAVED_SEARCHES_INDEX);
+    Ok(())
+}
+
+/// Whether a field path can be used in filters, given the promoted meta keys.
+pub fn is_filterable(path: &str, promoted: &BTreeSet<String>) -> bool {
+    FILTERABLE.contains(&path)
+        || path
+            .strip_prefix("meta.")
+            .is_some_and(|k| promoted.contains(k))
+}
+
+/// Meta keys (without the `meta.` prefix) currently filterable on the logs index.
+pub async fn meta_filterable(client: &Client) -> anyhow::Result<BTreeSet<String>> {
+    let attrs = client.index(INDEX_NAME).get_filterable_attributes().await?;
+    Ok(attrs
+        .iter()
+        .filter_map(|a| a.strip_prefix("meta."))
+        .map(String::from)
+        .collect())
+}
+
+/// Set the logs index filterable attributes to the built-in ones plus the
+/// given meta keys. Meilisearch reindexes in the background; the returned
+/// task tracks it.
+pub async fn set_meta_filterable(
+    client: &Client,
+    keys: &BTreeSet<String>,
+) -> anyhow::Result<TaskInfo> {
+    let attrs: Vec<String> = FILTERABLE
+        .iter()
+        .map(|a| a.to_string())
+        .chain(keys.iter().map(|k| format!("meta.{}", k)))
+        .collect();
+    Ok(client
+        .index(INDEX_NAME)
+        .set_filterable_attributes(&attrs)
+        .await?)
+}
+
+/// Create and configure the issues index.
+async fn init_issues_index(client: &Client) -> anyhow::Result<()> {
+    let task = client
+        .create_index(ISSUES_INDEX, Some("fingerprint"))
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let index = client.index(ISSUES_INDEX);
+
+    let task = index
+        .set_searchable_attributes(["title", "project"])
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let task = index
+        .set_filterable_attributes(["fingerprint", "project", "level", "status", "lastSeenMs"])
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let task = index
+        .set_sortable_attributes(["count", "firstSeenMs", "lastSeenMs"])
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    tracing::info!("Meilisearch index '{}' configured", ISSUES_INDEX);
+    Ok(())
+}
+
+/// Create and configure the patterns index.
+async fn init_patterns_index(client: &Client) -> anyhow::Result<()> {
+    let task = client.create_index(PATTERNS_INDEX, Some("id")).await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let index = client.index(PATTERNS_INDEX);
+
+    let task = index
+        .set_searchable_attributes(["template", "sample"])
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let task = index.set_filterable_attributes(["project"]).await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let task = index
+        .set_sortable_attributes(["seen", "lastSeenMs"])
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    tracing::info!("Meilisearch index '{}' configured", PATTERNS_INDEX);
+    Ok(())
+}
+
+/// Create and configure the traces index.
+async fn init_traces_index(client: &Client) -> anyhow::Result<()> {
+    let task = client.create_index(TRACES_INDEX, Some("traceId")).await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let index = client.index(TRACES_INDEX);
+
+    let task = index.set_searchable_attributes(["root"]).await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let task = index
+        .set_filterable_attributes([
+            "traceId",
+            "projects",
+            "hasError",
+            "durationMs",
+            "startMs",
+            "endMs",
+        ])
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    let task = index
+        .set_sortable_attributes(["startMs", "endMs", "durationMs", "errorCount"])
+        .await?;
+    let _ = client.wait_for_task(task, None, None).await;
+
+    tracing::info!("Meilisearch index '{}' configured", TRACES_INDEX);
     Ok(())
 }
 
@@ -131,7 +308,7 @@ impl MeiliBatcher {
 
 async fn flush(index: &meilisearch_sdk::indexes::Index, buffer: &mut Vec<LogEntry>) {
     let count = buffer.len();
-    let docs: Vec<LogEntry> = buffer.drain(..).collect();
+    let docs: Vec<LogEntry> = std::mem::take(buffer);
 
     match index.add_documents(&docs, Some("id")).await {
         Ok(_task) => {
@@ -144,63 +321,114 @@ async fn flush(index: &meilisearch_sdk::indexes::Index, buffer: &mut Vec<LogEntr
     }
 }
 
-/// Build a Meilisearch filter string from query parameters.
+/// Whether `id` is a valid Meilisearch document id: 1 to 511 ASCII letters,
+/// digits, `-` or `_`. The SDK puts ids into request paths unescaped, so
+/// anything else must be rejected before it reaches a URL.
+pub fn is_valid_id(id: &str) -> bool {
+    (1..=511).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Fetch one log entry by id; `None` when there is no such entry.
+pub async fn get_log(
+    index: &meilisearch_sdk::indexes::Index,
+    id: &str,
+) -> Result<Option<serde_json::Value>, meilisearch_sdk::errors::Error> {
+    use meilisearch_sdk::errors::{Error, ErrorCode, MeilisearchError};
+
+    // No document can have such an id
+    if !is_valid_id(id) {
+        return Ok(None);
+    }
+    match index.get_document::<serde_json::Value>(id).await {
+        Ok(doc) => Ok(Some(doc)),
+        Err(Error::Meilisearch(MeilisearchError {
+            error_code: ErrorCode::DocumentNotFound | ErrorCode::InvalidDocumentId,
+            ..
+        })) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Fetch several log entries by id with one filtered request, in the order
+/// given; `None` for ids with no entry.
+pub async fn get_logs(
+    index: &meilisearch_sdk::indexes::Index,
+    ids: &[&str],
+) -> Result<Vec<Option<serde_json::Value>>, meilisearch_sdk::errors::Error> {
+    let valid: Vec<&str> = ids.iter().copied().filter(|id| is_valid_id(id)).collect();
+    let mut found: HashMap<String, serde_json::Value> = HashMap::new();
+    if !valid.is_empty() {
+        let filter = Filter::is_in("id", valid.iter().copied())
+            .render()
+            .expect("id is a valid field");
+        let page = DocumentsQuery::new(index)
+            .with_filter(&filter)
+            .with_limit(valid.len())
+            .execute::<serde_json::Value>()
+            .await?;
+        for doc in page.results {
+            if let Some(id) = doc.get("id").and_then(|v| v.as_str()) {
+                found.insert(id.to_string(), doc);
+            }
+        }
+    }
+    Ok(ids.iter().map(|id| found.remove(*id)).collect())
+}
+
+/// Sort expression for a `sort` query parameter: `received` orders by server
+/// receive time, anything else by the client timestamp.
+pub fn sort_expr(sort: Option<&str>, descending: bool) -> &'static str {
+    match (sort, descending) {
+        (Some("received"), true) => "receivedAtMs:desc",
+        (Some("received"), false) => "receivedAtMs:asc",
+        (_, true) => "timestamp:desc",
+        (_, false) => "timestamp:asc",
+    }
+}
+
+/// Build filter clauses from query parameters, to be ANDed together.
 pub fn build_filter(
     project: Option<&str>,
-    level: Option<&str>,
     trace_id: Option<&str>,
     request_id: Option<&str>,
     environment: Option<&str>,
-    since: Option<&str>,
-) -> Option<String> {
-    let mut clauses: Vec<String> = Vec::new();
+    range: &TimeRange,
+) -> Vec<Filter> {
+    let mut clauses = Vec::new();
 
     if let Some(p) = project {
-        clauses.push(format!("project = \"{}\"", p));
-    }
-    if let Some(l) = level {
-        clauses.push(format!("level = \"{}\"", l));
+        clauses.push(Filter::eq("project", p));
     }
     if let Some(t) = trace_id {
-        clauses.push(format!("traceId = \"{}\"", t));
+        clauses.push(Filter::eq("traceId", t));
     }
     if let Some(r) = request_id {
-        clauses.push(format!("requestId = \"{}\"", r));
+        clauses.push(Filter::eq("requestId", r));
     }
     if let Some(e) = environment {
-        clauses.push(format!("environment = \"{}\"", e));
-    }
-    if let Some(s) = since {
-        if let Some(ms) = parse_duration(s) {
-            let cutoff = chrono::Utc::now().timestamp_millis() - ms;
-            clauses.push(format!("timestampMs > {}", cutoff));
-        }
+        clauses.push(Filter::eq("environment", e));
     }
+    clauses.extend(range.filters("timestampMs"));
 
-    if clauses.is_empty() {
-        None
-    } else {
-        Some(clauses.join(" AND "))
-    }
+    clauses
 }
 
-/// Parse a human-friendly duration string like "5m", "1h", "2d" into milliseconds.
-pub fn parse_duration(s: &str) -> Option<i64> {
-    let s = s.trim();
-    if s.len() < 2 {
-        return None;
+/// Level clauses: `levels` is a comma-separated list of exact levels,
+/// `min_level` a severity threshold.
+pub fn level_filter(levels: Option<&str>, min_level: Option<&str>) -> Result<Vec<Filter>, String> {
+    let mut clauses = Vec::new();
+    if let Some(l) = levels {
+        let levels = LogLevel::parse_list(l)?;
+        if !levels.is_empty() {
+            clauses.push(Filter::is_in("level", levels.iter().map(|l| l.to_string())));
+        }
     }
-
-    let (num_str, unit) = s.split_at(s.len() - 1);
-    let num: i64 = num_str.parse().ok()?;
-
-    let multiplier = match unit {
-        "s" => 1_000,
-        "m" => 60_000,
-        "h" => 3_600_000,
-        "d" => 86_400_000,
-        _ => return None,
-    };
-
-    Some(num * multiplier)
+    if let Some(m) = min_level {
+        let level = LogLevel::parse(m).ok_or_else(|| format!("unknown level {:?}", m))?;
+        clauses.push(Filter::cmp("severity", Op::Gte, level.severity() as i64));
+    }
+    Ok(clauses)
 }
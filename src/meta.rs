@@ -0,0 +1,224 @@
+//! Flattened `meta` attributes.
+//!
+//! At ingest, nested `meta` objects are flattened into dotted keys
+//! (`{"http": {"status": 500}}` becomes `{"http.status": 500}`) and every key
+//! is recorded in a registry with its count and value types. Operators
+//! promote chosen keys to filterable/facetable attributes through the config
+//! file or `POST /admin/meta/promote`; `/search` then accepts
+//! `meta.<key>=<op><value>` filters on them.
+
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::fields::type_name;
+use crate::filter::{self, Filter, Op};
+use crate::models::LogEntry;
+
+/// `meta` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetaConfig {
+    /// Nesting levels flattened; deeper objects are kept as values
+    pub max_depth: usize,
+    /// Maximum number of distinct keys tracked by the registry
+    pub max_keys: usize,
+    /// Keys (without the `meta.` prefix) made filterable at startup
+    pub filterable: Vec<String>,
+}
+
+impl Default for MetaConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_keys: 1000,
+            filterable: Vec::new(),
+        }
+    }
+}
+
+/// Registry entry for one flattened key, as reported by `GET /admin/meta`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyInfo {
+    pub key: String,
+    /// Entries seen with this key since startup
+    pub count: u64,
+    /// JSON types seen: string, number, boolean, array, object, null
+    pub types: BTreeSet<&'static str>,
+    pub filterable: bool,
+}
+
+#[derive(Default)]
+struct KeyStats {
+    count: u64,
+    types: BTreeSet<&'static str>,
+}
+
+pub struct MetaRegistry {
+    cfg: MetaConfig,
+    keys: DashMap<String, KeyStats>,
+    /// Keys currently filterable in the logs index
+    filterable: RwLock<BTreeSet<String>>,
+}
+
+impl MetaRegistry {
+    pub fn new(cfg: &MetaConfig) -> anyhow::Result<Self> {
+        for key in &cfg.filterable {
+            validate_key(key).map_err(|e| anyhow::anyhow!(e))?;
+        }
+        Ok(Self {
+            cfg: cfg.clone(),
+            keys: DashMap::new(),
+            filterable: RwLock::new(cfg.filterable.iter().cloned().collect()),
+        })
+    }
+
+    /// Flatten an entry's `meta` in place and record its keys.
+    pub fn process(&self, entry: &mut LogEntry) {
+        let Some(Value::Object(meta)) = entry.meta.take() else {
+            return;
+        };
+        let mut flat = Map::new();
+        flatten_into(&mut flat, None, meta, self.cfg.max_depth);
+
+        for (key, value) in &flat {
+            if let Some(mut stats) = self.keys.get_mut(key) {
+                stats.count += 1;
+                stats.types.insert(type_name(value));
+            } else if self.keys.len() < self.cfg.max_keys {
+                let mut stats = self.keys.entry(key.clone()).or_default();
+                stats.count += 1;
+                stats.types.insert(type_name(value));
+            }
+        }
+        entry.meta = Some(Value::Object(flat));
+    }
+
+    /// All known keys, plus filterable keys not seen since startup.
+    pub fn keys(&self) -> Vec<KeyInfo> {
+        let filterable = self.filterable.read().unwrap();
+        let mut keys: Vec<KeyInfo> = self
+            .keys
+            .iter()
+            .map(|k| KeyInfo {
+                key: k.key().clone(),
+                count: k.count,
+                types: k.types.clone(),
+                filterable: filterable.contains(k.key()),
+            })
+            .collect();
+        for key in filterable.iter() {
+            if !self.keys.contains_key(key) {
+                keys.push(KeyInfo {
+                    key: key.clone(),
+                    count: 0,
+                    types: BTreeSet::new(),
+                    filterable: true,
+                });
+            }
+        }
+        keys.sort_by(|a, b| a.key.cmp(&b.key));
+        keys
+    }
+
+    pub fn filterable(&self) -> BTreeSet<String> {
+        self.filterable.read().unwrap().clone()
+    }
+
+    /// Record keys as filterable. Returns the full filterable set.
+    pub fn promote<I: IntoIterator<Item = String>>(&self, keys: I) -> BTreeSet<String> {
+        let mut filterable = self.filterable.write().unwrap();
+        filterable.extend(keys);
+        filterable.clone()
+    }
+
+    /// Translate `meta.<key>` query parameters into filter clauses. Values
+    /// take an optional operator prefix: `!`, `>`, `>=`, `<`, `<=`; the
+    /// values `exists` and `!exists` test for presence.
+    pub fn filters(&self, params: &[(String, String)]) -> Result<Vec<Filter>, String> {
+        let filterable = self.filterable.read().unwrap();
+        let mut clauses = Vec::new();
+        for (name, value) in params {
+            let Some(key) = name.strip_prefix("meta.") else {
+                continue;
+            };
+            validate_key(key)?;
+            if !filterable.contains(key) {
+                return Err(format!(
+                    "meta.{} is not filterable; promote it with POST /admin/meta/promote",
+                    key
+                ));
+            }
+            clauses.push(filter_clause(key, value)?);
+        }
+        Ok(clauses)
+    }
+}
+
+/// Build one clause for `meta.<key>` from an operator-prefixed value.
+fn filter_clause(key: &str, value: &str) -> Result<Filter, String> {
+    let attr = format!("meta.{}", key);
+    match value {
+        "exists" => return Ok(Filter::exists(&attr)),
+        "!exists" => return Ok(Filter::exists(&attr).not()),
+        _ => {}
+    }
+
+    let (op, operand) = [
+        (">=", Op::Gte),
+        ("<=", Op::Lte),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+        ("!", Op::Ne),
+    ]
+    .iter()
+    .find_map(|(prefix, op)| value.strip_prefix(prefix).map(|rest| (*op, rest)))
+    .unwrap_or((Op::Eq, value));
+
+    match op {
+        Op::Eq | Op::Ne => Ok(Filter::cmp(&attr, op, operand)),
+        _ => {
+            let n: f64 = operand
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .ok_or_else(|| format!("meta.{}: {} needs a number", key, value))?;
+            Ok(Filter::cmp(&attr, op, n))
+        }
+    }
+}
+
+/// Keys must be valid filter field names once prefixed with `meta.`.
+pub fn validate_key(key: &str) -> Result<(), String> {
+    if filter::is_valid_field(&format!("meta.{}", key)) {
+        Ok(())
+    } else {
+        Err(format!("invalid meta key {:?}", key))
+    }
+}
+
+fn flatten_into(
+    out: &mut Map<String, Value>,
+    prefix: Option<&str>,
+    map: Map<String, Value>,
+    depth: usize,
+) {
+    for (key, value) in map {
+        let key = match prefix {
+            Some(p) => format!("{}.{}", p, key),
+            None => key,
+        };
+        match value {
+            Value::Object(inner) if depth > 1 && !inner.is_empty() => {
+                flatten_into(out, Some(&key), inner, depth - 1)
+            }
+            value => {
+                out.insert(key, value);
+            }
+        }
+    }
+}
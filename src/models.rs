@@ -1,5 +1,7 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::stacktrace::ErrorInfo;
 
 /// A single log entry — the core data model.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,14 +11,23 @@ pub struct LogEntry {
     #[serde(default = "generate_id")]
     pub id: String,
 
-    /// ISO 8601 timestamp
-    #[serde(default = "now")]
+    /// ISO 8601 timestamp. Epoch seconds/millis/nanos and common non-RFC
+    /// formats are accepted and converted by `normalize`.
+    #[serde(default = "now", deserialize_with = "deserialize_timestamp")]
     pub timestamp: String,
 
     /// Unix milliseconds (for Meilisearch numeric range filters)
     #[serde(default)]
     pub timestamp_ms: i64,
 
+    /// Unix milliseconds when the server received the entry (always server-set)
+    #[serde(default)]
+    pub received_at_ms: i64,
+
+    /// `timestampMs - receivedAtMs`, set when it exceeds the configured skew threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_skew_ms: Option<i64>,
+
     /// Project name: "frontend", "api-server", "auth-service", etc.
     #[serde(default = "default_project")]
     pub project: String,
@@ -25,6 +36,11 @@ pub struct LogEntry {
     #[serde(default = "default_level")]
     pub level: LogLevel,
 
+    /// Numeric severity of `level` (10 = trace … 60 = fatal), set at ingest
+    /// so the index can filter on ranges
+    #[serde(default)]
+    pub severity: u8,
+
     /// Log message
     #[serde(default)]
     pub message: String,
@@ -58,14 +74,41 @@ pub struct LogEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
 
+    /// Structured exception: type, message, raw stack and parsed frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorInfo>,
+
     /// Environment: dev, staging, prod
     #[serde(default = "default_env")]
     pub environment: String,
+
+    /// Error grouping key, set at ingest for error/fatal entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+
+    /// Message template id, set at ingest (see `patterns`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_id: Option<String>,
+
+    // --- Deduplication ---
+    /// Number of identical entries collapsed into this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_count: Option<u64>,
+
+    /// Timestamp of the first collapsed entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<String>,
+
+    /// Timestamp of the last collapsed entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Log levels, ordered from least to most severe.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
+    Trace,
     Debug,
     Info,
     Warn,
@@ -73,9 +116,99 @@ pub enum LogLevel {
     Fatal,
 }
 
+impl LogLevel {
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Trace,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+        LogLevel::Fatal,
+    ];
+
+    /// Numeric severity, using pino's scale.
+    pub fn severity(&self) -> u8 {
+        match self {
+            LogLevel::Trace => 10,
+            LogLevel::Debug => 20,
+            LogLevel::Info => 30,
+            LogLevel::Warn => 40,
+            LogLevel::Error => 50,
+            LogLevel::Fatal => 60,
+        }
+    }
+
+    /// Parse a comma-separated list of levels, e.g. `warn,error`.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| Self::parse(l).ok_or_else(|| format!("unknown level {:?}", l)))
+            .collect()
+    }
+
+    /// Parse a level name leniently: case-insensitive, common aliases
+    /// (`warning`, `err`, `critical`, ...), pino numeric levels (10–60) and
+    /// syslog severities (0–7). Returns `None` for anything unrecognized.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Ok(n) = s.parse::<f64>() {
+            return Self::from_number(n);
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "trace" | "trc" | "verbose" | "silly" | "finest" | "finer" => Some(LogLevel::Trace),
+            "debug" | "dbg" | "fine" => Some(LogLevel::Debug),
+            "info" | "inf" | "information" | "informational" | "notice" | "log" => {
+                Some(LogLevel::Info)
+            }
+            "warn" | "wrn" | "warning" => Some(LogLevel::Warn),
+            "error" | "err" | "eror" | "severe" => Some(LogLevel::Error),
+            "fatal" | "ftl" | "critical" | "crit" | "alert" | "emerg" | "emergency" | "panic" => {
+                Some(LogLevel::Fatal)
+            }
+            _ => None,
+        }
+    }
+
+    /// Map a numeric level: 0–7 are syslog severities, 10–60 pino levels.
+    fn from_number(n: f64) -> Option<Self> {
+        if !n.is_finite() || n < 0.0 {
+            return None;
+        }
+        let level = match n as u32 {
+            0..=2 => LogLevel::Fatal,
+            3 => LogLevel::Error,
+            4 => LogLevel::Warn,
+            5 | 6 => LogLevel::Info,
+            7 => LogLevel::Debug,
+            8..=14 => LogLevel::Trace,
+            15..=24 => LogLevel::Debug,
+            25..=34 => LogLevel::Info,
+            35..=44 => LogLevel::Warn,
+            45..=54 => LogLevel::Error,
+            _ => LogLevel::Fatal,
+        };
+        Some(level)
+    }
+}
+
+/// Levels never fail deserialization: unknown values fall back to `info`
+/// so one odd entry can't reject a whole batch.
+impl<'de> Deserialize<'de> for LogLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let level = match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(s) => LogLevel::parse(&s),
+            serde_json::Value::Number(n) => n.as_f64().and_then(LogLevel::from_number),
+            _ => None,
+        };
+        Ok(level.unwrap_or(LogLevel::Info))
+    }
+}
+
 impl std::fmt::Display for LogLevel {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            LogLevel::Trace => write!(f, "trace"),
             LogLevel::Debug => write!(f, "debug"),
             LogLevel::Info => write!(f, "info"),
             LogLevel::Warn => write!(f, "warn"),
@@ -86,39 +219,145 @@ impl std::fmt::Display for LogLevel {
 }
 
 impl LogEntry {
-    /// Normalize the entry: fill in defaults, compute timestamp_ms
+    /// Normalize the entry: fill in defaults, compute timestamp_ms, stamp
+    /// the receive time and clear fields only the server may set
     pub fn normalize(mut self) -> Self {
+        self.received_at_ms = Utc::now().timestamp_millis();
+        // Server-owned fields: never trust what the client sent
+        self.clock_skew_ms = None;
+        self.fingerprint = None;
+        self.repeat_count = None;
+        self.first_seen = None;
+        self.last_seen = None;
         if self.id.is_empty() {
             self.id = generate_id();
         }
         if self.timestamp.is_empty() {
             self.timestamp = now();
         }
-        // Compute timestamp_ms from the ISO string
-        if self.timestamp_ms == 0 {
-            self.timestamp_ms = self
-                .timestamp
-                .parse::<DateTime<Utc>>()
-                .map(|dt| dt.timestamp_millis())
-                .unwrap_or_else(|_| Utc::now().timestamp_millis());
+        // Compute timestamp_ms from the ISO string, coercing other formats
+        match self.timestamp.parse::<DateTime<Utc>>() {
+            Ok(dt) => {
+                if self.timestamp_ms == 0 {
+                    self.timestamp_ms = dt.timestamp_millis();
+                }
+            }
+            Err(_) => {
+                let original = std::mem::take(&mut self.timestamp);
+                let dt = parse_timestamp(&original).unwrap_or_else(Utc::now);
+                self.timestamp = dt.to_rfc3339();
+                if self.timestamp_ms == 0 {
+                    self.timestamp_ms = dt.timestamp_millis();
+                }
+                self.set_meta("originalTimestamp", original.into());
+            }
+        }
+        self.severity = self.level.severity();
+        if let Some(error) = self.error.as_ref() {
+            if self.message.is_empty() {
+                self.message = error.message.clone().unwrap_or_default();
+            }
         }
         // Serialize meta to a string representation for full-text search
         self
     }
+
+    /// Insert a key into `meta`, creating the object if needed. Non-object
+    /// meta values are left untouched.
+    pub fn set_meta(&mut self, key: &str, value: serde_json::Value) {
+        match self.meta {
+            Some(serde_json::Value::Object(ref mut map)) => {
+                map.insert(key.to_string(), value);
+            }
+            None => {
+                let mut map = serde_json::Map::new();
+                map.insert(key.to_string(), value);
+                self.meta = Some(serde_json::Value::Object(map));
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Accept timestamps as strings or bare numbers (epoch values).
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => s,
+        serde_json::Value::Number(n) => n.to_string(),
+        _ => String::new(),
+    })
+}
+
+/// Formats tried (as UTC unless they carry an offset) when a timestamp isn't
+/// RFC 3339.
+const NAIVE_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%d/%b/%Y:%H:%M:%S",
+];
+
+const OFFSET_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%d/%b/%Y:%H:%M:%S %z",
+];
+
+/// Parse a non-RFC 3339 timestamp: epoch seconds, millis, micros or nanos
+/// (chosen by magnitude), RFC 2822, common `strftime` layouts, or syslog's
+/// `Jan  2 15:04:05` (current year assumed).
+pub fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let s = raw.trim();
+    if let Ok(dt) = s.parse::<DateTime<Utc>>() {
+        return Some(dt);
+    }
+    if let Some(n) = s.parse::<f64>().ok().filter(|n| n.is_finite()) {
+        let abs = n.abs();
+        let ms = if abs < 1e11 {
+            n * 1e3
+        } else if abs < 1e14 {
+            n
+        } else if abs < 1e17 {
+            n / 1e3
+        } else {
+            n / 1e6
+        };
+        return DateTime::<Utc>::from_timestamp_millis(ms as i64);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(s) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    for fmt in OFFSET_FORMATS {
+        if let Ok(dt) = DateTime::parse_from_str(s, fmt) {
+            return Some(dt.with_timezone(&Utc));
+        }
+    }
+    for fmt in NAIVE_FORMATS {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(Utc.from_utc_datetime(&dt));
+        }
+    }
+    // Syslog (RFC 3164) has no year
+    let with_year = format!("{} {}", Utc::now().format("%Y"), s);
+    NaiveDateTime::parse_from_str(&with_year, "%Y %b %e %H:%M:%S")
+        .ok()
+        .map(|dt| Utc.from_utc_datetime(&dt))
 }
 
 /// Incoming payload: single entry or batch
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 pub enum IngestPayload {
-    Single(LogEntry),
+    Single(Box<LogEntry>),
     Batch(Vec<LogEntry>),
 }
 
 impl IngestPayload {
     pub fn into_entries(self) -> Vec<LogEntry> {
         match self {
-            IngestPayload::Single(e) => vec![e],
+            IngestPayload::Single(e) => vec![*e],
             IngestPayload::Batch(v) => v,
         }
     }
@@ -128,15 +367,181 @@ impl IngestPayload {
 #[derive(Debug, Deserialize)]
 pub struct SearchParams {
     pub q: Option<String>,
+    /// Query language expression, e.g. `project:api level:>=warn "db timeout"`
+    pub query: Option<String>,
     pub project: Option<String>,
+    /// One level or a comma-separated list, e.g. `warn,error`
     pub level: Option<String>,
+    /// Only entries at least this severe, e.g. `warn`
+    #[serde(rename = "minLevel")]
+    pub min_level: Option<String>,
     pub trace_id: Option<String>,
     pub request_id: Option<String>,
     pub environment: Option<String>,
+    /// Time window ending now, e.g. `1h`, `1h30m`
+    pub since: Option<String>,
+    /// Start of the range: RFC 3339, epoch millis, `now-2h`, `yesterday`, ...
+    pub from: Option<String>,
+    /// End of the range, same syntax as `from`
+    pub to: Option<String>,
+    pub limit: Option<usize>,
+    /// Sort by `timestamp` (client time, default) or `received` (server time)
+    pub sort: Option<String>,
+    /// `nextCursor` or `prevCursor` from a previous page
+    pub cursor: Option<String>,
+    /// Run a saved search; the other parameters narrow or override it
+    pub saved: Option<String>,
+}
+
+/// Query parameters for the /issues endpoint
+#[derive(Debug, Deserialize)]
+pub struct IssueParams {
+    pub project: Option<String>,
+    /// `unresolved`, `resolved` or `ignored`
+    pub status: Option<crate::issues::IssueStatus>,
+    /// Only issues seen within this window (e.g. 1h, 7d)
     pub since: Option<String>,
+    /// Start of the range: RFC 3339, epoch millis, `now-2h`, `yesterday`, ...
+    pub from: Option<String>,
+    /// End of the range, same syntax as `from`
+    pub to: Option<String>,
+    /// `lastSeen` (default), `firstSeen` or `count`
+    pub sort: Option<String>,
     pub limit: Option<usize>,
 }
 
+/// Query parameters for the /fields endpoint
+#[derive(Debug, Deserialize)]
+pub struct FieldsParams {
+    pub project: Option<String>,
+    /// Only entries within this window (e.g. 1h, 7d)
+    pub since: Option<String>,
+    /// Start of the range: RFC 3339, epoch millis, `now-2h`, `yesterday`, ...
+    pub from: Option<String>,
+    /// End of the range, same syntax as `from`
+    pub to: Option<String>,
+    /// Number of recent entries to sample (default 1000, max 5000)
+    pub sample: Option<usize>,
+}
+
+/// Query parameters for the /export endpoint, alongside [`SearchParams`]
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: crate::export::ExportFormat,
+    /// Comma-separated CSV columns, e.g. `timestamp,level,meta.http.status`
+    pub fields: Option<String>,
+}
+
+/// Query parameters for the /histogram endpoint, alongside [`SearchParams`]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramParams {
+    /// Bucket width, e.g. `1m`; picked from the range when omitted
+    pub interval: Option<String>,
+    /// Split counts by `level` or `project`
+    pub group_by: Option<String>,
+}
+
+/// Query parameters for the /stats endpoint, alongside [`SearchParams`]
+#[derive(Debug, Deserialize)]
+pub struct StatsParams {
+    /// Field path, e.g. `meta.path` or `meta.durationMs`
+    pub field: Option<String>,
+    /// Comma-separated aggregations: `top` (default), `count_distinct`,
+    /// `min`, `max`, `avg`, `p50`, `p95`, `p99`
+    pub agg: Option<String>,
+    /// Values returned by `top` (default 10)
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for the /diff endpoint, alongside [`SearchParams`]
+#[derive(Debug, Deserialize)]
+pub struct DiffParams {
+    /// Window compared against, e.g. `now-2h..now-1h`; defaults to the
+    /// window of the same length just before `target`
+    pub baseline: Option<String>,
+    /// Window under investigation (default `now-1h..now`)
+    pub target: Option<String>,
+    /// Entries per list (default 20, max 200)
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for the /patterns endpoint, alongside [`SearchParams`]
+#[derive(Debug, Deserialize)]
+pub struct PatternsParams {
+    /// Templates returned (default 50, max 1000)
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for the /logs endpoint
+#[derive(Debug, Deserialize)]
+pub struct LogsParams {
+    /// Comma-separated entry ids
+    pub ids: Option<String>,
+}
+
+/// Query parameters for the /logs/{id}/context endpoint
+#[derive(Debug, Deserialize)]
+pub struct ContextParams {
+    /// Entries before the anchor (default 50, max 500)
+    pub before: Option<usize>,
+    /// Entries after the anchor (default 50, max 500)
+    pub after: Option<usize>,
+    /// `project` (default), `request`, `trace` or `source`
+    #[serde(default)]
+    pub scope: crate::context::Scope,
+}
+
+/// Query parameters for the /saved-searches endpoint
+#[derive(Debug, Deserialize)]
+pub struct SavedSearchParams {
+    /// Only searches saved by this owner
+    pub owner: Option<String>,
+}
+
+/// Body of `POST /admin/meta/promote`
+#[derive(Debug, Deserialize)]
+pub struct PromoteMeta {
+    /// Flattened keys, with or without the `meta.` prefix
+    pub keys: Vec<String>,
+}
+
+/// Query parameters for the /trace and /request timelines
+#[derive(Debug, Default, Deserialize)]
+pub struct TimelineParams {
+    /// Sort by `timestamp` (default) or `received`
+    pub sort: Option<String>,
+}
+
+/// Query parameters for the /traces endpoint
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TracesParams {
+    /// Only traces with entries from this project
+    pub project: Option<String>,
+    /// Only traces at least this long, e.g. `2s`
+    pub min_duration: Option<String>,
+    /// Only traces with (or without) errors
+    pub has_error: Option<bool>,
+    /// Only traces overlapping this window (e.g. 1h, 7d)
+    pub since: Option<String>,
+    /// Start of the range: RFC 3339, epoch millis, `now-2h`, `yesterday`, ...
+    pub from: Option<String>,
+    /// End of the range, same syntax as `from`
+    pub to: Option<String>,
+    /// `recent` (default), `duration` or `errors`
+    pub sort: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Query parameters for the /trace timeline, alongside [`TimelineParams`]
+#[derive(Debug, Default, Deserialize)]
+pub struct TraceParams {
+    /// `timeline` (default) or `tree` for the span hierarchy
+    pub view: Option<String>,
+}
+
 // --- Defaults ---
 
 fn generate_id() -> String {
@@ -162,3 +567,131 @@ fn default_level() -> LogLevel {
 fn default_env() -> String {
     "dev".into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch_magnitudes() {
+        for (raw, ms) in [
+            // Seconds, below 1e11
+            ("0", 0),
+            ("-1", -1_000),
+            ("1700000000", 1_700_000_000_000),
+            ("1700000000.5", 1_700_000_000_500),
+            ("99999999999", 99_999_999_999_000),
+            // Milliseconds, from 1e11
+            ("100000000000", 100_000_000_000),
+            ("1700000000123", 1_700_000_000_123),
+            ("99999999999999", 99_999_999_999_999),
+            // Microseconds, from 1e14
+            ("100000000000000", 100_000_000_000),
+            ("1700000000123456", 1_700_000_000_123),
+            // Largest f64 below 1e17
+            ("99999999999999984", 99_999_999_999_999),
+            // Nanoseconds, from 1e17
+            ("100000000000000000", 100_000_000_000),
+            ("1700000000123456789", 1_700_000_000_123),
+            (" 1700000000 ", 1_700_000_000_000),
+        ] {
+            let parsed = parse_timestamp(raw).map(|dt| dt.timestamp_millis());
+            assert_eq!(parsed, Some(ms), "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn timestamp_layouts() {
+        let expected = Some(1_700_000_000_000);
+        for raw in [
+            "2023-11-14T22:13:20Z",
+            "2023-11-14T23:13:20+01:00",
+            "Tue, 14 Nov 2023 22:13:20 +0000",
+            "2023-11-14 22:13:20",
+            "2023-11-14T22:13:20.000",
+            "2023/11/14 22:13:20",
+            "2023-11-14 23:13:20 +0100",
+            "14/Nov/2023:22:13:20 +0000",
+        ] {
+            let parsed = parse_timestamp(raw).map(|dt| dt.timestamp_millis());
+            assert_eq!(parsed, expected, "{raw:?}");
+        }
+        for raw in ["", "soon", "2023-13-01 00:00:00", "NaN", "inf"] {
+            assert_eq!(parse_timestamp(raw), None, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn level_aliases() {
+        use LogLevel::*;
+        for (raw, level) in [
+            ("trace", Trace),
+            ("VERBOSE", Trace),
+            ("silly", Trace),
+            ("finer", Trace),
+            ("debug", Debug),
+            ("dbg", Debug),
+            ("fine", Debug),
+            ("info", Info),
+            ("Information", Info),
+            ("notice", Info),
+            ("log", Info),
+            (" warn ", Warn),
+            ("WARNING", Warn),
+            ("wrn", Warn),
+            ("error", Error),
+            ("err", Error),
+            ("severe", Error),
+            ("fatal", Fatal),
+            ("critical", Fatal),
+            ("emerg", Fatal),
+            ("panic", Fatal),
+        ] {
+            assert_eq!(LogLevel::parse(raw), Some(level), "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn numeric_levels() {
+        use LogLevel::*;
+        for (raw, level) in [
+            // Syslog severities
+            ("0", Fatal),
+            ("2", Fatal),
+            ("3", Error),
+            ("4", Warn),
+            ("5", Info),
+            ("6", Info),
+            ("7", Debug),
+            // Pino levels and the ranges around them
+            ("8", Trace),
+            ("10", Trace),
+            ("14", Trace),
+            ("15", Debug),
+            ("20", Debug),
+            ("30", Info),
+            ("34.9", Info),
+            ("35", Warn),
+            ("40", Warn),
+            ("50", Error),
+            ("54", Error),
+            ("55", Fatal),
+            ("60", Fatal),
+            ("1000", Fatal),
+        ] {
+            assert_eq!(LogLevel::parse(raw), Some(level), "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn unknown_levels() {
+        for raw in ["", "loud", "warnings", "e", "-1", "NaN", "inf"] {
+            assert_eq!(LogLevel::parse(raw), None, "{raw:?}");
+        }
+        assert_eq!(
+            LogLevel::parse_list("warn, error,"),
+            Ok(vec![LogLevel::Warn, LogLevel::Error])
+        );
+        assert!(LogLevel::parse_list("warn,loud").is_err());
+    }
+}
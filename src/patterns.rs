@@ -0,0 +1,499 @@
+//! Log template mining with Drain.
+//!
+//! Messages are split on whitespace and tokens containing digits are masked.
+//! A small prefix tree routes each message by project, token count and its
+//! first tokens to a handful of templates; the most similar one absorbs the
+//! message when enough tokens match (a masked token matches a `<*>`),
+//! turning the positions that differ into `<*>`, and otherwise the message
+//! starts a new template. Every entry gets the `patternId` of its template.
+//! Templates are kept in memory for ingest and persisted to the `patterns`
+//! Meilisearch index.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use meilisearch_sdk::client::Client;
+use meilisearch_sdk::documents::DocumentsQuery;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::meili::{MAX_VALUES_PER_FACET, PATTERNS_INDEX};
+use crate::models::LogEntry;
+
+/// Template token standing for a variable part.
+pub const WILDCARD: &str = "<*>";
+
+/// `patterns` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PatternConfig {
+    pub enabled: bool,
+    /// Share of a message's tokens that must equal a template's for the
+    /// message to join it
+    pub similarity: f64,
+    /// Leading tokens used to route messages before comparing templates
+    pub depth: usize,
+    /// Distinct tokens per tree level; more share one `<*>` branch
+    pub max_children: usize,
+    /// Templates kept at most; messages that would start another get no pattern
+    pub max_patterns: usize,
+    /// Messages with more tokens get no pattern
+    pub max_tokens: usize,
+}
+
+impl Default for PatternConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            similarity: 0.5,
+            depth: 2,
+            max_children: 100,
+            max_patterns: 10_000,
+            max_tokens: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pattern {
+    pub id: String,
+    pub project: String,
+    /// e.g. `User <*> logged in from <*>`
+    pub template: String,
+    /// Entries seen at ingest, including ones sampled out afterwards
+    pub seen: u64,
+    pub first_seen_ms: i64,
+    pub last_seen_ms: i64,
+    /// Most recent message
+    pub sample: String,
+}
+
+#[derive(Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    /// Indices into `Miner::clusters`
+    clusters: Vec<usize>,
+}
+
+struct Cluster {
+    pattern: Pattern,
+    tokens: Vec<String>,
+}
+
+#[derive(Default)]
+struct Miner {
+    /// Trees by project and token count
+    roots: HashMap<(String, usize), Node>,
+    clusters: Vec<Cluster>,
+    by_id: HashMap<String, usize>,
+    /// Clusters changed since the last flush to Meilisearch
+    dirty: HashSet<usize>,
+}
+
+pub struct PatternMiner {
+    cfg: PatternConfig,
+    miner: Mutex<Miner>,
+}
+
+impl PatternMiner {
+    pub fn new(cfg: &PatternConfig) -> anyhow::Result<Self> {
+        if !(0.0..=1.0).contains(&cfg.similarity) {
+            anyhow::bail!(
+                "pattern similarity must be between 0 and 1, got {}",
+                cfg.similarity
+            );
+        }
+        if cfg.max_children < 2 {
+            anyhow::bail!("pattern maxChildren must be at least 2");
+        }
+        Ok(Self {
+            cfg: cfg.clone(),
+            miner: Mutex::new(Miner::default()),
+        })
+    }
+
+    /// Load persisted templates from Meilisearch.
+    pub async fn load(&self, client: &Client) -> anyhow::Result<()> {
+        let index = client.index(PATTERNS_INDEX);
+        let mut patterns = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = DocumentsQuery::new(&index)
+                .with_offset(offset)
+                .with_limit(1000)
+                .execute::<Pattern>()
+                .await?;
+            let fetched = page.results.len();
+            patterns.extend(page.results);
+            offset += fetched;
+            if fetched == 0 || offset >= page.total as usize {
+                break;
+            }
+        }
+        let mut miner = self.miner.lock().unwrap();
+        for pattern in patterns {
+            let tokens: Vec<String> = pattern
+                .template
+                .split_whitespace()
+                .map(String::from)
+                .collect();
+            miner.insert(&self.cfg, pattern, tokens);
+        }
+        tracing::info!("Loaded {} patterns", miner.clusters.len());
+        Ok(())
+    }
+
+    /// Find or start the template of an entry's message and set `patternId`.
+    /// Any `patternId` the client sent is cleared first.
+    pub fn assign(&self, entry: &mut LogEntry) {
+        entry.pattern_id = None;
+        if !self.cfg.enabled {
+            return;
+        }
+        let tokens = tokenize(&entry.message);
+        if tokens.is_empty() || tokens.len() > self.cfg.max_tokens {
+            return;
+        }
+
+        let mut miner = self.miner.lock().unwrap();
+        let idx = match miner.best_match(&self.cfg, &entry.project, &tokens) {
+            Some(idx) => {
+                let cluster = &mut miner.clusters[idx];
+                for (t, m) in cluster.tokens.iter_mut().zip(&tokens) {
+                    if t != m {
+                        *t = WILDCARD.to_string();
+                    }
+                }
+                let pattern = &mut cluster.pattern;
+                pattern.template = cluster.tokens.join(" ");
+                pattern.seen += 1;
+                pattern.first_seen_ms = pattern.first_seen_ms.min(entry.timestamp_ms);
+                if entry.timestamp_ms >= pattern.last_seen_ms {
+                    pattern.last_seen_ms = entry.timestamp_ms;
+                    pattern.sample = entry.message.clone();
+                }
+                idx
+            }
+            None if miner.clusters.len() >= self.cfg.max_patterns => return,
+            None => {
+                let template = tokens.join(" ");
+                let pattern = Pattern {
+                    id: miner.new_id(&entry.project, &template),
+                    project: entry.project.clone(),
+                    template,
+                    seen: 1,
+                    first_seen_ms: entry.timestamp_ms,
+                    last_seen_ms: entry.timestamp_ms,
+                    sample: entry.message.clone(),
+                };
+                miner.insert(&self.cfg, pattern, tokens)
+            }
+        };
+        miner.dirty.insert(idx);
+        entry.pattern_id = Some(miner.clusters[idx].pattern.id.clone());
+    }
+
+    /// Templates changed since the last call.
+    pub fn take_dirty(&self) -> Vec<Pattern> {
+        let mut miner = self.miner.lock().unwrap();
+        let dirty = std::mem::take(&mut miner.dirty);
+        dirty
+            .into_iter()
+            .map(|idx| miner.clusters[idx].pattern.clone())
+            .collect()
+    }
+
+    /// Mark templates changed again, e.g. after a failed persist.
+    pub fn mark_dirty(&self, patterns: &[Pattern]) {
+        let mut miner = self.miner.lock().unwrap();
+        for pattern in patterns {
+            if let Some(&idx) = miner.by_id.get(&pattern.id) {
+                miner.dirty.insert(idx);
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Pattern> {
+        let miner = self.miner.lock().unwrap();
+        let idx = *miner.by_id.get(id)?;
+        Some(miner.clusters[idx].pattern.clone())
+    }
+}
+
+impl Miner {
+    /// Most similar template in the message's leaf, if similar enough.
+    fn best_match(&self, cfg: &PatternConfig, project: &str, tokens: &[String]) -> Option<usize> {
+        let mut node = self.roots.get(&(project.to_string(), tokens.len()))?;
+        for token in tokens.iter().take(cfg.depth) {
+            node = node
+                .children
+                .get(token)
+                .or_else(|| node.children.get(WILDCARD))?;
+        }
+        node.clusters
+            .iter()
+            .map(|&idx| {
+                let template = &self.clusters[idx].tokens;
+                let (same, wildcards) =
+                    template
+                        .iter()
+                        .zip(tokens)
+                        .fold((0, 0), |(same, wild), (t, m)| match t.as_str() {
+                            // Both masked: the message has a variable here too
+                            WILDCARD if m == WILDCARD => (same + 1, wild + 1),
+                            WILDCARD => (same, wild + 1),
+                            _ if t == m => (same + 1, wild),
+                            _ => (same, wild),
+                        });
+                (idx, same as f64 / tokens.len() as f64, wildcards)
+            })
+            .filter(|(_, similarity, _)| *similarity >= cfg.similarity)
+            // Most similar, then the most general
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(a.2.cmp(&b.2)))
+            .map(|(idx, _, _)| idx)
+    }
+
+    /// Add a template to the tree, creating its path. Returns its index.
+    fn insert(&mut self, cfg: &PatternConfig, pattern: Pattern, tokens: Vec<String>) -> usize {
+        let idx = self.clusters.len();
+        let mut node = self
+            .roots
+            .entry((pattern.project.clone(), tokens.len()))
+            .or_default();
+        for token in tokens.iter().take(cfg.depth) {
+            let key = if node.children.contains_key(token)
+                || node.children.len() + 1 < cfg.max_children
+            {
+                token.as_str()
+            } else {
+                WILDCARD
+            };
+            node = node.children.entry(key.to_string()).or_default();
+        }
+        node.clusters.push(idx);
+        self.by_id.insert(pattern.id.clone(), idx);
+        self.clusters.push(Cluster { pattern, tokens });
+        idx
+    }
+
+    /// Id for a new template, from its project and first form.
+    fn new_id(&self, project: &str, template: &str) -> String {
+        (0u32..)
+            .map(|attempt| {
+                let mut hasher = Sha256::new();
+                hasher.update(project.as_bytes());
+                hasher.update([0]);
+                hasher.update(template.as_bytes());
+                if attempt > 0 {
+                    hasher.update(attempt.to_be_bytes());
+                }
+                let digest = hasher.finalize();
+                digest[..6].iter().map(|b| format!("{:02x}", b)).collect()
+            })
+            .find(|id: &String| !self.by_id.contains_key(id))
+            .unwrap_or_default()
+    }
+}
+
+/// Whitespace-separated tokens, with any token containing a digit masked.
+fn tokenize(message: &str) -> Vec<String> {
+    message
+        .split_whitespace()
+        .map(|t| {
+            if t.bytes().any(|b| b.is_ascii_digit()) {
+                WILDCARD.to_string()
+            } else {
+                t.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Write changed templates to Meilisearch. Returns `false` if the write
+/// failed.
+pub async fn persist(client: &Client, patterns: &[Pattern]) -> bool {
+    if patterns.is_empty() {
+        return true;
+    }
+    let index = client.index(PATTERNS_INDEX);
+    match index.add_documents(patterns, Some("id")).await {
+        Ok(_task) => {
+            tracing::debug!("Persisted {} patterns", patterns.len());
+            true
+        }
+        Err(e) => {
+            tracing::error!("Pattern persist error: {:?}", e);
+            false
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PatternCount {
+    /// Matching entries with this pattern
+    pub count: usize,
+    #[serde(flatten)]
+    pub pattern: Pattern,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PatternCounts {
+    /// Matching entries
+    pub total: usize,
+    /// Most frequent first
+    pub patterns: Vec<PatternCount>,
+    /// True when Meilisearch returned as many patterns as a facet holds, so
+    /// rarer ones may be missing
+    pub truncated: bool,
+}
+
+/// Templates of the entries matching `text` and `filter`, with how many of
+/// those entries each one has.
+pub async fn counts(
+    index: &Index,
+    text: &str,
+    filter: Option<&str>,
+    miner: &PatternMiner,
+    limit: usize,
+) -> Result<PatternCounts, meilisearch_sdk::errors::Error> {
+    let mut search = index.search();
+    search.with_query(text);
+    search.with_limit(0);
+    search.with_facets(Selectors::Some(&["patternId", "level"]));
+    if let Some(f) = filter {
+        search.with_filter(f);
+    }
+    let mut distribution = search
+        .execute::<Value>()
+        .await?
+        .facet_distribution
+        .unwrap_or_default();
+    let total = distribution
+        .get("level")
+        .map_or(0, |levels| levels.values().sum());
+    let ids = distribution.remove("patternId").unwrap_or_default();
+    let truncated = ids.len() >= MAX_VALUES_PER_FACET;
+
+    let mut patterns: Vec<PatternCount> = ids
+        .into_iter()
+        .filter_map(|(id, count)| {
+            Some(PatternCount {
+                count,
+                pattern: miner.get(&id)?,
+            })
+        })
+        .collect();
+    patterns.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.pattern.id.cmp(&b.pattern.id))
+    });
+    patterns.truncate(limit);
+    Ok(PatternCounts {
+        total,
+        patterns,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(project: &str, message: &str) -> LogEntry {
+        serde_json::from_value(serde_json::json!({ "project": project, "message": message }))
+            .unwrap()
+    }
+
+    fn assign(miner: &PatternMiner, project: &str, message: &str) -> String {
+        let mut entry = entry(project, message);
+        miner.assign(&mut entry);
+        entry.pattern_id.expect("no pattern assigned")
+    }
+
+    #[test]
+    fn dirty_templates_can_be_marked_again() {
+        let miner = PatternMiner::new(&PatternConfig::default()).unwrap();
+        assign(&miner, "api", "Connection closed by peer");
+        let dirty = miner.take_dirty();
+        assert_eq!(dirty.len(), 1);
+        assert!(miner.take_dirty().is_empty());
+        // A failed persist puts them back for the next one
+        miner.mark_dirty(&dirty);
+        assert_eq!(miner.take_dirty()[0].id, dirty[0].id);
+    }
+
+    #[test]
+    fn numeric_messages_share_a_pattern() {
+        let miner = PatternMiner::new(&PatternConfig::default()).unwrap();
+        let first = assign(&miner, "api", "GET /api/users/123 200 15ms");
+        for message in [
+            "GET /api/users/123 200 15ms",
+            "GET /api/users/456 404 3ms",
+            "GET /api/orders/7 500 1200ms",
+        ] {
+            assert_eq!(assign(&miner, "api", message), first, "{message:?}");
+        }
+        let pattern = miner.get(&first).unwrap();
+        assert_eq!(pattern.template, "GET <*> <*> <*>");
+        assert_eq!(pattern.seen, 4);
+        assert_eq!(pattern.sample, "GET /api/orders/7 500 1200ms");
+    }
+
+    #[test]
+    fn differing_tokens_become_wildcards() {
+        let miner = PatternMiner::new(&PatternConfig::default()).unwrap();
+        let id = assign(&miner, "api", "User 1 logged in");
+        assert_eq!(assign(&miner, "api", "User 2 logged in"), id);
+        assert_eq!(miner.get(&id).unwrap().template, "User <*> logged in");
+
+        let id = assign(&miner, "api", "Connection closed by alice");
+        assert_eq!(assign(&miner, "api", "Connection closed by bob"), id);
+        assert_eq!(miner.get(&id).unwrap().template, "Connection closed by <*>");
+    }
+
+    #[test]
+    fn dissimilar_messages_and_projects_are_separate() {
+        let miner = PatternMiner::new(&PatternConfig::default()).unwrap();
+        let login = assign(&miner, "api", "User 1 logged in");
+        assert_ne!(
+            assign(&miner, "api", "User 1 logged out of everything"),
+            login
+        );
+        assert_ne!(assign(&miner, "api", "User cache was flushed"), login);
+        assert_ne!(assign(&miner, "web", "User 1 logged in"), login);
+    }
+
+    #[test]
+    fn client_pattern_id_is_replaced() {
+        let miner = PatternMiner::new(&PatternConfig {
+            enabled: false,
+            ..PatternConfig::default()
+        })
+        .unwrap();
+        let mut entry = entry("api", "User 1 logged in");
+        entry.pattern_id = Some("forged".into());
+        miner.assign(&mut entry);
+        assert_eq!(entry.pattern_id, None);
+    }
+
+    #[test]
+    fn stops_at_max_patterns() {
+        let miner = PatternMiner::new(&PatternConfig {
+            max_patterns: 2,
+            ..PatternConfig::default()
+        })
+        .unwrap();
+        let first = assign(&miner, "api", "cache warmed");
+        assign(&miner, "api", "queue drained");
+        let mut entry = entry("api", "socket reset by peer");
+        miner.assign(&mut entry);
+        assert_eq!(entry.pattern_id, None);
+        // Existing templates still match
+        assert_eq!(assign(&miner, "api", "cache warmed"), first);
+    }
+}
@@ -0,0 +1,150 @@
+//! Ingest pipeline shared by the HTTP and WebSocket ingest paths.
+//!
+//! Every entry goes through the same stages before it reaches live
+//! subscribers or the Meilisearch batcher.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::PipelineConfig;
+use crate::dedup::Deduplicator;
+use crate::issues::IssueTracker;
+use crate::meta::MetaRegistry;
+use crate::models::LogEntry;
+use crate::patterns::PatternMiner;
+use crate::redact::Redactor;
+use crate::sampling::{Decision, Sampler};
+use crate::traces::TraceTracker;
+
+/// What to do with entries whose timestamp is too far from the receive time.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SkewAction {
+    /// Only record `clockSkewMs`
+    #[default]
+    Flag,
+    /// Record `clockSkewMs` and reset future timestamps to the receive time
+    Clamp,
+}
+
+/// `clockSkew` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClockSkewConfig {
+    /// Skew (either direction) above which an entry is flagged
+    pub max_skew_secs: u64,
+    pub action: SkewAction,
+}
+
+impl Default for ClockSkewConfig {
+    fn default() -> Self {
+        Self {
+            max_skew_secs: 300,
+            action: SkewAction::Flag,
+        }
+    }
+}
+
+pub struct IngestPipeline {
+    clock_skew: ClockSkewConfig,
+    redactor: Redactor,
+    pub sampler: Sampler,
+    pub dedup: Deduplicator,
+    pub issues: IssueTracker,
+    pub patterns: PatternMiner,
+    pub traces: TraceTracker,
+    pub meta: MetaRegistry,
+}
+
+/// Outcome of one ingest request.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestSummary {
+    pub accepted: usize,
+    pub sampled: usize,
+    pub rate_limited: usize,
+    pub deduplicated: usize,
+}
+
+impl IngestPipeline {
+    pub fn new(cfg: &PipelineConfig) -> anyhow::Result<Self> {
+        let redactor = Redactor::new(&cfg.redaction)?;
+        if !redactor.is_empty() {
+            tracing::info!("Redaction enabled ({} rules)", cfg.redaction.rules.len());
+        }
+        let sampler = Sampler::new(&cfg.sampling)?;
+        let dedup = Deduplicator::new(&cfg.dedup);
+        Ok(Self {
+            clock_skew: cfg.clock_skew.clone(),
+            redactor,
+            sampler,
+            dedup,
+            issues: IssueTracker::new(),
+            patterns: PatternMiner::new(&cfg.patterns)?,
+            traces: TraceTracker::new(),
+            meta: MetaRegistry::new(&cfg.meta)?,
+        })
+    }
+
+    /// Run a single entry through all stages. Returns `None` if the entry
+    /// was sampled out, rate limited or collapsed into an earlier repeat.
+    pub fn process(&self, entry: LogEntry, summary: &mut IngestSummary) -> Option<LogEntry> {
+        let mut entry = entry.normalize();
+        self.check_clock_skew(&mut entry);
+
+        // Redact before parsing stack frames and mining templates, so
+        // secrets never reach either, and mine before sampling, so rules can
+        // match the pattern
+        self.redactor.redact(&mut entry);
+        if let Some(error) = entry.error.as_mut() {
+            error.parse_frames();
+        }
+        self.patterns.assign(&mut entry);
+
+        match self.sampler.check(&entry) {
+            Decision::Keep => summary.accepted += 1,
+            Decision::Sampled => {
+                summary.sampled += 1;
+                return None;
+            }
+            Decision::RateLimited => {
+                summary.rate_limited += 1;
+                return None;
+            }
+        }
+
+        // Count every occurrence in its issue and trace, even ones dedup
+        // collapses
+        self.issues.record(&mut entry);
+        self.traces.record(&entry);
+
+        self.meta.process(&mut entry);
+
+        if self.dedup.check(&entry) {
+            summary.accepted -= 1;
+            summary.deduplicated += 1;
+            return None;
+        }
+
+        Some(entry)
+    }
+
+    /// Flag (and optionally clamp) entries whose client timestamp is too far
+    /// from the server receive time. Only future timestamps are clamped;
+    /// past ones are usually legitimately buffered deliveries.
+    fn check_clock_skew(&self, entry: &mut LogEntry) {
+        let skew = entry.timestamp_ms - entry.received_at_ms;
+        if skew.unsigned_abs() <= self.clock_skew.max_skew_secs * 1000 {
+            return;
+        }
+        entry.clock_skew_ms = Some(skew);
+        if self.clock_skew.action == SkewAction::Clamp && skew > 0 {
+            let original = std::mem::take(&mut entry.timestamp);
+            entry.set_meta("originalTimestamp", original.into());
+            entry.timestamp_ms = entry.received_at_ms;
+            entry.timestamp =
+                chrono::DateTime::<chrono::Utc>::from_timestamp_millis(entry.received_at_ms)
+                    .unwrap_or_else(chrono::Utc::now)
+                    .to_rfc3339();
+        }
+    }
+}
@@ -0,0 +1,984 @@
+//! Log query language.
+//!
+//! ```text
+//! project:api-server AND level:>=warn AND NOT meta.path:/health AND "db timeout"
+//! ```
+//!
+//! - `field:value` matches a field; `field:!value` excludes it, `field:>n`,
+//!   `>=`, `<`, `<=` compare numbers (or levels), `field:*` tests presence
+//! - bare words and `"quoted phrases"` are full-text terms
+//! - `AND` (implied between terms), `OR`, `NOT` or a leading `-`, parentheses
+//! - `since:1h` restricts to recent entries
+//!
+//! A query parses into an [`Expr`], which compiles to a Meilisearch filter
+//! plus full-text query for `/search`, or is evaluated directly against
+//! entries for live subscriptions.
+
+use serde_json::Value;
+
+use crate::filter::{self, Filter};
+use crate::models::{LogEntry, LogLevel};
+use crate::timerange;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        field: String,
+        op: CmpOp,
+        value: String,
+    },
+    Exists(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl CmpOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "!=",
+            CmpOp::Gt => ">",
+            CmpOp::Gte => ">=",
+            CmpOp::Lt => "<",
+            CmpOp::Lte => "<=",
+        }
+    }
+
+    fn to_filter(self) -> filter::Op {
+        match self {
+            CmpOp::Eq => filter::Op::Eq,
+            CmpOp::Ne => filter::Op::Ne,
+            CmpOp::Gt => filter::Op::Gt,
+            CmpOp::Gte => filter::Op::Gte,
+            CmpOp::Lt => filter::Op::Lt,
+            CmpOp::Lte => filter::Op::Lte,
+        }
+    }
+
+    fn holds(self, ord: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::*;
+        match self {
+            CmpOp::Eq => ord == Equal,
+            CmpOp::Ne => ord != Equal,
+            CmpOp::Gt => ord == Greater,
+            CmpOp::Gte => ord != Less,
+            CmpOp::Lt => ord == Less,
+            CmpOp::Lte => ord != Greater,
+        }
+    }
+}
+
+/// A syntax error with the byte offset where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn error<T>(message: impl Into<String>, position: usize) -> Result<T, ParseError> {
+    Err(ParseError {
+        message: message.into(),
+        position,
+    })
+}
+
+// ─── Lexer ───
+
+/// Quote `value` as a phrase the lexer reads back unchanged.
+pub fn quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    start: usize,
+    end: usize,
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                let tok = if c == '(' { Tok::LParen } else { Tok::RParen };
+                tokens.push(Token {
+                    tok,
+                    start,
+                    end: start + 1,
+                });
+            }
+            '"' => {
+                chars.next();
+                let mut phrase = String::new();
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            end = Some(i + 1);
+                            break;
+                        }
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                phrase.push(escaped);
+                            }
+                        }
+                        c => phrase.push(c),
+                    }
+                }
+                let Some(end) = end else {
+                    return error("unterminated quote", start);
+                };
+                tokens.push(Token {
+                    tok: Tok::Phrase(phrase),
+                    start,
+                    end,
+                });
+            }
+            '-' => {
+                // A leading `-` negates the following term
+                chars.next();
+                match chars.peek() {
+                    Some(&(_, next)) if !next.is_whitespace() => tokens.push(Token {
+                        tok: Tok::Not,
+                        start,
+                        end: start + 1,
+                    }),
+                    _ => tokens.push(Token {
+                        tok: Tok::Word("-".into()),
+                        start,
+                        end: start + 1,
+                    }),
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let tok = match word.as_str() {
+                    "AND" => Tok::And,
+                    "OR" => Tok::Or,
+                    "NOT" => Tok::Not,
+                    _ => Tok::Word(word),
+                };
+                tokens.push(Token { tok, start, end });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// ─── Parser ───
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    len: usize,
+}
+
+/// Parse a query. An empty query matches everything.
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: lex(input)?,
+        pos: 0,
+        len: input.len(),
+    };
+    if parser.tokens.is_empty() {
+        return Ok(Expr::And(Vec::new()));
+    }
+    let expr = parser.or()?;
+    if let Some(t) = parser.peek() {
+        return match t.tok {
+            Tok::RParen => error("unexpected ')'", t.start),
+            _ => error("unexpected input", t.start),
+        };
+    }
+    Ok(expr)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut items = vec![self.and()?];
+        while self.peek().is_some_and(|t| t.tok == Tok::Or) {
+            self.next();
+            items.push(self.and()?);
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            Expr::Or(items)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut items = vec![self.unary()?];
+        loop {
+            match self.peek().map(|t| &t.tok) {
+                Some(Tok::And) => {
+                    self.next();
+                    items.push(self.unary()?);
+                }
+                // Juxtaposed terms are ANDed
+                Some(Tok::Word(_) | Tok::Phrase(_) | Tok::LParen | Tok::Not) => {
+                    items.push(self.unary()?)
+                }
+                _ => break,
+            }
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            Expr::And(items)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek().is_some_and(|t| t.tok == Tok::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let Some(token) = self.next() else {
+            return error("unexpected end of query", self.len);
+        };
+        match token.tok {
+            Tok::LParen => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token {
+                        tok: Tok::RParen, ..
+                    }) => Ok(expr),
+                    _ => error("missing closing parenthesis", token.start),
+                }
+            }
+            Tok::RParen => error("unexpected ')'", token.start),
+            Tok::And => error("expected a term before AND", token.start),
+            Tok::Or => error("expected a term before OR", token.start),
+            Tok::Not => error("expected a term after NOT", token.start),
+            Tok::Phrase(p) => Ok(Expr::Text(p)),
+            Tok::Word(w) => self.term(w, token.start, token.end),
+        }
+    }
+
+    /// A bare word: `field:value` or a full-text term.
+    fn term(&mut self, word: String, start: usize, end: usize) -> Result<Expr, ParseError> {
+        // URLs (`https://...`) are text, not fields
+        let Some((field, rest)) = word
+            .split_once(':')
+            .filter(|(f, rest)| is_field_name(f) && !rest.starts_with("//"))
+        else {
+            return Ok(Expr::Text(word));
+        };
+        let field = canonical_field(field).to_string();
+
+        let (op, value) = [
+            (">=", CmpOp::Gte),
+            ("<=", CmpOp::Lte),
+            (">", CmpOp::Gt),
+            ("<", CmpOp::Lt),
+            ("!", CmpOp::Ne),
+        ]
+        .iter()
+        .find_map(|(p, op)| rest.strip_prefix(p).map(|v| (*op, v)))
+        .unwrap_or((CmpOp::Eq, rest));
+
+        let value = if value.is_empty() {
+            // `field:"quoted value"`
+            match self.peek() {
+                Some(Token {
+                    tok: Tok::Phrase(p),
+                    start: phrase_start,
+                    ..
+                }) if *phrase_start == end => {
+                    let p = p.clone();
+                    self.next();
+                    p
+                }
+                _ => return error(format!("missing value for {}", field), start),
+            }
+        } else {
+            value.to_string()
+        };
+
+        if value == "*" {
+            return match op {
+                CmpOp::Eq => Ok(Expr::Exists(field)),
+                CmpOp::Ne => Ok(Expr::Not(Box::new(Expr::Exists(field)))),
+                _ => error("`*` only works with `:` and `:!`", start),
+            };
+        }
+        Ok(Expr::Compare { field, op, value })
+    }
+}
+
+fn is_field_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        && !s.ends_with('.')
+}
+
+/// Short aliases for common fields.
+fn canonical_field(field: &str) -> &str {
+    match field {
+        "trace" => "traceId",
+        "request" => "requestId",
+        "span" => "spanId",
+        "env" => "environment",
+        f => f,
+    }
+}
+
+// ─── Meilisearch compilation ───
+
+/// A query compiled for Meilisearch.
+#[derive(Debug, Default)]
+pub struct Compiled {
+    pub filter: Option<Filter>,
+    /// Full-text terms, phrases kept quoted
+    pub text: String,
+}
+
+/// Compile to a Meilisearch filter plus full-text query. Full-text terms
+/// must be top-level AND terms, since Meilisearch's text search can't be
+/// negated or ORed with filters.
+pub fn compile(expr: &Expr, is_filterable: impl Fn(&str) -> bool) -> Result<Compiled, String> {
+    let mut text = Vec::new();
+    let mut clauses = Vec::new();
+    let top: Vec<&Expr> = match expr {
+        Expr::And(items) => items.iter().collect(),
+        e => vec![e],
+    };
+    for item in top {
+        collect(item, &mut text, &mut clauses, &is_filterable)?;
+    }
+    Ok(Compiled {
+        filter: (!clauses.is_empty()).then_some(Filter::And(clauses)),
+        text: text.join(" "),
+    })
+}
+
+fn collect(
+    expr: &Expr,
+    text: &mut Vec<String>,
+    clauses: &mut Vec<Filter>,
+    is_filterable: &impl Fn(&str) -> bool,
+) -> Result<(), String> {
+    match expr {
+        Expr::Text(t) if t.contains(|c: char| c.is_whitespace() || c == '"') => {
+            text.push(phrase(t))
+        }
+        Expr::Text(t) => text.push(t.clone()),
+        Expr::And(items) => {
+            for item in items {
+                collect(item, text, clauses, is_filterable)?;
+            }
+        }
+        e => clauses.push(filter(e, is_filterable)?),
+    }
+    Ok(())
+}
+
+/// Quote a term as a Meilisearch phrase, escaping the quotes inside it.
+fn phrase(t: &str) -> String {
+    format!("\"{}\"", t.replace('"', "\\\""))
+}
+
+fn filter(expr: &Expr, is_filterable: &impl Fn(&str) -> bool) -> Result<Filter, String> {
+    match expr {
+        Expr::And(items) | Expr::Or(items) => {
+            let parts = items
+                .iter()
+                .map(|e| filter(e, is_filterable))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(if matches!(expr, Expr::And(_)) {
+                Filter::And(parts)
+            } else {
+                Filter::Or(parts)
+            })
+        }
+        Expr::Not(inner) => Ok(filter(inner, is_filterable)?.not()),
+        Expr::Text(t) => Err(format!(
+            "full-text term {:?} can't be used inside OR or NOT; use a field filter",
+            t
+        )),
+        Expr::Exists(field) => {
+            check_filterable(field, is_filterable)?;
+            Ok(Filter::exists(field))
+        }
+        Expr::Compare { field, op, value } => match field.as_str() {
+            "since" => {
+                let ms = timerange::parse_duration(value)
+                    .ok_or_else(|| format!("invalid duration {:?} for since", value))?;
+                let cutoff = chrono::Utc::now().timestamp_millis() - ms;
+                Ok(Filter::gt("timestampMs", cutoff))
+            }
+            "level" => level_filter(*op, value),
+            _ => {
+                check_filterable(field, is_filterable)?;
+                match op {
+                    CmpOp::Eq | CmpOp::Ne => Ok(Filter::cmp(field, op.to_filter(), value)),
+                    _ => Ok(Filter::cmp(
+                        field,
+                        op.to_filter(),
+                        number(field, *op, value)?,
+                    )),
+                }
+            }
+        },
+    }
+}
+
+fn check_filterable(field: &str, is_filterable: &impl Fn(&str) -> bool) -> Result<(), String> {
+    if is_filterable(field) {
+        Ok(())
+    } else if field.starts_with("meta.") {
+        Err(format!(
+            "{} is not filterable; promote it with POST /admin/meta/promote",
+            field
+        ))
+    } else {
+        Err(format!("unknown or non-filterable field {}", field))
+    }
+}
+
+fn number(field: &str, op: CmpOp, value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .ok_or_else(|| format!("{}:{}{} needs a number", field, op.as_str(), value))
+}
+
+fn parse_level(value: &str) -> Result<LogLevel, String> {
+    LogLevel::parse(value).ok_or_else(|| format!("unknown level {:?}", value))
+}
+
+/// Levels are strings in the index, so comparisons become `IN` lists.
+fn level_filter(op: CmpOp, value: &str) -> Result<Filter, String> {
+    let level = parse_level(value)?;
+    let levels: Vec<String> = LogLevel::ALL
+        .iter()
+        .filter(|l| op.holds((*l).cmp(&level)))
+        .map(|l| l.to_string())
+        .collect();
+    if levels.is_empty() {
+        return Err(format!("level:{}{} matches no level", op.as_str(), value));
+    }
+    Ok(Filter::is_in("level", levels))
+}
+
+// ─── In-memory evaluation ───
+
+/// Evaluate against an entry, for live subscriptions. Any field can be
+/// used; full-text terms match case-insensitively in the message and error.
+/// Filters follow Meilisearch: string equality ignores case and `field:*`
+/// holds for present fields, even `null` ones.
+pub fn matches(expr: &Expr, entry: &LogEntry) -> bool {
+    let doc = serde_json::to_value(entry).unwrap_or(Value::Null);
+    eval(expr, entry, &doc)
+}
+
+fn eval(expr: &Expr, entry: &LogEntry, doc: &Value) -> bool {
+    match expr {
+        Expr::And(items) => items.iter().all(|e| eval(e, entry, doc)),
+        Expr::Or(items) => items.iter().any(|e| eval(e, entry, doc)),
+        Expr::Not(inner) => !eval(inner, entry, doc),
+        Expr::Text(t) => {
+            let needle = t.to_lowercase();
+            let error = entry.error.as_ref();
+            std::iter::once(Some(entry.message.as_str()))
+                .chain([
+                    error.and_then(|e| e.message.as_deref()),
+                    error.and_then(|e| e.stack.as_deref()),
+                ])
+                .flatten()
+                .any(|s| s.to_lowercase().contains(&needle))
+        }
+        Expr::Exists(field) => lookup(doc, field).is_some(),
+        Expr::Compare { field, op, value } => match field.as_str() {
+            "since" => timerange::parse_duration(value)
+                .is_some_and(|ms| entry.timestamp_ms > chrono::Utc::now().timestamp_millis() - ms),
+            "level" => LogLevel::parse(value).is_some_and(|l| op.holds(entry.level.cmp(&l))),
+            _ => match lookup(doc, field) {
+                Some(Value::Array(items)) => match op {
+                    CmpOp::Ne => !items.iter().any(|v| compare(v, CmpOp::Eq, value)),
+                    _ => items.iter().any(|v| compare(v, *op, value)),
+                },
+                Some(v) => compare(v, *op, value),
+                None => *op == CmpOp::Ne,
+            },
+        },
+    }
+}
+
+fn compare(found: &Value, op: CmpOp, value: &str) -> bool {
+    let as_number = match found {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    match op {
+        CmpOp::Eq | CmpOp::Ne => {
+            let equal = match found {
+                Value::String(s) => s.to_lowercase() == value.to_lowercase(),
+                Value::Bool(b) => value == if *b { "true" } else { "false" },
+                Value::Number(_) => as_number
+                    .zip(value.parse::<f64>().ok())
+                    .is_some_and(|(a, b)| a == b),
+                _ => false,
+            };
+            equal == (op == CmpOp::Eq)
+        }
+        _ => match (as_number, value.parse::<f64>().ok()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).is_some_and(|o| op.holds(o)),
+            _ => false,
+        },
+    }
+}
+
+/// Find a dotted path, allowing keys that themselves contain dots (flattened
+/// `meta` keys such as `http.status`).
+pub fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let obj = value.as_object()?;
+    if let Some(v) = obj.get(path) {
+        return Some(v);
+    }
+    path.match_indices('.').find_map(|(i, _)| {
+        obj.get(&path[..i])
+            .and_then(|inner| lookup(inner, &path[i + 1..]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::filter::Value as FilterValue;
+
+    fn text(t: &str) -> Expr {
+        Expr::Text(t.into())
+    }
+
+    fn cmp(field: &str, op: CmpOp, value: &str) -> Expr {
+        Expr::Compare {
+            field: field.into(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    fn not(e: Expr) -> Expr {
+        Expr::Not(Box::new(e))
+    }
+
+    #[test]
+    fn precedence() {
+        for (query, expected) in [
+            ("", Expr::And(vec![])),
+            ("a", text("a")),
+            ("a b", Expr::And(vec![text("a"), text("b")])),
+            ("a AND b", Expr::And(vec![text("a"), text("b")])),
+            // AND binds tighter than OR
+            (
+                "a OR b c",
+                Expr::Or(vec![text("a"), Expr::And(vec![text("b"), text("c")])]),
+            ),
+            (
+                "a b OR c",
+                Expr::Or(vec![Expr::And(vec![text("a"), text("b")]), text("c")]),
+            ),
+            (
+                "a (b OR c)",
+                Expr::And(vec![text("a"), Expr::Or(vec![text("b"), text("c")])]),
+            ),
+            // NOT and `-` apply to the next term only
+            ("NOT a b", Expr::And(vec![not(text("a")), text("b")])),
+            ("-a OR b", Expr::Or(vec![not(text("a")), text("b")])),
+            ("NOT NOT a", not(not(text("a")))),
+            ("-(a OR b)", not(Expr::Or(vec![text("a"), text("b")]))),
+            // A lone `-` is a word
+            ("a - b", Expr::And(vec![text("a"), text("-"), text("b")])),
+            // Lowercase keywords are words
+            ("a or b", Expr::And(vec![text("a"), text("or"), text("b")])),
+        ] {
+            assert_eq!(parse(query), Ok(expected), "{query:?}");
+        }
+    }
+
+    #[test]
+    fn comparisons() {
+        for (query, expected) in [
+            ("project:api", cmp("project", CmpOp::Eq, "api")),
+            ("project:!api", cmp("project", CmpOp::Ne, "api")),
+            ("meta.status:>500", cmp("meta.status", CmpOp::Gt, "500")),
+            ("meta.status:>=500", cmp("meta.status", CmpOp::Gte, "500")),
+            ("meta.status:<500", cmp("meta.status", CmpOp::Lt, "500")),
+            ("meta.status:<=500", cmp("meta.status", CmpOp::Lte, "500")),
+            ("meta.path:/health", cmp("meta.path", CmpOp::Eq, "/health")),
+            ("level:>=warn", cmp("level", CmpOp::Gte, "warn")),
+            ("trace:abc", cmp("traceId", CmpOp::Eq, "abc")),
+            ("request:r1", cmp("requestId", CmpOp::Eq, "r1")),
+            ("span:s1", cmp("spanId", CmpOp::Eq, "s1")),
+            ("env:prod", cmp("environment", CmpOp::Eq, "prod")),
+            (
+                "meta.user:\"Jane Doe\"",
+                cmp("meta.user", CmpOp::Eq, "Jane Doe"),
+            ),
+            (
+                "meta.user:!\"Jane Doe\"",
+                cmp("meta.user", CmpOp::Ne, "Jane Doe"),
+            ),
+            ("meta.region:*", Expr::Exists("meta.region".into())),
+            ("meta.region:!*", not(Expr::Exists("meta.region".into()))),
+            ("-meta.region:*", not(Expr::Exists("meta.region".into()))),
+            // Not fields: URLs, invalid names, a separate phrase
+            ("https://example.com/a", text("https://example.com/a")),
+            ("1x:2", text("1x:2")),
+            ("a.:b", text("a.:b")),
+        ] {
+            assert_eq!(parse(query), Ok(expected), "{query:?}");
+        }
+    }
+
+    #[test]
+    fn phrases() {
+        for (query, expected) in [
+            ("\"db timeout\"", text("db timeout")),
+            (r#""say \"hi\"""#, text(r#"say "hi""#)),
+            (r#""back\\slash""#, text(r"back\slash")),
+            ("\"\"", text("")),
+            ("a\"b c\"", Expr::And(vec![text("a"), text("b c")])),
+        ] {
+            assert_eq!(parse(query), Ok(expected), "{query:?}");
+        }
+        for value in ["plain", "two words", r#"with "quotes""#, r"back\slash\"] {
+            assert_eq!(parse(&quote(value)), Ok(text(value)), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for (query, message, position) in [
+            (
+                "level:>=warn (project:api",
+                "missing closing parenthesis",
+                13,
+            ),
+            ("a )", "unexpected ')'", 2),
+            (")", "unexpected ')'", 0),
+            ("()", "unexpected ')'", 1),
+            ("AND a", "expected a term before AND", 0),
+            ("a AND OR b", "expected a term before OR", 6),
+            ("a OR", "unexpected end of query", 4),
+            ("NOT", "unexpected end of query", 3),
+            ("a NOT", "unexpected end of query", 5),
+            ("say \"hi", "unterminated quote", 4),
+            ("project:", "missing value for project", 0),
+            ("a project: b", "missing value for project", 2),
+            ("meta.x:>*", "`*` only works with `:` and `:!`", 0),
+        ] {
+            let err = parse(query).unwrap_err();
+            assert_eq!(
+                (err.message.as_str(), err.position),
+                (message, position),
+                "{query:?}"
+            );
+        }
+        let err = parse("(a").unwrap_err();
+        assert_eq!(err.to_string(), "missing closing parenthesis at position 0");
+    }
+
+    fn compiled(query: &str) -> Result<(Option<String>, String), String> {
+        let c = compile(&parse(query).unwrap(), |f| !f.starts_with("meta.secret"))?;
+        let filter = c.filter.map(|f| f.render().unwrap());
+        Ok((filter, c.text))
+    }
+
+    #[test]
+    fn compiles_filters_and_text() {
+        for (query, filter, text) in [
+            ("", None, ""),
+            ("db timeout", None, "db timeout"),
+            (
+                "project:api \"db timeout\"",
+                Some(r#"project = "api""#),
+                r#""db timeout""#,
+            ),
+            (r#""say \"hi\"""#, None, r#""say \"hi\"""#),
+            (r#""a\"b""#, None, r#""a\"b""#),
+            ("level:>=error", Some(r#"level IN ["error", "fatal"]"#), ""),
+            ("level:<debug", Some(r#"level IN ["trace"]"#), ""),
+            (
+                "(project:api OR project:web) -meta.path:/health",
+                Some(r#"((project = "api" OR project = "web") AND NOT (meta.path = "/health"))"#),
+                "",
+            ),
+            (
+                "meta.status:>=500 meta.region:*",
+                Some(r#"(meta.status >= 500 AND meta.region EXISTS)"#),
+                "",
+            ),
+            ("meta.status:!500", Some(r#"meta.status != "500""#), ""),
+        ] {
+            assert_eq!(
+                compiled(query),
+                Ok((filter.map(String::from), text.to_string())),
+                "{query:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn compile_errors() {
+        for (query, message) in [
+            (
+                "a OR project:api",
+                "full-text term \"a\" can't be used inside OR or NOT; use a field filter",
+            ),
+            (
+                "NOT timeout",
+                "full-text term \"timeout\" can't be used inside OR or NOT; use a field filter",
+            ),
+            (
+                "meta.secret:x",
+                "meta.secret is not filterable; promote it with POST /admin/meta/promote",
+            ),
+            ("meta.status:>high", "meta.status:>high needs a number"),
+            ("level:loud", "unknown level \"loud\""),
+            ("level:<trace", "level:<trace matches no level"),
+            ("since:soon", "invalid duration \"soon\" for since"),
+        ] {
+            assert_eq!(compiled(query).unwrap_err(), message, "{query:?}");
+        }
+        let err = compile(&parse("meta.secret:x").unwrap(), |f| f == "project").unwrap_err();
+        assert!(err.contains("promote"), "{err}");
+        let err = compile(&parse("hostname:x").unwrap(), |f| f == "project").unwrap_err();
+        assert_eq!(err, "unknown or non-filterable field hostname");
+    }
+
+    /// Evaluate a compiled filter the way Meilisearch does: string equality
+    /// ignores case, quoted numbers equal numbers, arrays match on any
+    /// element, `!=` and `NOT` include documents without the field, and
+    /// `EXISTS` holds for present fields.
+    fn meili_matches(filter: &Filter, doc: &Value) -> bool {
+        let equal = |found: &Value, value: &FilterValue| match (found, value) {
+            (Value::String(s), FilterValue::Str(v)) => s.to_lowercase() == v.to_lowercase(),
+            (Value::Bool(b), FilterValue::Str(v)) => b.to_string() == *v,
+            (Value::Number(n), FilterValue::Str(v)) => v.parse::<f64>().ok() == n.as_f64(),
+            (Value::Number(n), FilterValue::Float(v)) => n.as_f64() == Some(*v),
+            (Value::Number(n), FilterValue::Int(v)) => n.as_f64() == Some(*v as f64),
+            _ => false,
+        };
+        let values = |field: &str| -> Vec<Value> {
+            match lookup(doc, field) {
+                Some(Value::Array(items)) => items.clone(),
+                Some(v) => vec![v.clone()],
+                None => Vec::new(),
+            }
+        };
+        match filter {
+            Filter::Cmp { field, op, value } => {
+                let found = values(field);
+                match op {
+                    filter::Op::Eq => found.iter().any(|f| equal(f, value)),
+                    filter::Op::Ne => !found.iter().any(|f| equal(f, value)),
+                    _ => {
+                        let v = match value {
+                            FilterValue::Float(v) => *v,
+                            FilterValue::Int(v) => *v as f64,
+                            FilterValue::Str(_) => panic!("non-numeric comparison {:?}", filter),
+                        };
+                        found.iter().filter_map(Value::as_f64).any(|n| match op {
+                            filter::Op::Gt => n > v,
+                            filter::Op::Gte => n >= v,
+                            filter::Op::Lt => n < v,
+                            filter::Op::Lte => n <= v,
+                            _ => unreachable!(),
+                        })
+                    }
+                }
+            }
+            Filter::In {
+                field,
+                values: list,
+            } => values(field)
+                .iter()
+                .any(|f| list.iter().any(|v| equal(f, v))),
+            Filter::Exists(field) => lookup(doc, field).is_some(),
+            Filter::Not(inner) => !meili_matches(inner, doc),
+            Filter::And(items) => items.iter().all(|f| meili_matches(f, doc)),
+            Filter::Or(items) => items.iter().any(|f| meili_matches(f, doc)),
+        }
+    }
+
+    fn entries() -> Vec<LogEntry> {
+        [
+            json!({ "project": "api", "level": "error", "message": "db timeout",
+                    "traceId": "abc", "environment": "prod",
+                    "meta": { "status": 500, "region": "eu", "tags": ["beta", "canary"],
+                              "flag": true, "user": "Jane Doe" } }),
+            json!({ "project": "api", "level": "info", "message": "GET /health",
+                    "meta": { "status": 200, "path": "/health", "region": null } }),
+            json!({ "project": "Web", "level": "warn", "message": "slow render",
+                    "environment": "staging",
+                    "meta": { "status": 404, "tags": ["stable"], "http.status": 404 } }),
+            json!({ "project": "worker", "level": "fatal", "message": "out of memory",
+                    "meta": { "flag": false, "user": "jane doe" } }),
+            json!({ "project": "worker", "level": "debug", "message": "tick" }),
+        ]
+        .into_iter()
+        .map(|v| serde_json::from_value(v).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn compile_and_eval_agree() {
+        let entries = entries();
+        let queries = [
+            "project:api",
+            "project:API",
+            "project:web",
+            "project:!api",
+            "level:>=warn",
+            "level:<info",
+            "level:!error",
+            "level:error OR project:web",
+            "NOT project:api",
+            "-level:info -level:debug",
+            "(project:api OR project:worker) level:>=error",
+            "env:prod",
+            "environment:!prod",
+            "trace:abc",
+            "trace:*",
+            "-trace:*",
+            "meta.status:500",
+            "meta.status:!500",
+            "meta.status:>=404",
+            "meta.status:<404",
+            "meta.status:>200.5",
+            "meta.http.status:404",
+            "meta.region:*",
+            "meta.region:!*",
+            "meta.region:eu",
+            "meta.tags:beta",
+            "meta.tags:!beta",
+            "meta.tags:*",
+            "meta.flag:true",
+            "meta.flag:false",
+            "meta.user:\"jane doe\"",
+            "meta.missing:!x",
+            "meta.missing:x",
+            "since:1h",
+        ];
+        let now = chrono::Utc::now().timestamp_millis();
+        for query in queries {
+            let expr = parse(query).unwrap();
+            let compiled = compile(&expr, |_| true).unwrap();
+            assert!(compiled.text.is_empty(), "{query:?}");
+            let filter = compiled.filter.unwrap();
+            for entry in &entries {
+                let mut entry = entry.clone();
+                entry.timestamp_ms = now;
+                let doc = serde_json::to_value(&entry).unwrap();
+                assert_eq!(
+                    matches(&expr, &entry),
+                    meili_matches(&filter, &doc),
+                    "{query:?} on {doc}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn eval_text_terms() {
+        let entries = entries();
+        let hits = |query: &str| -> Vec<&str> {
+            let expr = parse(query).unwrap();
+            entries
+                .iter()
+                .filter(|e| matches(&expr, e))
+                .map(|e| e.message.as_str())
+                .collect()
+        };
+        assert_eq!(hits("DB"), ["db timeout"]);
+        assert_eq!(hits("\"out of\" OR tick"), ["out of memory", "tick"]);
+        assert_eq!(hits("project:api -timeout"), ["GET /health"]);
+        assert_eq!(
+            hits(""),
+            entries
+                .iter()
+                .map(|e| e.message.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,22 +1,150 @@
 use std::sync::Arc;
 
+use axum::body::Body;
 use axum::extract::ws::{Message, WebSocket};
 use axum::extract::{Query, State, WebSocketUpgrade};
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Json};
 use futures::{SinkExt, StreamExt};
 use serde_json::json;
 use tokio::sync::mpsc;
 
+use crate::context::{self, ContextError};
+use crate::cursor::{self, Cursor, PageError};
+use crate::diff;
+use crate::export;
+use crate::fields;
+use crate::filter::{self, Filter, FilterError};
+use crate::histogram;
+use crate::issues::{IssueStatus, StatusChange};
 use crate::meili;
-use crate::models::{IngestPayload, LogEntry, SearchParams};
+use crate::meta;
+use crate::models::{
+    ContextParams, DiffParams, ExportParams, FieldsParams, HistogramParams, IngestPayload,
+    IssueParams, LogEntry, LogLevel, LogsParams, PatternsParams, PromoteMeta, SavedSearchParams,
+    SearchParams, StatsParams, TimelineParams, TraceParams, TracesParams,
+};
+use crate::patterns;
+use crate::pipeline::{IngestPipeline, IngestSummary};
+use crate::query;
+use crate::saved::{self, SavedSearch};
+use crate::spans;
+use crate::stats;
 use crate::subscribers::{SubscriberFilter, SubscriberManager};
+use crate::timerange::{self, TimeRange};
+use crate::traces;
 
 /// Shared state passed to all route handlers.
 pub struct AppState {
     pub meili_tx: mpsc::UnboundedSender<LogEntry>,
     pub subscribers: Arc<SubscriberManager>,
     pub meili_client: meilisearch_sdk::client::Client,
+    pub pipeline: IngestPipeline,
+}
+
+impl AppState {
+    /// Run entries through the ingest pipeline, broadcast them to live
+    /// subscribers and queue them for Meilisearch.
+    pub fn ingest_entries(&self, entries: Vec<LogEntry>) -> IngestSummary {
+        let mut summary = IngestSummary::default();
+        for entry in entries {
+            let Some(entry) = self.pipeline.process(entry, &mut summary) else {
+                continue;
+            };
+
+            // Broadcast to live subscribers immediately
+            self.subscribers.broadcast(&entry);
+
+            // Send to Meilisearch batcher
+            let _ = self.meili_tx.send(entry);
+        }
+        for issue in self.pipeline.issues.take_regressions() {
+            self.subscribers.broadcast_regression(&issue);
+        }
+        summary
+    }
+
+    /// Parse and compile a `query=` expression against the logs index's
+    /// filterable attributes. Errors are ready-made JSON bodies for a 400.
+    pub fn compile_query(&self, input: &str) -> Result<query::Compiled, serde_json::Value> {
+        let expr = query::parse(input)
+            .map_err(|e| json!({ "error": e.to_string(), "position": e.position }))?;
+        let promoted = self.pipeline.meta.filterable();
+        query::compile(&expr, |f| meili::is_filterable(f, &promoted))
+            .map_err(|e| json!({ "error": e }))
+    }
+}
+
+/// 400 response for a filter that failed to render.
+fn bad_filter(e: FilterError) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": e.to_string() })),
+    )
+}
+
+/// Parse `since`/`from`/`to` query parameters, or a 400 response.
+fn time_range(
+    since: &Option<String>,
+    from: &Option<String>,
+    to: &Option<String>,
+) -> Result<TimeRange, (StatusCode, Json<serde_json::Value>)> {
+    TimeRange::parse(since.as_deref(), from.as_deref(), to.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))))
+}
+
+/// Filter clauses and full-text query for `/search`-style parameters,
+/// including `meta.*` filters from the raw query string.
+fn search_filters(
+    state: &AppState,
+    params: &SearchParams,
+    raw: &[(String, String)],
+) -> Result<(Vec<Filter>, String), (StatusCode, Json<serde_json::Value>)> {
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    let meta_clauses = state.pipeline.meta.filters(raw).map_err(bad_request)?;
+    let compiled = match params.query.as_deref().map(|q| state.compile_query(q)) {
+        Some(Ok(c)) => c,
+        Some(Err(e)) => return Err((StatusCode::BAD_REQUEST, Json(e))),
+        None => query::Compiled::default(),
+    };
+    let text = [params.q.as_deref().unwrap_or(""), compiled.text.as_str()]
+        .join(" ")
+        .trim()
+        .to_string();
+    let level_clauses = meili::level_filter(params.level.as_deref(), params.min_level.as_deref())
+        .map_err(bad_request)?;
+    let range = time_range(&params.since, &params.from, &params.to)?;
+    let filters = meili::build_filter(
+        params.project.as_deref(),
+        params.trace_id.as_deref(),
+        params.request_id.as_deref(),
+        params.environment.as_deref(),
+        &range,
+    )
+    .into_iter()
+    .chain(level_clauses)
+    .chain(meta_clauses)
+    .chain(compiled.filter)
+    .collect();
+    Ok((filters, text))
+}
+
+/// Apply the saved search named by `saved`, if any, to the parameters.
+async fn with_saved(
+    state: &AppState,
+    mut params: SearchParams,
+) -> Result<SearchParams, (StatusCode, Json<serde_json::Value>)> {
+    let Some(name) = params.saved.as_deref() else {
+        return Ok(params);
+    };
+    match saved::get(&state.meili_client, name).await {
+        Ok(Some(search)) => {
+            search.apply(&mut params);
+            Ok(params)
+        }
+        Ok(None) => Err(saved_not_found(name)),
+        Err(e) => Err(saved_error(e)),
+    }
 }
 
 // ────────────────────────────────────────────
@@ -27,20 +155,17 @@ pub async fn ingest(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<IngestPayload>,
 ) -> impl IntoResponse {
-    let entries = payload.into_entries();
-    let count = entries.len();
-
-    for entry in entries {
-        let entry = entry.normalize();
+    let summary = state.ingest_entries(payload.into_entries());
 
-        // Broadcast to live subscribers immediately
-        state.subscribers.broadcast(&entry);
+    (StatusCode::ACCEPTED, Json(summary))
+}
 
-        // Send to Meilisearch batcher
-        let _ = state.meili_tx.send(entry);
-    }
+// ────────────────────────────────────────────
+// GET /ingest/stats — per-project accepted / sampled / rate-limited counts
+// ────────────────────────────────────────────
 
-    (StatusCode::ACCEPTED, Json(json!({ "accepted": count })))
+pub async fn ingest_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(json!({ "projects": state.pipeline.sampler.stats() }))
 }
 
 // ────────────────────────────────────────────
@@ -50,43 +175,331 @@ pub async fn ingest(
 pub async fn search(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SearchParams>,
+    Query(raw): Query<Vec<(String, String)>>,
 ) -> impl IntoResponse {
+    let params = match with_saved(&state, params).await {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
     let index = state.meili_client.index(meili::INDEX_NAME);
-    let query_str = params.q.as_deref().unwrap_or("");
     let limit = params.limit.unwrap_or(20).min(200);
 
-    let filter = meili::build_filter(
-        params.project.as_deref(),
-        params.level.as_deref(),
-        params.trace_id.as_deref(),
-        params.request_id.as_deref(),
-        params.environment.as_deref(),
-        params.since.as_deref(),
-    );
+    let (filters, text) = match search_filters(&state, &params, &raw) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+    let cursor = match params.cursor.as_deref().map(Cursor::decode).transpose() {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))),
+    };
+    let search = cursor::Search {
+        index: &index,
+        text: &text,
+        filters,
+        facets: &["project", "level"],
+        totals: true,
+    };
+    let field = cursor::sort_field(params.sort.as_deref());
 
-    let mut search_query = index.search();
-    search_query.with_query(query_str);
-    search_query.with_limit(limit);
-    search_query.with_sort(&["timestamp:desc"]);
-    search_query.with_facets(meilisearch_sdk::search::Selectors::Some(&[
-        "project", "level",
-    ]));
+    match search.page(field, cursor.as_ref(), limit).await {
+        Ok(page) => (StatusCode::OK, Json(json!(page))),
+        Err(PageError::Filter(e)) => bad_filter(e),
+        Err(e) => {
+            tracing::error!("Search error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+        }
+    }
+}
 
-    if let Some(ref f) = filter {
-        search_query.with_filter(f);
+// ────────────────────────────────────────────
+// GET /export — stream every match as NDJSON, CSV or JSON
+// ────────────────────────────────────────────
+
+pub async fn export(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+    Query(export): Query<ExportParams>,
+    Query(raw): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let params = match with_saved(&state, params).await {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+    let (filters, text) = match search_filters(&state, &params, &raw) {
+        Ok(f) => f,
+        Err(e) => return e.into_response(),
+    };
+    let columns = match export.fields.as_deref() {
+        Some(fields) => fields
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(String::from)
+            .collect(),
+        None => export::DEFAULT_COLUMNS
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+    };
+    let format = export.format;
+    let body = export::Export {
+        index: state.meili_client.index(meili::INDEX_NAME),
+        text,
+        filters,
+        field: cursor::sort_field(params.sort.as_deref()),
+        limit: params.limit,
+        format,
+        columns,
     }
+    .stream();
 
-    match search_query.execute::<serde_json::Value>().await {
-        Ok(results) => {
-            let response = json!({
-                "totalHits": results.estimated_total_hits,
-                "facets": results.facet_distribution,
-                "hits": results.hits.iter().map(|h| &h.result).collect::<Vec<_>>(),
-            });
-            (StatusCode::OK, Json(response))
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"logs.{}\"", format.extension()),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+// ────────────────────────────────────────────
+// GET /histogram — match counts per time bucket
+// ────────────────────────────────────────────
+
+pub async fn histogram(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+    Query(hist): Query<HistogramParams>,
+    Query(raw): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let params = match with_saved(&state, params).await {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    if let Some(g) = hist.group_by.as_deref() {
+        if !histogram::GROUPS.contains(&g) {
+            return bad_request(format!(
+                "groupBy must be one of: {}",
+                histogram::GROUPS.join(", ")
+            ));
         }
+    }
+    let range = match time_range(&params.since, &params.from, &params.to) {
+        Ok(r) => r,
+        Err(e) => return e,
+    };
+    let plan = match histogram::Plan::new(&range, hist.interval.as_deref()) {
+        Ok(p) => p,
+        Err(e) => return bad_request(e),
+    };
+    let (filters, text) = match search_filters(&state, &params, &raw) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+    let filter = match filter::render_all(filters) {
+        Ok(f) => f,
+        Err(e) => return bad_filter(e),
+    };
+
+    let index = state.meili_client.index(meili::INDEX_NAME);
+    match histogram::compute(
+        &index,
+        &text,
+        filter.as_deref(),
+        &plan,
+        hist.group_by.as_deref(),
+    )
+    .await
+    {
+        Ok(h) => (StatusCode::OK, Json(json!(h))),
         Err(e) => {
-            tracing::error!("Search error: {:?}", e);
+            tracing::error!("Histogram error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+        }
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /stats — top values, distinct counts and numeric stats for a field
+// ────────────────────────────────────────────
+
+pub async fn stats(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+    Query(stats): Query<StatsParams>,
+    Query(raw): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let params = match with_saved(&state, params).await {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    let Some(field) = stats.field.as_deref() else {
+        return bad_request("field is required".into());
+    };
+    if !filter::is_valid_field(field) {
+        return bad_request(format!("invalid field {:?}", field));
+    }
+    let aggs = match stats::Agg::parse_list(stats.agg.as_deref().unwrap_or("top")) {
+        Ok(a) if a.is_empty() => return bad_request("agg must not be empty".into()),
+        Ok(a) => a,
+        Err(e) => return bad_request(e),
+    };
+    let (filters, text) = match search_filters(&state, &params, &raw) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    let index = state.meili_client.index(meili::INDEX_NAME);
+    let promoted = meili::meta_filterable(&state.meili_client)
+        .await
+        .unwrap_or_default();
+    let request = stats::Request {
+        index: &index,
+        text: &text,
+        filters,
+        field,
+        aggs: &aggs,
+        limit: stats.limit.unwrap_or(10).min(1000),
+        filterable: meili::is_filterable(field, &promoted),
+    };
+
+    match request.run().await {
+        Ok(s) => (StatusCode::OK, Json(json!(s))),
+        Err(PageError::Filter(e)) => bad_filter(e),
+        Err(e) => {
+            tracing::error!("Stats error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+        }
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /diff — what changed between two time windows
+// ────────────────────────────────────────────
+
+pub async fn diff(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+    Query(windows): Query<DiffParams>,
+    Query(raw): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    if params.since.is_some() || params.from.is_some() || params.to.is_some() {
+        return bad_request("use baseline and target instead of since, from and to".into());
+    }
+    let mut params = match with_saved(&state, params).await {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    // A saved search's default range doesn't apply to either window
+    params.since = None;
+    params.from = None;
+    params.to = None;
+
+    let now = chrono::Utc::now();
+    let target =
+        match TimeRange::parse_window(windows.target.as_deref().unwrap_or("now-1h..now"), now) {
+            Ok(w) => w,
+            Err(e) => return bad_request(e),
+        };
+    let baseline = match windows.baseline.as_deref() {
+        Some(b) => match TimeRange::parse_window(b, now) {
+            Ok(w) => w,
+            Err(e) => return bad_request(e),
+        },
+        None => {
+            let (from, to) = (target.from.unwrap_or(0), target.to.unwrap_or(0));
+            TimeRange {
+                from: Some(from - (to - from)),
+                to: Some(from),
+            }
+        }
+    };
+    let (filters, text) = match search_filters(&state, &params, &raw) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+
+    let index = state.meili_client.index(meili::INDEX_NAME);
+    let request = diff::Request {
+        index: &index,
+        text: &text,
+        filters,
+        baseline,
+        target,
+        limit: windows.limit.unwrap_or(diff::DEFAULT_LIMIT).min(200),
+        patterns: &state.pipeline.patterns,
+    };
+    match request.run().await {
+        Ok(mut d) => {
+            for change in &mut d.fingerprints {
+                change.title = state.pipeline.issues.get(&change.value).map(|i| i.title);
+            }
+            (StatusCode::OK, Json(json!(d)))
+        }
+        Err(PageError::Filter(e)) => bad_filter(e),
+        Err(e) => {
+            tracing::error!("Diff error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+        }
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /patterns — message templates with counts for the matching logs
+// ────────────────────────────────────────────
+
+pub async fn patterns(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+    Query(list): Query<PatternsParams>,
+    Query(raw): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    let params = match with_saved(&state, params).await {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let (filters, text) = match search_filters(&state, &params, &raw) {
+        Ok(f) => f,
+        Err(e) => return e,
+    };
+    let filter = match filter::render_all(filters) {
+        Ok(f) => f,
+        Err(e) => return bad_filter(e),
+    };
+
+    let index = state.meili_client.index(meili::INDEX_NAME);
+    let limit = list.limit.unwrap_or(50).min(1000);
+    match patterns::counts(
+        &index,
+        &text,
+        filter.as_deref(),
+        &state.pipeline.patterns,
+        limit,
+    )
+    .await
+    {
+        Ok(counts) => (StatusCode::OK, Json(json!(counts))),
+        Err(e) => {
+            tracing::error!("Patterns error: {:?}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({ "error": format!("{}", e) })),
@@ -127,6 +540,221 @@ pub async fn projects(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     }
 }
 
+// ────────────────────────────────────────────
+// GET /logs/:id — one log entry
+// ────────────────────────────────────────────
+
+pub async fn log(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if !meili::is_valid_id(&id) {
+        return invalid_id(&id);
+    }
+    let index = state.meili_client.index(meili::INDEX_NAME);
+
+    match meili::get_log(&index, &id).await {
+        Ok(Some(entry)) => (StatusCode::OK, Json(json!({ "log": entry }))),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "log entry not found" })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{}", e) })),
+        ),
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /logs?ids=a,b,c — several log entries by id
+// ────────────────────────────────────────────
+
+/// Ids accepted by one batch lookup.
+const MAX_BATCH_IDS: usize = 100;
+
+fn invalid_id(id: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": format!("invalid id {:?}", id) })),
+    )
+}
+
+pub async fn logs(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<LogsParams>,
+) -> impl IntoResponse {
+    let mut ids: Vec<&str> = Vec::new();
+    for id in params
+        .ids
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+    {
+        if !id.is_empty() && !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "ids is required" })),
+        );
+    }
+    if ids.len() > MAX_BATCH_IDS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("at most {} ids per request", MAX_BATCH_IDS) })),
+        );
+    }
+    if let Some(id) = ids.iter().find(|id| !meili::is_valid_id(id)) {
+        return invalid_id(id);
+    }
+
+    let index = state.meili_client.index(meili::INDEX_NAME);
+    match meili::get_logs(&index, &ids).await {
+        Ok(found) => {
+            let missing: Vec<&str> = ids
+                .iter()
+                .zip(&found)
+                .filter(|(_, entry)| entry.is_none())
+                .map(|(id, _)| *id)
+                .collect();
+            let logs: Vec<serde_json::Value> = found.into_iter().flatten().collect();
+            (
+                StatusCode::OK,
+                Json(json!({ "logs": logs, "missing": missing })),
+            )
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{}", e) })),
+        ),
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /logs/:id/context — entries around one log entry
+// ────────────────────────────────────────────
+
+pub async fn log_context(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(params): Query<ContextParams>,
+) -> impl IntoResponse {
+    let index = state.meili_client.index(meili::INDEX_NAME);
+    let lines = |n: Option<usize>| n.unwrap_or(context::DEFAULT_LINES).min(context::MAX_LINES);
+
+    match context::around(
+        &index,
+        &id,
+        params.scope,
+        lines(params.before),
+        lines(params.after),
+    )
+    .await
+    {
+        Ok(ctx) => (StatusCode::OK, Json(json!(ctx))),
+        Err(ContextError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "log entry not found" })),
+        ),
+        Err(ContextError::Filter(e)) => bad_filter(e),
+        Err(e @ ContextError::MissingField(_)) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": e.to_string() })),
+        ),
+        Err(e) => {
+            tracing::error!("Context error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+        }
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /traces — trace summaries
+// ────────────────────────────────────────────
+
+pub async fn traces(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TracesParams>,
+) -> impl IntoResponse {
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    let sort = match params.sort.as_deref() {
+        None | Some("recent") => "startMs:desc",
+        Some("duration") => "durationMs:desc",
+        Some("errors") => "errorCount:desc",
+        Some(s) => {
+            return bad_request(format!(
+                "sort must be recent, duration or errors, not {:?}",
+                s
+            ))
+            .into_response()
+        }
+    };
+    let range = match time_range(&params.since, &params.from, &params.to) {
+        Ok(r) => r,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut filters = Vec::new();
+    if let Some(ref project) = params.project {
+        filters.push(Filter::eq("projects", project));
+    }
+    if let Some(ref d) = params.min_duration {
+        match timerange::parse_duration(d) {
+            Some(ms) => filters.push(Filter::cmp("durationMs", filter::Op::Gte, ms)),
+            None => return bad_request(format!("invalid duration {:?}", d)).into_response(),
+        }
+    }
+    if let Some(has_error) = params.has_error {
+        filters.push(Filter::eq("hasError", has_error.to_string()));
+    }
+    // Traces overlapping the range, not just those starting in it
+    if let Some(from) = range.from {
+        filters.push(Filter::cmp("endMs", filter::Op::Gte, from));
+    }
+    if let Some(to) = range.to {
+        filters.push(Filter::cmp("startMs", filter::Op::Lte, to));
+    }
+    let filter = match filter::render_all(filters) {
+        Ok(f) => f,
+        Err(e) => return bad_filter(e).into_response(),
+    };
+
+    let index = state.meili_client.index(meili::TRACES_INDEX);
+    let sort = [sort];
+    let mut search_query = index.search();
+    search_query.with_query("");
+    search_query.with_sort(&sort);
+    search_query.with_attributes_to_retrieve(meilisearch_sdk::search::Selectors::Some(
+        traces::LISTED_FIELDS,
+    ));
+    search_query.with_limit(params.limit.unwrap_or(50).min(500));
+    if let Some(ref f) = filter {
+        search_query.with_filter(f);
+    }
+
+    match search_query.execute::<traces::TraceSummary>().await {
+        Ok(results) => {
+            let response = json!({
+                "totalTraces": results.estimated_total_hits,
+                "traces": results.hits.into_iter().map(|h| h.result).collect::<Vec<_>>(),
+            });
+            Json(response).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{}", e) })),
+        )
+            .into_response(),
+    }
+}
+
 // ────────────────────────────────────────────
 // GET /trace/:trace_id — full trace timeline
 // ────────────────────────────────────────────
@@ -134,15 +762,44 @@ pub async fn projects(State(state): State<Arc<AppState>>) -> impl IntoResponse {
 pub async fn trace(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(trace_id): axum::extract::Path<String>,
+    Query(params): Query<TimelineParams>,
+    Query(view): Query<TraceParams>,
 ) -> impl IntoResponse {
+    let tree = match view.view.as_deref() {
+        None | Some("timeline") => false,
+        Some("tree") => true,
+        Some(v) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": format!("view must be timeline or tree, not {:?}", v) })),
+            )
+                .into_response()
+        }
+    };
     let index = state.meili_client.index(meili::INDEX_NAME);
 
-    let filter = format!("traceId = \"{}\"", trace_id);
+    if tree {
+        return match spans::fetch(&index, &trace_id, params.sort.as_deref()).await {
+            Ok(tree) => Json(json!(tree)).into_response(),
+            Err(PageError::Filter(e)) => bad_filter(e).into_response(),
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+                .into_response(),
+        };
+    }
+
+    let filter = match Filter::eq("traceId", &trace_id).render() {
+        Ok(f) => f,
+        Err(e) => return bad_filter(e).into_response(),
+    };
+    let sort = [meili::sort_expr(params.sort.as_deref(), false)];
 
     let mut search_query = index.search();
     search_query.with_query("");
     search_query.with_filter(&filter);
-    search_query.with_sort(&["timestamp:asc"]);
+    search_query.with_sort(&sort);
     search_query.with_limit(500);
 
     match search_query.execute::<serde_json::Value>().await {
@@ -176,15 +833,20 @@ pub async fn trace(
 pub async fn request(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(request_id): axum::extract::Path<String>,
+    Query(params): Query<TimelineParams>,
 ) -> impl IntoResponse {
     let index = state.meili_client.index(meili::INDEX_NAME);
 
-    let filter = format!("requestId = \"{}\"", request_id);
+    let filter = match Filter::eq("requestId", &request_id).render() {
+        Ok(f) => f,
+        Err(e) => return bad_filter(e).into_response(),
+    };
+    let sort = [meili::sort_expr(params.sort.as_deref(), false)];
 
     let mut search_query = index.search();
     search_query.with_query("");
     search_query.with_filter(&filter);
-    search_query.with_sort(&["timestamp:asc"]);
+    search_query.with_sort(&sort);
     search_query.with_limit(500);
 
     match search_query.execute::<serde_json::Value>().await {
@@ -221,43 +883,50 @@ pub async fn errors(
 ) -> impl IntoResponse {
     let index = state.meili_client.index(meili::INDEX_NAME);
 
-    let mut filter_parts = vec!["level = \"error\" OR level = \"fatal\"".to_string()];
-
-    if let Some(ref p) = params.project {
-        filter_parts.push(format!("project = \"{}\"", p));
-    }
-    if let Some(ref s) = params.since {
-        if let Some(ms) = meili::parse_duration(s) {
-            let cutoff = chrono::Utc::now().timestamp_millis() - ms;
-            filter_parts.push(format!("timestampMs > {}", cutoff));
-        }
+    // Errors and fatals unless the caller asks for other levels
+    let mut levels = match meili::level_filter(params.level.as_deref(), params.min_level.as_deref())
+    {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response(),
+    };
+    if levels.is_empty() {
+        levels.push(Filter::is_in("level", ["error", "fatal"]));
     }
-
-    // Wrap the OR clause in parens when combining with AND
-    let filter = if filter_parts.len() > 1 {
-        let first = format!("({})", filter_parts[0]);
-        let rest: Vec<String> = filter_parts[1..].to_vec();
-        let mut all = vec![first];
-        all.extend(rest);
-        all.join(" AND ")
-    } else {
-        filter_parts[0].clone()
+    let range = match time_range(&params.since, &params.from, &params.to) {
+        Ok(r) => r,
+        Err(e) => return e.into_response(),
+    };
+    let scope = meili::build_filter(params.project.as_deref(), None, None, None, &range);
+    let filter = match filter::render_all(levels.into_iter().chain(scope)) {
+        Ok(f) => f.unwrap_or_default(),
+        Err(e) => return bad_filter(e).into_response(),
     };
 
     let query_str = params.q.as_deref().unwrap_or("");
 
+    let sort = [meili::sort_expr(params.sort.as_deref(), true)];
     let mut search_query = index.search();
     search_query.with_query(query_str);
     search_query.with_filter(&filter);
-    search_query.with_sort(&["timestamp:desc"]);
+    search_query.with_sort(&sort);
     search_query.with_limit(params.limit.unwrap_or(30).min(100));
-    search_query.with_facets(meilisearch_sdk::search::Selectors::Some(&["project"]));
+    search_query.with_facets(meilisearch_sdk::search::Selectors::Some(&[
+        "project",
+        "fingerprint",
+    ]));
 
     match search_query.execute::<serde_json::Value>().await {
-        Ok(results) => {
+        Ok(mut results) => {
+            let by_issue = results
+                .facet_distribution
+                .as_mut()
+                .and_then(|f| f.remove("fingerprint"))
+                .map(|counts| issue_counts(&state, counts))
+                .unwrap_or_default();
             let response = json!({
                 "totalErrors": results.estimated_total_hits,
                 "byProject": results.facet_distribution,
+                "byIssue": by_issue,
                 "errors": results.hits.iter().map(|h| &h.result).collect::<Vec<_>>(),
             });
             Json(response).into_response()
@@ -270,6 +939,373 @@ pub async fn errors(
     }
 }
 
+/// Join fingerprint facet counts with the issue store, largest first.
+fn issue_counts(
+    state: &AppState,
+    counts: std::collections::HashMap<String, usize>,
+) -> Vec<serde_json::Value> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|c| std::cmp::Reverse(c.1));
+    counts
+        .into_iter()
+        .map(|(fp, count)| {
+            let issue = state.pipeline.issues.get(&fp);
+            json!({
+                "fingerprint": fp,
+                "count": count,
+                "title": issue.as_ref().map(|i| i.title.as_str()),
+                "project": issue.as_ref().map(|i| i.project.as_str()),
+                "totalCount": issue.as_ref().map(|i| i.count),
+                "firstSeen": issue.as_ref().map(|i| i.first_seen.as_str()),
+            })
+        })
+        .collect()
+}
+
+// ────────────────────────────────────────────
+// GET /issues — errors grouped by fingerprint
+// ────────────────────────────────────────────
+
+pub async fn issues(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<IssueParams>,
+) -> impl IntoResponse {
+    let range = match time_range(&params.since, &params.from, &params.to) {
+        Ok(r) => r,
+        Err(e) => return e,
+    };
+
+    let issues = state.pipeline.issues.list(
+        params.project.as_deref(),
+        params.status,
+        &range,
+        params.sort.as_deref(),
+    );
+    let total = issues.len();
+    let limit = params.limit.unwrap_or(50).min(500);
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "totalIssues": total,
+            "issues": issues.into_iter().take(limit).collect::<Vec<_>>(),
+        })),
+    )
+}
+
+// ────────────────────────────────────────────
+// GET /issues/:fingerprint — one issue with its latest occurrences
+// ────────────────────────────────────────────
+
+pub async fn issue(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(fingerprint): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    let Some(issue) = state.pipeline.issues.get(&fingerprint) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "issue not found" })),
+        )
+            .into_response();
+    };
+
+    let index = state.meili_client.index(meili::INDEX_NAME);
+    let filter = match Filter::eq("fingerprint", &fingerprint).render() {
+        Ok(f) => f,
+        Err(e) => return bad_filter(e).into_response(),
+    };
+
+    let mut search_query = index.search();
+    search_query.with_query("");
+    search_query.with_filter(&filter);
+    search_query.with_sort(&["timestamp:desc"]);
+    search_query.with_limit(20);
+
+    match search_query.execute::<serde_json::Value>().await {
+        Ok(results) => Json(json!({
+            "issue": issue,
+            "recent": results.hits.iter().map(|h| &h.result).collect::<Vec<_>>(),
+        }))
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+// ────────────────────────────────────────────
+// POST /issues/:fingerprint/status — resolve, ignore or reopen an issue
+// ────────────────────────────────────────────
+
+pub async fn issue_status(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(fingerprint): axum::extract::Path<String>,
+    Json(change): Json<StatusChange>,
+) -> impl IntoResponse {
+    let ignore_for_ms = match change.duration.as_deref() {
+        Some(_) if change.status != IssueStatus::Ignored => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "`for` only applies to status \"ignored\"" })),
+            )
+                .into_response();
+        }
+        Some(d) => match timerange::parse_duration(d) {
+            Some(ms) => Some(ms),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": format!("invalid duration: {}", d) })),
+                )
+                    .into_response();
+            }
+        },
+        None => None,
+    };
+
+    match state.pipeline.issues.set_status(
+        &fingerprint,
+        change.status,
+        ignore_for_ms,
+        change.version,
+    ) {
+        Some(issue) => Json(json!({ "issue": issue })).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "issue not found" })),
+        )
+            .into_response(),
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /fields — observed field paths with types, distinct values and top values
+// ────────────────────────────────────────────
+
+pub async fn fields(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<FieldsParams>,
+) -> impl IntoResponse {
+    let range = match time_range(&params.since, &params.from, &params.to) {
+        Ok(r) => r,
+        Err(e) => return e,
+    };
+    let filter = meili::build_filter(params.project.as_deref(), None, None, None, &range);
+    let filter = match filter::render_all(filter) {
+        Ok(f) => f,
+        Err(e) => return bad_filter(e),
+    };
+    let limit = params.sample.unwrap_or(fields::DEFAULT_SAMPLE);
+
+    match fields::sample(&state.meili_client, filter.as_deref(), limit).await {
+        Ok((docs, total)) => {
+            let promoted = state.pipeline.meta.filterable();
+            let mut fields = fields::describe(&docs, total, |p| meili::is_filterable(p, &promoted));
+            // Fall back to the sample estimates
+            if let Err(e) =
+                fields::count_distinct(&state.meili_client, filter.as_deref(), &mut fields).await
+            {
+                tracing::warn!("Field facet error: {:?}", e);
+            }
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "sampled": docs.len(),
+                    "totalHits": total,
+                    "fields": fields,
+                })),
+            )
+        }
+        Err(e) => {
+            tracing::error!("Fields error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+        }
+    }
+}
+
+// ────────────────────────────────────────────
+// GET /admin/meta — flattened meta keys seen at ingest
+// ────────────────────────────────────────────
+
+pub async fn meta_keys(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(json!({ "keys": state.pipeline.meta.keys() }))
+}
+
+// ────────────────────────────────────────────
+// POST /admin/meta/promote — make meta keys filterable and facetable
+// ────────────────────────────────────────────
+
+pub async fn promote_meta(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PromoteMeta>,
+) -> impl IntoResponse {
+    let keys: Vec<String> = req
+        .keys
+        .iter()
+        .map(|k| k.strip_prefix("meta.").unwrap_or(k).to_string())
+        .collect();
+    if let Some(err) = keys.iter().find_map(|k| meta::validate_key(k).err()) {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": err })));
+    }
+
+    let mut filterable = state.pipeline.meta.filterable();
+    filterable.extend(keys);
+    match meili::set_meta_filterable(&state.meili_client, &filterable).await {
+        Ok(task) => {
+            let filterable = state.pipeline.meta.promote(filterable);
+            (
+                StatusCode::ACCEPTED,
+                Json(json!({ "filterable": filterable, "taskUid": task.task_uid })),
+            )
+        }
+        Err(e) => {
+            tracing::error!("Promote meta error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{}", e) })),
+            )
+        }
+    }
+}
+
+// ────────────────────────────────────────────
+// /saved-searches — named searches, run with `saved=<name>`
+// ────────────────────────────────────────────
+
+/// Check a saved search the way `/search` would check its parameters.
+fn validate_saved(state: &AppState, search: &SavedSearch) -> Result<(), serde_json::Value> {
+    saved::validate_name(&search.name).map_err(|e| json!({ "error": e }))?;
+    if let Some(q) = search.query.as_deref() {
+        state.compile_query(q)?;
+    }
+    let f = &search.filters;
+    meili::level_filter(f.level.as_deref(), f.min_level.as_deref())
+        .map_err(|e| json!({ "error": e }))?;
+    TimeRange::parse(
+        search.since.as_deref(),
+        search.from.as_deref(),
+        search.to.as_deref(),
+    )
+    .map_err(|e| json!({ "error": e }))?;
+    Ok(())
+}
+
+fn saved_not_found(name: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("saved search {:?} not found", name) })),
+    )
+}
+
+fn saved_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Saved search error: {:?}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": format!("{}", e) })),
+    )
+}
+
+pub async fn list_saved(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SavedSearchParams>,
+) -> impl IntoResponse {
+    match saved::list(&state.meili_client, params.owner.as_deref()).await {
+        Ok(searches) => (StatusCode::OK, Json(json!({ "savedSearches": searches }))),
+        Err(e) => saved_error(e),
+    }
+}
+
+pub async fn create_saved(
+    State(state): State<Arc<AppState>>,
+    Json(mut search): Json<SavedSearch>,
+) -> impl IntoResponse {
+    if let Err(e) = validate_saved(&state, &search) {
+        return (StatusCode::BAD_REQUEST, Json(e));
+    }
+    match saved::get(&state.meili_client, &search.name).await {
+        Ok(Some(_)) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(json!({ "error": format!("saved search {:?} already exists", search.name) })),
+            )
+        }
+        Ok(None) => {}
+        Err(e) => return saved_error(e),
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    search.created_at = now.clone();
+    search.updated_at = now;
+    match saved::put(&state.meili_client, &search).await {
+        Ok(()) => (StatusCode::CREATED, Json(json!({ "savedSearch": search }))),
+        Err(e) => saved_error(e),
+    }
+}
+
+pub async fn get_saved(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    match saved::get(&state.meili_client, &name).await {
+        Ok(Some(search)) => (StatusCode::OK, Json(json!({ "savedSearch": search }))),
+        Ok(None) => saved_not_found(&name),
+        Err(e) => saved_error(e),
+    }
+}
+
+/// Create or replace the saved search `name`.
+pub async fn put_saved(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    Json(mut search): Json<SavedSearch>,
+) -> impl IntoResponse {
+    if !search.name.is_empty() && search.name != name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "name in the body doesn't match the path" })),
+        );
+    }
+    search.name = name;
+    if let Err(e) = validate_saved(&state, &search) {
+        return (StatusCode::BAD_REQUEST, Json(e));
+    }
+    let existing = match saved::get(&state.meili_client, &search.name).await {
+        Ok(existing) => existing,
+        Err(e) => return saved_error(e),
+    };
+
+    let now = chrono::Utc::now().to_rfc3339();
+    search.created_at = existing
+        .as_ref()
+        .map_or_else(|| now.clone(), |e| e.created_at.clone());
+    search.updated_at = now;
+    let status = if existing.is_some() {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    match saved::put(&state.meili_client, &search).await {
+        Ok(()) => (status, Json(json!({ "savedSearch": search }))),
+        Err(e) => saved_error(e),
+    }
+}
+
+pub async fn delete_saved(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    match saved::delete(&state.meili_client, &name).await {
+        Ok(true) => (StatusCode::OK, Json(json!({ "deleted": name }))),
+        Ok(false) => saved_not_found(&name),
+        Err(e) => saved_error(e),
+    }
+}
+
 // ────────────────────────────────────────────
 // GET /health
 // ────────────────────────────────────────────
@@ -289,9 +1325,17 @@ pub async fn health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
 pub struct WsParams {
     pub mode: Option<String>,
     pub projects: Option<String>,
+    /// Comma-separated levels
     pub levels: Option<String>,
+    /// Only entries at least this severe
+    #[serde(rename = "minLevel")]
+    pub min_level: Option<String>,
     #[serde(rename = "traceId")]
     pub trace_id: Option<String>,
+    /// Query language expression (see `query`)
+    pub query: Option<String>,
+    /// Subscribe with a saved search; the other parameters override it
+    pub saved: Option<String>,
 }
 
 use serde::Deserialize;
@@ -314,17 +1358,67 @@ async fn handle_ws(socket: WebSocket, params: WsParams, state: Arc<AppState>) {
 }
 
 /// Subscribe mode: stream matching logs to the client in real time.
-async fn handle_subscribe(socket: WebSocket, params: WsParams, state: Arc<AppState>) {
+async fn handle_subscribe(mut socket: WebSocket, mut params: WsParams, state: Arc<AppState>) {
+    if let Some(name) = params.saved.as_deref() {
+        let search = match saved::get(&state.meili_client, name).await {
+            Ok(Some(search)) => search,
+            Ok(None) => {
+                let msg = json!({ "type": "error", "error": format!("saved search {:?} not found", name) });
+                let _ = socket.send(Message::Text(msg.to_string().into())).await;
+                return;
+            }
+            Err(e) => {
+                let msg = json!({ "type": "error", "error": e.to_string() });
+                let _ = socket.send(Message::Text(msg.to_string().into())).await;
+                return;
+            }
+        };
+        let f = search.filters.clone();
+        params.query = saved::and_queries(
+            search.subscription_query().as_deref(),
+            params.query.as_deref(),
+        );
+        params.projects = params.projects.take().or(f.project);
+        params.levels = params.levels.take().or(f.level);
+        params.min_level = params.min_level.take().or(f.min_level);
+        params.trace_id = params.trace_id.take().or(f.trace_id);
+    }
+    let query = match params.query.as_deref().map(query::parse).transpose() {
+        Ok(q) => q,
+        Err(e) => {
+            let msg = json!({ "type": "error", "error": e.to_string(), "position": e.position });
+            let _ = socket.send(Message::Text(msg.to_string().into())).await;
+            return;
+        }
+    };
+    let levels = params
+        .levels
+        .as_deref()
+        .map(LogLevel::parse_list)
+        .transpose();
+    let min_level = params
+        .min_level
+        .as_deref()
+        .map(|m| LogLevel::parse(m).ok_or_else(|| format!("unknown level {:?}", m)))
+        .transpose();
+    let (levels, min_level) = match (levels, min_level) {
+        (Ok(levels), Ok(min_level)) => (levels.unwrap_or_default(), min_level),
+        (Err(e), _) | (_, Err(e)) => {
+            let msg = json!({ "type": "error", "error": e });
+            let _ = socket.send(Message::Text(msg.to_string().into())).await;
+            return;
+        }
+    };
     let filter = SubscriberFilter {
         projects: params
             .projects
             .map(|p| p.split(',').map(String::from).collect())
             .unwrap_or_default(),
-        levels: params
-            .levels
-            .map(|l| l.split(',').map(String::from).collect())
-            .unwrap_or_default(),
+        levels,
+        min_level,
         trace_id: params.trace_id,
+        query_text: params.query,
+        query,
     };
 
     let (sub_id, mut rx) = state.subscribers.subscribe(filter.clone());
@@ -339,7 +1433,9 @@ async fn handle_subscribe(socket: WebSocket, params: WsParams, state: Arc<AppSta
         "filters": {
             "projects": filter.projects,
             "levels": filter.levels,
+            "minLevel": filter.min_level,
             "traceId": filter.trace_id,
+            "query": filter.query_text,
         }
     }))
     .unwrap();
@@ -371,11 +1467,7 @@ async fn handle_ingest(socket: WebSocket, state: Arc<AppState>) {
         if let Message::Text(text) = msg {
             match serde_json::from_str::<IngestPayload>(&text) {
                 Ok(payload) => {
-                    for entry in payload.into_entries() {
-                        let entry = entry.normalize();
-                        state.subscribers.broadcast(&entry);
-                        let _ = state.meili_tx.send(entry);
-                    }
+                    state.ingest_entries(payload.into_entries());
                 }
                 Err(e) => {
                     tracing::warn!("Invalid WS message: {:?}", e);
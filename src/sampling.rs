@@ -0,0 +1,462 @@
+//! Server-side sampling and per-project ingest rate limits.
+//!
+//! Sampling rules keep a fraction of matching entries. The keep/drop decision
+//! hashes `traceId` (or the entry ID when there is none), so every entry of a
+//! sampled trace is either kept or dropped together. Rate limits are token
+//! buckets keyed by rule, project and level. Levels listed in `exemptLevels`
+//! bypass both.
+//!
+//! Project names come from clients, so buckets and counters are capped at
+//! [`MAX_KEYS`]. When full, buckets that have refilled and projects idle for
+//! [`IDLE`] are dropped; if that frees nothing, new projects share the
+//! [`OVERFLOW`] key.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{LogEntry, LogLevel};
+
+/// `sampling` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SamplingConfig {
+    /// Levels that are never sampled or rate limited
+    pub exempt_levels: Vec<LogLevel>,
+    /// Probabilistic sampling rules; the first matching rule applies
+    pub rules: Vec<SampleRule>,
+    /// Token-bucket limits; the first matching rule applies
+    pub rate_limits: Vec<RateLimitRule>,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            exempt_levels: vec![LogLevel::Error, LogLevel::Fatal],
+            rules: Vec::new(),
+            rate_limits: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleRule {
+    /// Project to match (any when omitted)
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Level to match (any when omitted)
+    #[serde(default)]
+    pub level: Option<LogLevel>,
+    /// `patternId` to match (any when omitted)
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Fraction of entries to keep, 0.0–1.0
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitRule {
+    /// Project to match (any when omitted; each project still gets its own bucket)
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Level to match (any when omitted)
+    #[serde(default)]
+    pub level: Option<LogLevel>,
+    /// `patternId` to match (any when omitted)
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Sustained entries per second
+    pub per_second: f64,
+    /// Bucket size (defaults to `perSecond`)
+    #[serde(default)]
+    pub burst: Option<f64>,
+}
+
+impl RateLimitRule {
+    fn burst(&self) -> f64 {
+        self.burst.unwrap_or(self.per_second).max(1.0)
+    }
+}
+
+/// Buckets, and projects with counters, tracked at most.
+const MAX_KEYS: usize = 10_000;
+/// Project key shared by new projects once [`MAX_KEYS`] is reached.
+const OVERFLOW: &str = "(other)";
+/// Counters of projects without entries for this long may be dropped.
+const IDLE: Duration = Duration::from_secs(3600);
+/// Pruning when full happens at most this often.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+fn rule_matches(
+    project: &Option<String>,
+    level: &Option<LogLevel>,
+    pattern: &Option<String>,
+    entry: &LogEntry,
+) -> bool {
+    project.as_deref().is_none_or(|p| p == entry.project)
+        && level.as_ref().is_none_or(|l| *l == entry.level)
+        && pattern
+            .as_deref()
+            .is_none_or(|p| entry.pattern_id.as_deref() == Some(p))
+}
+
+/// Why an entry was not kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Keep,
+    Sampled,
+    RateLimited,
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+#[derive(Default)]
+struct ProjectCounters {
+    accepted: AtomicU64,
+    sampled: AtomicU64,
+    rate_limited: AtomicU64,
+    /// Milliseconds after `Sampler::started` of the last entry
+    last_ms: AtomicU64,
+}
+
+/// Per-project ingest counters as reported by `GET /ingest/stats`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub accepted: u64,
+    pub sampled: u64,
+    pub rate_limited: u64,
+}
+
+pub struct Sampler {
+    cfg: SamplingConfig,
+    buckets: DashMap<(usize, String, String), Bucket>,
+    counters: DashMap<String, ProjectCounters>,
+    started: Instant,
+    last_prune: Mutex<Option<Instant>>,
+}
+
+impl Sampler {
+    pub fn new(cfg: &SamplingConfig) -> anyhow::Result<Self> {
+        for rule in &cfg.rules {
+            if !(0.0..=1.0).contains(&rule.rate) {
+                anyhow::bail!("sampling rate must be between 0 and 1, got {}", rule.rate);
+            }
+        }
+        for rule in &cfg.rate_limits {
+            if rule.per_second <= 0.0 {
+                anyhow::bail!(
+                    "rate limit perSecond must be positive, got {}",
+                    rule.per_second
+                );
+            }
+        }
+        Ok(Self {
+            cfg: cfg.clone(),
+            buckets: DashMap::new(),
+            counters: DashMap::new(),
+            started: Instant::now(),
+            last_prune: Mutex::new(None),
+        })
+    }
+
+    /// Decide whether to keep an entry and record the outcome.
+    pub fn check(&self, entry: &LogEntry) -> Decision {
+        self.check_at(entry, Instant::now())
+    }
+
+    fn check_at(&self, entry: &LogEntry, now: Instant) -> Decision {
+        let decision = self.decide(entry, now);
+        let project = if self.counters.contains_key(&entry.project)
+            || self.has_room(now, |s| s.counters.len())
+        {
+            entry.project.clone()
+        } else {
+            OVERFLOW.to_string()
+        };
+        let counters = self.counters.entry(project).or_default();
+        let counter = match decision {
+            Decision::Keep => &counters.accepted,
+            Decision::Sampled => &counters.sampled,
+            Decision::RateLimited => &counters.rate_limited,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        counters
+            .last_ms
+            .fetch_max(self.millis(now), Ordering::Relaxed);
+        decision
+    }
+
+    fn decide(&self, entry: &LogEntry, now: Instant) -> Decision {
+        if self.cfg.exempt_levels.contains(&entry.level) {
+            return Decision::Keep;
+        }
+
+        if let Some(rule) = self
+            .cfg
+            .rules
+            .iter()
+            .find(|r| rule_matches(&r.project, &r.level, &r.pattern, entry))
+        {
+            let key = entry.trace_id.as_deref().unwrap_or(&entry.id);
+            if unit_hash(key) >= rule.rate {
+                return Decision::Sampled;
+            }
+        }
+
+        if let Some((idx, rule)) = self
+            .cfg
+            .rate_limits
+            .iter()
+            .enumerate()
+            .find(|(_, r)| rule_matches(&r.project, &r.level, &r.pattern, entry))
+        {
+            let level = rule
+                .level
+                .as_ref()
+                .map(|_| entry.level.to_string())
+                .unwrap_or_default();
+            let burst = rule.burst();
+            let mut key = (idx, entry.project.clone(), level);
+            if !self.buckets.contains_key(&key) && !self.has_room(now, |s| s.buckets.len()) {
+                key.1 = OVERFLOW.to_string();
+            }
+            let mut bucket = self.buckets.entry(key).or_insert_with(|| Bucket {
+                tokens: burst,
+                last: now,
+            });
+            let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * rule.per_second).min(burst);
+            bucket.last = now;
+            if bucket.tokens < 1.0 {
+                return Decision::RateLimited;
+            }
+            bucket.tokens -= 1.0;
+        }
+
+        Decision::Keep
+    }
+
+    /// Whether a map of `len(self)` keys can take another one, pruning first
+    /// when it's full.
+    fn has_room(&self, now: Instant, len: impl Fn(&Self) -> usize) -> bool {
+        if len(self) < MAX_KEYS {
+            return true;
+        }
+        {
+            let mut last = self.last_prune.lock().unwrap();
+            if last.is_some_and(|t| now.saturating_duration_since(t) < PRUNE_INTERVAL) {
+                return false;
+            }
+            *last = Some(now);
+        }
+        self.prune(now);
+        len(self) < MAX_KEYS
+    }
+
+    /// Drop buckets that have refilled, which a new bucket would match, and
+    /// counters of projects idle for [`IDLE`].
+    fn prune(&self, now: Instant) {
+        self.buckets.retain(|(idx, _, _), bucket| {
+            let rule = &self.cfg.rate_limits[*idx];
+            let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+            bucket.tokens + elapsed * rule.per_second < rule.burst()
+        });
+        let cutoff = self.millis(now).saturating_sub(IDLE.as_millis() as u64);
+        self.counters
+            .retain(|_, c| c.last_ms.load(Ordering::Relaxed) >= cutoff);
+    }
+
+    fn millis(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_millis() as u64
+    }
+
+    /// Snapshot of the per-project counters.
+    pub fn stats(&self) -> BTreeMap<String, ProjectStats> {
+        self.counters
+            .iter()
+            .map(|c| {
+                (
+                    c.key().clone(),
+                    ProjectStats {
+                        accepted: c.accepted.load(Ordering::Relaxed),
+                        sampled: c.sampled.load(Ordering::Relaxed),
+                        rate_limited: c.rate_limited.load(Ordering::Relaxed),
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// Map a key to [0, 1) with FNV-1a, stable across restarts and instances.
+fn unit_hash(key: &str) -> f64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in key.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(project: &str, level: &str, trace_id: Option<&str>) -> LogEntry {
+        serde_json::from_value(serde_json::json!({
+            "project": project,
+            "level": level,
+            "message": "hello",
+            "traceId": trace_id,
+        }))
+        .unwrap()
+    }
+
+    fn rate_limited(per_second: f64, burst: f64) -> Sampler {
+        Sampler::new(&SamplingConfig {
+            rate_limits: vec![RateLimitRule {
+                project: None,
+                level: None,
+                pattern: None,
+                per_second,
+                burst: Some(burst),
+            }],
+            ..SamplingConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn exempt_levels_bypass_sampling_and_limits() {
+        let sampler = Sampler::new(&SamplingConfig {
+            rules: vec![SampleRule {
+                project: None,
+                level: None,
+                pattern: None,
+                rate: 0.0,
+            }],
+            rate_limits: vec![RateLimitRule {
+                project: None,
+                level: None,
+                pattern: None,
+                per_second: 1.0,
+                burst: None,
+            }],
+            ..SamplingConfig::default()
+        })
+        .unwrap();
+        for _ in 0..10 {
+            assert_eq!(sampler.check(&entry("api", "error", None)), Decision::Keep);
+            assert_eq!(sampler.check(&entry("api", "fatal", None)), Decision::Keep);
+        }
+        assert_eq!(
+            sampler.check(&entry("api", "info", None)),
+            Decision::Sampled
+        );
+        let stats = sampler.stats();
+        assert_eq!(stats["api"].accepted, 20);
+        assert_eq!(stats["api"].sampled, 1);
+    }
+
+    #[test]
+    fn sampling_keeps_or_drops_whole_traces() {
+        let config = SamplingConfig {
+            rules: vec![SampleRule {
+                project: None,
+                level: None,
+                pattern: None,
+                rate: 0.5,
+            }],
+            ..SamplingConfig::default()
+        };
+        let first = Sampler::new(&config).unwrap();
+        let second = Sampler::new(&config).unwrap();
+        let mut kept = 0;
+        for i in 0..1000 {
+            let trace = format!("trace-{}", i);
+            let decision = first.check(&entry("api", "info", Some(&trace)));
+            for level in ["debug", "info", "warn"] {
+                assert_eq!(first.check(&entry("api", level, Some(&trace))), decision);
+                assert_eq!(second.check(&entry("web", level, Some(&trace))), decision);
+            }
+            kept += (decision == Decision::Keep) as usize;
+        }
+        assert!((400..600).contains(&kept), "kept {}", kept);
+    }
+
+    #[test]
+    fn buckets_refill_at_the_configured_rate() {
+        let sampler = rate_limited(1.0, 2.0);
+        let t0 = Instant::now();
+        let at = |ms: u64, project: &str| {
+            sampler.check_at(
+                &entry(project, "info", None),
+                t0 + Duration::from_millis(ms),
+            )
+        };
+        assert_eq!(at(0, "api"), Decision::Keep);
+        assert_eq!(at(0, "api"), Decision::Keep);
+        assert_eq!(at(0, "api"), Decision::RateLimited);
+        // Each project has its own bucket
+        assert_eq!(at(0, "web"), Decision::Keep);
+        assert_eq!(at(500, "api"), Decision::RateLimited);
+        assert_eq!(at(1000, "api"), Decision::Keep);
+        assert_eq!(at(1500, "api"), Decision::RateLimited);
+        // Refills up to the burst size only
+        assert_eq!(at(60_000, "api"), Decision::Keep);
+        assert_eq!(at(60_000, "api"), Decision::Keep);
+        assert_eq!(at(60_000, "api"), Decision::RateLimited);
+    }
+
+    #[test]
+    fn new_projects_share_a_bucket_once_full() {
+        let sampler = rate_limited(1.0, 1.0);
+        let t0 = Instant::now();
+        for i in 0..MAX_KEYS {
+            sampler.check_at(&entry(&format!("p{}", i), "info", None), t0);
+        }
+        assert_eq!(sampler.buckets.len(), MAX_KEYS);
+        let check = |project: &str, ms: u64| {
+            sampler.check_at(
+                &entry(project, "info", None),
+                t0 + Duration::from_millis(ms),
+            )
+        };
+        assert_eq!(check("new-1", 0), Decision::Keep);
+        assert_eq!(check("new-2", 0), Decision::RateLimited);
+        assert!(sampler.buckets.len() <= MAX_KEYS + 1);
+        // Once the old buckets refill they are dropped to make room
+        assert_eq!(check("new-3", 2000), Decision::Keep);
+        assert_eq!(check("new-4", 2000), Decision::Keep);
+        assert!(sampler.buckets.len() < 10);
+    }
+
+    #[test]
+    fn idle_project_counters_make_room() {
+        let sampler = Sampler::new(&SamplingConfig::default()).unwrap();
+        let t0 = Instant::now();
+        for i in 0..MAX_KEYS {
+            sampler.check_at(&entry(&format!("p{}", i), "info", None), t0);
+        }
+        sampler.check_at(&entry("late", "info", None), t0);
+        let stats = sampler.stats();
+        assert_eq!(stats.len(), MAX_KEYS + 1);
+        assert_eq!(stats[OVERFLOW].accepted, 1);
+        assert!(!stats.contains_key("late"));
+
+        sampler.check_at(&entry("p0", "info", None), t0 + IDLE);
+        sampler.check_at(&entry("later", "info", None), t0 + IDLE + PRUNE_INTERVAL);
+        let stats = sampler.stats();
+        assert_eq!(stats.keys().collect::<Vec<_>>(), ["later", "p0"]);
+    }
+}
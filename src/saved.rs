@@ -0,0 +1,249 @@
+//! Saved searches: named `/search` queries stored in their own Meilisearch
+//! index, so the HTTP API, the CLI, MCP and WebSocket subscribers can all
+//! run them by name.
+
+use meilisearch_sdk::client::Client;
+use meilisearch_sdk::documents::DocumentsQuery;
+use meilisearch_sdk::errors::{Error, ErrorCode, MeilisearchError};
+use serde::{Deserialize, Serialize};
+
+use crate::meili::SAVED_SEARCHES_INDEX;
+use crate::models::SearchParams;
+use crate::query;
+
+const MAX_NAME_LEN: usize = 100;
+
+/// Structured `/search` filters of a saved search.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// One level or a comma-separated list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    /// Letters, digits, `-` and `_`; used as `@name`
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Query language expression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub filters: SavedFilters,
+    /// Default time range, used when a run doesn't give one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// Set by the server
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+impl SavedSearch {
+    /// Fill in what `params` leaves unset. A query in `params` narrows the
+    /// saved one rather than replacing it.
+    pub fn apply(&self, params: &mut SearchParams) {
+        let f = &self.filters;
+        params.query = and_queries(self.query.as_deref(), params.query.as_deref());
+        params.project = params.project.take().or_else(|| f.project.clone());
+        params.level = params.level.take().or_else(|| f.level.clone());
+        params.min_level = params.min_level.take().or_else(|| f.min_level.clone());
+        params.trace_id = params.trace_id.take().or_else(|| f.trace_id.clone());
+        params.request_id = params.request_id.take().or_else(|| f.request_id.clone());
+        params.environment = params.environment.take().or_else(|| f.environment.clone());
+        if params.since.is_none() && params.from.is_none() && params.to.is_none() {
+            params.since = self.since.clone();
+            params.from = self.from.clone();
+            params.to = self.to.clone();
+        }
+    }
+
+    /// The saved query plus the filters a live subscription has no
+    /// parameter for, as one query language expression.
+    pub fn subscription_query(&self) -> Option<String> {
+        let terms: Vec<String> = [
+            ("requestId", &self.filters.request_id),
+            ("environment", &self.filters.environment),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| {
+            value
+                .as_ref()
+                .map(|v| format!("{}:{}", field, query::quote(v)))
+        })
+        .collect();
+        let extra = (!terms.is_empty()).then(|| terms.join(" "));
+        and_queries(self.query.as_deref(), extra.as_deref())
+    }
+}
+
+/// Both expressions, ANDed; either alone when the other is missing.
+pub fn and_queries(a: Option<&str>, b: Option<&str>) -> Option<String> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(format!("({}) ({})", a, b)),
+        (a, b) => a.or(b).map(String::from),
+    }
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!(
+            "name must be 1 to {} characters long",
+            MAX_NAME_LEN
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "invalid name {:?}: use letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a Meilisearch error means the document or index doesn't exist.
+fn is_missing(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Meilisearch(MeilisearchError {
+            error_code: ErrorCode::DocumentNotFound
+                | ErrorCode::IndexNotFound
+                | ErrorCode::InvalidDocumentId,
+            ..
+        })
+    )
+}
+
+/// The saved search `name`; `None` when there is none. Invalid names are
+/// never looked up, since the SDK puts them into request paths unescaped.
+pub async fn get(client: &Client, name: &str) -> anyhow::Result<Option<SavedSearch>> {
+    if validate_name(name).is_err() {
+        return Ok(None);
+    }
+    let index = client.index(SAVED_SEARCHES_INDEX);
+    match index.get_document::<SavedSearch>(name).await {
+        Ok(saved) => Ok(Some(saved)),
+        Err(e) if is_missing(&e) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// All saved searches, by name; only `owner`'s when given.
+pub async fn list(client: &Client, owner: Option<&str>) -> anyhow::Result<Vec<SavedSearch>> {
+    let index = client.index(SAVED_SEARCHES_INDEX);
+    let mut searches = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = match DocumentsQuery::new(&index)
+            .with_offset(offset)
+            .with_limit(1000)
+            .execute::<SavedSearch>()
+            .await
+        {
+            Ok(page) => page,
+            Err(e) if is_missing(&e) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let fetched = page.results.len();
+        searches.extend(page.results);
+        offset += fetched;
+        if fetched == 0 || offset >= page.total as usize {
+            break;
+        }
+    }
+    searches.retain(|s| owner.is_none_or(|o| s.owner.as_deref() == Some(o)));
+    searches.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(searches)
+}
+
+/// Create or replace a saved search, waiting until it's readable.
+pub async fn put(client: &Client, saved: &SavedSearch) -> anyhow::Result<()> {
+    let index = client.index(SAVED_SEARCHES_INDEX);
+    let task = index
+        .add_or_replace(std::slice::from_ref(saved), Some("name"))
+        .await?;
+    let task = client.wait_for_task(task, None, None).await?;
+    if task.is_failure() {
+        anyhow::bail!(
+            "saving {:?} failed: {:?}",
+            saved.name,
+            task.unwrap_failure()
+        );
+    }
+    Ok(())
+}
+
+/// Delete a saved search; false when there was none.
+pub async fn delete(client: &Client, name: &str) -> anyhow::Result<bool> {
+    if validate_name(name).is_err() {
+        return Ok(false);
+    }
+    if get(client, name).await?.is_none() {
+        return Ok(false);
+    }
+    let index = client.index(SAVED_SEARCHES_INDEX);
+    let task = index.delete_document(name).await?;
+    let task = client.wait_for_task(task, None, None).await?;
+    if task.is_failure() {
+        anyhow::bail!("deleting {:?} failed: {:?}", name, task.unwrap_failure());
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{CmpOp, Expr};
+
+    #[test]
+    fn subscription_query_keeps_values_verbatim() {
+        for value in [r#"a"b"#, "trailing\\", r#"\" OR level:error"#] {
+            let search = SavedSearch {
+                name: "s".into(),
+                owner: None,
+                query: None,
+                filters: SavedFilters {
+                    request_id: Some(value.into()),
+                    ..Default::default()
+                },
+                since: None,
+                from: None,
+                to: None,
+                created_at: String::new(),
+                updated_at: String::new(),
+            };
+            let expr = query::parse(&search.subscription_query().unwrap()).unwrap();
+            assert_eq!(
+                expr,
+                Expr::Compare {
+                    field: "requestId".into(),
+                    op: CmpOp::Eq,
+                    value: value.into(),
+                }
+            );
+        }
+    }
+}
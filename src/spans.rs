@@ -0,0 +1,294 @@
+//! Span trees for `/trace/{id}?view=tree`.
+//!
+//! Entries are grouped by `spanId` and linked through `parentSpanId`. A span
+//! runs from its first to its last entry unless one of its entries reports
+//! `meta.durationMs`, in which case it ends at that entry and started that
+//! long before. Spans whose parent isn't in the trace are orphans.
+//!
+//! [`fetch`] reads up to [`MAX_ENTRIES`] entries of a trace; past that the
+//! tree is built from the newest ones and marked `truncated`.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use meilisearch_sdk::indexes::Index;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cursor::{self, PageError};
+use crate::filter::Filter;
+
+/// Entries read at most to build one tree.
+pub const MAX_ENTRIES: usize = 50_000;
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Span {
+    pub span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_span_id: Option<String>,
+    /// `meta.operation`, or the first entry's message
+    pub name: String,
+    pub project: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub duration_ms: i64,
+    /// `entries` (first to last entry) or `duration` (`meta.durationMs`)
+    pub timing: &'static str,
+    /// Whether any entry is an error or carries an exception
+    pub error: bool,
+    pub entry_count: usize,
+    /// Ordered by start
+    pub children: Vec<Span>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceTree {
+    pub trace_id: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub duration_ms: i64,
+    pub span_count: usize,
+    pub entry_count: usize,
+    /// Entries without a `spanId`
+    pub unspanned: usize,
+    /// Whether any entry is an error, in a span or not
+    pub error: bool,
+    /// Projects with entries in the trace
+    pub projects: BTreeSet<String>,
+    /// Spans without a parent, ordered by start
+    pub roots: Vec<Span>,
+    /// Spans whose parent isn't in the trace, with their subtrees
+    pub orphans: Vec<Span>,
+    /// True when the trace has more than [`MAX_ENTRIES`] entries and the
+    /// tree was built from the newest ones only
+    pub truncated: bool,
+}
+
+/// A span's entries, folded.
+struct Acc {
+    parent: Option<String>,
+    name: Option<String>,
+    first_message: String,
+    project: String,
+    first_ms: i64,
+    last_ms: i64,
+    /// Start and end from `meta.durationMs`
+    reported: Option<(i64, i64)>,
+    error: bool,
+    entries: usize,
+}
+
+impl Acc {
+    /// Start, end and where they came from.
+    fn bounds(&self) -> (i64, i64, &'static str) {
+        match self.reported {
+            Some((start, end)) => (start, end, "duration"),
+            None => (self.first_ms, self.last_ms, "entries"),
+        }
+    }
+
+    fn span(&self, span_id: &str, children: Vec<Span>) -> Span {
+        let (start_ms, end_ms, timing) = self.bounds();
+        Span {
+            span_id: span_id.to_string(),
+            parent_span_id: self.parent.clone(),
+            name: self
+                .name
+                .clone()
+                .unwrap_or_else(|| self.first_message.clone()),
+            project: self.project.clone(),
+            start_ms,
+            end_ms,
+            duration_ms: end_ms - start_ms,
+            timing,
+            error: self.error,
+            entry_count: self.entries,
+            children,
+        }
+    }
+}
+
+/// Build the span hierarchy of a trace's entries.
+pub fn build(trace_id: &str, entries: &[Value]) -> TraceTree {
+    let str_field = |entry: &Value, name: &str| {
+        entry
+            .get(name)
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+
+    let mut spans: HashMap<String, Acc> = HashMap::new();
+    let mut unspanned = 0;
+    let mut any_error = false;
+    let mut projects = BTreeSet::new();
+    let (mut first_ms, mut last_ms) = (i64::MAX, i64::MIN);
+    for entry in entries {
+        let ts = entry
+            .get("timestampMs")
+            .and_then(Value::as_i64)
+            .unwrap_or(0);
+        first_ms = first_ms.min(ts);
+        last_ms = last_ms.max(ts);
+        let error = matches!(
+            entry.get("level").and_then(Value::as_str),
+            Some("error" | "fatal")
+        ) || entry.get("error").is_some_and(|e| !e.is_null());
+        any_error |= error;
+        projects.extend(str_field(entry, "project"));
+        let Some(span_id) = str_field(entry, "spanId") else {
+            unspanned += 1;
+            continue;
+        };
+        let meta = entry.get("meta");
+
+        let acc = spans.entry(span_id).or_insert_with(|| Acc {
+            parent: None,
+            name: None,
+            first_message: str_field(entry, "message").unwrap_or_default(),
+            project: str_field(entry, "project").unwrap_or_default(),
+            first_ms: ts,
+            last_ms: ts,
+            reported: None,
+            error: false,
+            entries: 0,
+        });
+        acc.entries += 1;
+        acc.error |= error;
+        if ts < acc.first_ms {
+            acc.first_ms = ts;
+            acc.first_message = str_field(entry, "message").unwrap_or_default();
+        }
+        acc.last_ms = acc.last_ms.max(ts);
+        if acc.parent.is_none() {
+            acc.parent = str_field(entry, "parentSpanId");
+        }
+        if acc.name.is_none() {
+            acc.name = meta
+                .and_then(|m| m.get("operation"))
+                .and_then(Value::as_str)
+                .map(String::from);
+        }
+        if let Some(ms) = meta
+            .and_then(|m| m.get("durationMs"))
+            .and_then(Value::as_f64)
+        {
+            let duration = ms.round() as i64;
+            if acc
+                .reported
+                .is_none_or(|(start, end)| duration > end - start)
+            {
+                acc.reported = Some((ts - duration, ts));
+            }
+        }
+    }
+
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    let (mut roots, mut orphans) = (Vec::new(), Vec::new());
+    for (id, acc) in &spans {
+        match acc.parent.as_deref() {
+            Some(parent) if spans.contains_key(parent) => {
+                children.entry(parent).or_default().push(id)
+            }
+            Some(_) => orphans.push(id.as_str()),
+            None => roots.push(id.as_str()),
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let tree = |ids: Vec<&str>, visited: &mut HashSet<String>| {
+        let mut built: Vec<Span> = ids
+            .into_iter()
+            .filter_map(|id| subtree(id, &spans, &children, visited))
+            .collect();
+        built.sort_by(|a, b| {
+            a.start_ms
+                .cmp(&b.start_ms)
+                .then_with(|| a.span_id.cmp(&b.span_id))
+        });
+        built
+    };
+    let roots = tree(roots, &mut visited);
+    let mut orphans = tree(orphans, &mut visited);
+    // Parent cycles are unreachable from any root; report them as orphans
+    let mut unreached: Vec<&str> = spans
+        .keys()
+        .filter(|id| !visited.contains(*id))
+        .map(String::as_str)
+        .collect();
+    unreached.sort();
+    orphans.extend(tree(unreached, &mut visited));
+
+    for acc in spans.values() {
+        let (start, end, _) = acc.bounds();
+        first_ms = first_ms.min(start);
+        last_ms = last_ms.max(end);
+    }
+    if entries.is_empty() {
+        (first_ms, last_ms) = (0, 0);
+    }
+    TraceTree {
+        trace_id: trace_id.to_string(),
+        start_ms: first_ms,
+        end_ms: last_ms,
+        duration_ms: last_ms - first_ms,
+        span_count: spans.len(),
+        entry_count: entries.len(),
+        unspanned,
+        error: any_error,
+        projects,
+        roots,
+        orphans,
+        truncated: false,
+    }
+}
+
+/// Read every entry of a trace, up to [`MAX_ENTRIES`], and build its tree.
+pub async fn fetch(
+    index: &Index,
+    trace_id: &str,
+    sort: Option<&str>,
+) -> Result<TraceTree, PageError> {
+    let search = cursor::Search {
+        index,
+        text: "",
+        filters: vec![Filter::eq("traceId", trace_id)],
+        facets: &[],
+        totals: false,
+    };
+    let mut entries = Vec::new();
+    let complete = search
+        .scan(cursor::sort_field(sort), PAGE_SIZE, MAX_ENTRIES, |doc| {
+            entries.push(doc.clone())
+        })
+        .await?;
+    let mut tree = build(trace_id, &entries);
+    tree.truncated = !complete;
+    Ok(tree)
+}
+
+/// A span with its descendants; `None` if it was already placed.
+fn subtree(
+    id: &str,
+    spans: &HashMap<String, Acc>,
+    children: &HashMap<&str, Vec<&str>>,
+    visited: &mut HashSet<String>,
+) -> Option<Span> {
+    if !visited.insert(id.to_string()) {
+        return None;
+    }
+    let mut kids: Vec<Span> = children
+        .get(id)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| subtree(child, spans, children, visited))
+        .collect();
+    kids.sort_by(|a, b| {
+        a.start_ms
+            .cmp(&b.start_ms)
+            .then_with(|| a.span_id.cmp(&b.span_id))
+    });
+    Some(spans[id].span(id, kids))
+}
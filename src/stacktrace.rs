@@ -0,0 +1,374 @@
+//! Structured exceptions and stack trace parsing.
+//!
+//! SDKs send `error.stack` as the raw text their runtime produced. At ingest
+//! it is parsed into `error.frames` so frames can be fingerprinted and
+//! rendered the same way regardless of language. Supported formats:
+//! JavaScript (V8 and Firefox), Python, Java, Go and Rust.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The `error` field of a log entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorInfo {
+    /// Exception class, e.g. `TypeError`, `java.io.IOException`
+    #[serde(
+        rename = "type",
+        alias = "name",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub error_type: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Raw stack trace text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<String>,
+
+    /// Frames parsed from `stack`, innermost first (set at ingest)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<StackFrame>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackFrame {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+}
+
+impl StackFrame {
+    /// Function and file without line/column, stable across deploys.
+    pub fn signature(&self) -> String {
+        format!(
+            "{}@{}",
+            self.function.as_deref().unwrap_or("?"),
+            self.file.as_deref().unwrap_or("?")
+        )
+    }
+
+    /// Runtime and third-party frames (panic machinery, `node_modules`,
+    /// JDK internals), skipped when picking the frame to fingerprint.
+    pub fn is_library(&self) -> bool {
+        const FUNCTIONS: &[&str] = &[
+            "std::",
+            "core::",
+            "alloc::",
+            "rust_begin_unwind",
+            "__rust",
+            "runtime.",
+            "java.",
+            "jdk.",
+            "sun.",
+        ];
+        const FILES: &[&str] = &[
+            "node_modules/",
+            "node:internal",
+            "/rustc/",
+            "/site-packages/",
+            "/usr/lib/python",
+        ];
+        self.function
+            .as_deref()
+            .is_some_and(|f| FUNCTIONS.iter().any(|p| f.starts_with(p)))
+            || self
+                .file
+                .as_deref()
+                .is_some_and(|f| FILES.iter().any(|p| f.contains(p)))
+    }
+}
+
+impl std::fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "at {}",
+            self.function.as_deref().unwrap_or("<anonymous>")
+        )?;
+        if let Some(file) = &self.file {
+            write!(f, " ({}", file)?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+                if let Some(col) = self.column {
+                    write!(f, ":{}", col)?;
+                }
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl ErrorInfo {
+    /// Parse `stack` into `frames` unless the client already sent frames.
+    /// Runs after redaction, so frames only hold what survived it.
+    pub fn parse_frames(&mut self) {
+        if self.frames.is_empty() {
+            if let Some(stack) = &self.stack {
+                self.frames = parse_stack(stack);
+            }
+        }
+    }
+}
+
+/// `File "app.py", line 10, in handler`
+static PYTHON_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^File "([^"]+)", line (\d+)(?:, in (.+))?$"#).unwrap());
+/// `at com.example.Foo.bar(Foo.java:42)` — no space before the parenthesis
+static JAVA_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^at ([\w$.<>/]+)\(([^():]+)(?::(\d+))?\)$").unwrap());
+/// `at handler (/app/server.js:10:5)`, `at /app/server.js:10:5`, and Rust's
+/// `at ./src/main.rs:10:5` location lines
+static V8_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^at (?:(?:async )?(.+?) \()?(.+?):(\d+)(?::(\d+))?\)?$").unwrap()
+});
+/// `handler@https://example.com/app.js:10:5`
+static FIREFOX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.*?)@(.+?):(\d+)(?::(\d+))?$").unwrap());
+/// `/app/main.go:42 +0x1d`
+static GO_FILE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\S+\.go):(\d+)(?: \+0x[0-9a-f]+)?$").unwrap());
+/// `main.(*Server).handle(0xc000010000, 0x1)`
+static GO_FUNC_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\S+\.\S+?)\([^()]*\)$").unwrap());
+/// `  3: app::handler::h1a2b3c4d5e6f7a8b`
+static RUST_FUNC_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d+: (.+?)(?:::h[0-9a-f]{16})?$").unwrap());
+
+/// Parse a stack trace into frames. Lines that aren't frames (the exception
+/// header, `Caused by:`, `... 5 more`, goroutine headers) are skipped.
+pub fn parse_stack(stack: &str) -> Vec<StackFrame> {
+    let mut frames = Vec::new();
+    // Go and Rust put the function and its location on separate lines
+    let mut pending: Option<String> = None;
+    let mut outermost_first = false;
+
+    for line in stack.lines().map(str::trim) {
+        if let Some(c) = PYTHON_RE.captures(line) {
+            outermost_first = true;
+            frames.push(StackFrame {
+                function: c.get(3).map(|m| m.as_str().to_string()),
+                file: Some(c[1].to_string()),
+                line: c[2].parse().ok(),
+                column: None,
+            });
+        } else if let Some(c) = JAVA_RE.captures(line) {
+            frames.push(StackFrame {
+                function: Some(c[1].to_string()),
+                file: Some(c[2].to_string()),
+                line: c.get(3).and_then(|m| m.as_str().parse().ok()),
+                column: None,
+            });
+        } else if let Some(c) = V8_RE.captures(line) {
+            frames.push(StackFrame {
+                function: c
+                    .get(1)
+                    .map(|m| m.as_str().to_string())
+                    .or_else(|| pending.take()),
+                file: Some(c[2].to_string()),
+                line: c[3].parse().ok(),
+                column: c.get(4).and_then(|m| m.as_str().parse().ok()),
+            });
+        } else if let Some(c) = GO_FILE_RE.captures(line) {
+            frames.push(StackFrame {
+                function: pending.take(),
+                file: Some(c[1].to_string()),
+                line: c[2].parse().ok(),
+                column: None,
+            });
+        } else if let Some(c) = RUST_FUNC_RE
+            .captures(line)
+            .or_else(|| GO_FUNC_RE.captures(line))
+            .filter(|_| !outermost_first)
+        {
+            // A function with no location line (e.g. Rust std frames)
+            if let Some(function) = pending.replace(c[1].to_string()) {
+                frames.push(StackFrame {
+                    function: Some(function),
+                    ..Default::default()
+                });
+            }
+        } else if let Some(c) = FIREFOX_RE.captures(line) {
+            let function = &c[1];
+            frames.push(StackFrame {
+                function: (!function.is_empty()).then(|| function.to_string()),
+                file: Some(c[2].to_string()),
+                line: c[3].parse().ok(),
+                column: c.get(4).and_then(|m| m.as_str().parse().ok()),
+            });
+        }
+    }
+    if let Some(function) = pending {
+        frames.push(StackFrame {
+            function: Some(function),
+            ..Default::default()
+        });
+    }
+
+    // Python prints the most recent call last
+    if outermost_first {
+        frames.reverse();
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(
+        function: Option<&str>,
+        file: Option<&str>,
+        line: Option<u32>,
+        column: Option<u32>,
+    ) -> StackFrame {
+        StackFrame {
+            function: function.map(String::from),
+            file: file.map(String::from),
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn python() {
+        let stack = "Traceback (most recent call last):
+  File \"/app/main.py\", line 10, in <module>
+    main()
+  File \"/app/handlers.py\", line 42, in handle
+    raise ValueError(\"bad\")
+ValueError: bad";
+        assert_eq!(
+            parse_stack(stack),
+            [
+                frame(Some("handle"), Some("/app/handlers.py"), Some(42), None),
+                frame(Some("<module>"), Some("/app/main.py"), Some(10), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn java() {
+        let stack = "java.io.IOException: closed
+\tat com.example.Db.query(Db.java:42)
+\tat com.example.Server$Handler.run(Server.java)
+Caused by: java.net.SocketException
+\t... 5 more";
+        assert_eq!(
+            parse_stack(stack),
+            [
+                frame(
+                    Some("com.example.Db.query"),
+                    Some("Db.java"),
+                    Some(42),
+                    None
+                ),
+                frame(
+                    Some("com.example.Server$Handler.run"),
+                    Some("Server.java"),
+                    None,
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn v8() {
+        let stack = "TypeError: x is undefined
+    at handler (/app/server.js:10:5)
+    at async Promise.all (index 0)
+    at async run (/app/index.js:3:1)
+    at /app/anon.js:7:12";
+        assert_eq!(
+            parse_stack(stack),
+            [
+                frame(Some("handler"), Some("/app/server.js"), Some(10), Some(5)),
+                frame(Some("run"), Some("/app/index.js"), Some(3), Some(1)),
+                frame(None, Some("/app/anon.js"), Some(7), Some(12)),
+            ]
+        );
+    }
+
+    #[test]
+    fn firefox() {
+        let stack = "handler@https://example.com/app.js:10:5
+@https://example.com/app.js:20:1";
+        assert_eq!(
+            parse_stack(stack),
+            [
+                frame(
+                    Some("handler"),
+                    Some("https://example.com/app.js"),
+                    Some(10),
+                    Some(5)
+                ),
+                frame(None, Some("https://example.com/app.js"), Some(20), Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn go() {
+        let stack = "goroutine 1 [running]:
+main.(*Server).handle(0xc000010000, 0x1)
+\t/app/server.go:42 +0x1d
+main.main()
+\t/app/main.go:10 +0x25";
+        assert_eq!(
+            parse_stack(stack),
+            [
+                frame(
+                    Some("main.(*Server).handle"),
+                    Some("/app/server.go"),
+                    Some(42),
+                    None
+                ),
+                frame(Some("main.main"), Some("/app/main.go"), Some(10), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn rust() {
+        let stack = "   0: std::panicking::begin_panic
+   1: app::handler::h1a2b3c4d5e6f7a8b
+             at ./src/handler.rs:10:5
+   2: app::main
+             at ./src/main.rs:3:1";
+        assert_eq!(
+            parse_stack(stack),
+            [
+                frame(Some("std::panicking::begin_panic"), None, None, None),
+                frame(
+                    Some("app::handler"),
+                    Some("./src/handler.rs"),
+                    Some(10),
+                    Some(5)
+                ),
+                frame(Some("app::main"), Some("./src/main.rs"), Some(3), Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn client_frames_are_kept() {
+        let mut error = ErrorInfo {
+            stack: Some("at handler (/app/server.js:10:5)".into()),
+            frames: vec![frame(Some("sent"), None, None, None)],
+            ..Default::default()
+        };
+        error.parse_frames();
+        assert_eq!(error.frames, [frame(Some("sent"), None, None, None)]);
+    }
+}
@@ -0,0 +1,389 @@
+//! Field aggregations for `/stats`.
+//!
+//! `top` and `count_distinct` on a filterable field come straight from
+//! Meilisearch facets while the field has fewer distinct values than a facet
+//! returns. Everything else walks the matching entries page by page, up to
+//! [`MAX_SCAN`] of them, and reports whether the scan covered every match.
+
+use std::collections::HashMap;
+
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::cursor::{self, PageError};
+use crate::filter::Filter;
+use crate::meili::MAX_VALUES_PER_FACET;
+use crate::query;
+
+/// Entries scanned at most for one request.
+pub const MAX_SCAN: usize = 100_000;
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Agg {
+    Top,
+    CountDistinct,
+    Min,
+    Max,
+    Avg,
+    P50,
+    P95,
+    P99,
+}
+
+impl Agg {
+    const ALL: [Agg; 8] = [
+        Agg::Top,
+        Agg::CountDistinct,
+        Agg::Min,
+        Agg::Max,
+        Agg::Avg,
+        Agg::P50,
+        Agg::P95,
+        Agg::P99,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Agg::Top => "top",
+            Agg::CountDistinct => "count_distinct",
+            Agg::Min => "min",
+            Agg::Max => "max",
+            Agg::Avg => "avg",
+            Agg::P50 => "p50",
+            Agg::P95 => "p95",
+            Agg::P99 => "p99",
+        }
+    }
+
+    /// Parse a comma-separated list such as `top,count_distinct`.
+    pub fn parse_list(s: &str) -> Result<Vec<Agg>, String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(|a| {
+                Agg::ALL
+                    .into_iter()
+                    .find(|agg| agg.as_str() == a)
+                    .ok_or_else(|| {
+                        let names: Vec<&str> = Agg::ALL.iter().map(|a| a.as_str()).collect();
+                        format!("unknown agg {:?}; expected one of: {}", a, names.join(", "))
+                    })
+            })
+            .collect()
+    }
+
+    fn facetable(self) -> bool {
+        matches!(self, Agg::Top | Agg::CountDistinct)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopValue {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub field: String,
+    /// `facets` or `scan`
+    pub source: &'static str,
+    /// Entries matching the filters
+    pub matched: usize,
+    /// Entries read when scanning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scanned: Option<usize>,
+    /// Scanned entries without the field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing: Option<usize>,
+    /// Values that aren't numbers, when numeric aggs were asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_numeric: Option<usize>,
+    /// False when the scan stopped at `MAX_SCAN` before the last match
+    pub complete: bool,
+    pub stats: Map<String, Value>,
+}
+
+/// What to aggregate over.
+pub struct Request<'a> {
+    pub index: &'a Index,
+    pub text: &'a str,
+    pub filters: Vec<Filter>,
+    pub field: &'a str,
+    pub aggs: &'a [Agg],
+    /// Values returned by `top`
+    pub limit: usize,
+    /// Whether `field` is a filterable attribute, so it can be faceted
+    pub filterable: bool,
+}
+
+impl Request<'_> {
+    pub async fn run(self) -> Result<Stats, PageError> {
+        if facetable(self.filterable, self.aggs) {
+            if let Some(stats) = self.faceted().await? {
+                return Ok(stats);
+            }
+        }
+        self.scan().await
+    }
+
+    /// Exact answers from the facet distribution, or `None` when it may
+    /// have been cut off.
+    async fn faceted(&self) -> Result<Option<Stats>, PageError> {
+        let mut distribution = self.facets(&[self.field, "level"]).await?;
+        let matched = level_total(&distribution);
+        let counts = distribution.remove(self.field).unwrap_or_default();
+        let Some(stats) = facet_stats(self.aggs, counts, self.limit) else {
+            return Ok(None);
+        };
+        Ok(Some(Stats {
+            field: self.field.to_string(),
+            source: "facets",
+            matched,
+            scanned: None,
+            missing: None,
+            non_numeric: None,
+            complete: true,
+            stats,
+        }))
+    }
+
+    async fn scan(&self) -> Result<Stats, PageError> {
+        let search = cursor::Search {
+            index: self.index,
+            text: self.text,
+            filters: self.filters.clone(),
+            facets: &[],
+            totals: false,
+        };
+        let mut tally = Tally::default();
+        let matched = level_total(&self.facets(&["level"]).await?);
+
+        let complete = search
+            .scan(cursor::sort_field(None), PAGE_SIZE, MAX_SCAN, |doc| {
+                tally.add(query::lookup(doc, self.field))
+            })
+            .await?;
+
+        let numeric = self.aggs.iter().any(|a| !a.facetable());
+        Ok(Stats {
+            field: self.field.to_string(),
+            source: "scan",
+            matched,
+            scanned: Some(tally.scanned),
+            missing: Some(tally.missing),
+            non_numeric: numeric.then_some(tally.non_numeric),
+            complete,
+            stats: tally.stats(self.aggs, self.limit),
+        })
+    }
+
+    /// Facet distribution of the whole query, without hits.
+    async fn facets(&self, fields: &[&str]) -> Result<Distribution, PageError> {
+        let filter = crate::filter::render_all(self.filters.iter().cloned())?;
+        let mut search = self.index.search();
+        search.with_query(self.text);
+        search.with_limit(0);
+        search.with_facets(Selectors::Some(fields));
+        if let Some(ref f) = filter {
+            search.with_filter(f);
+        }
+        let results = search.execute::<Value>().await?;
+        Ok(results.facet_distribution.unwrap_or_default())
+    }
+}
+
+type Distribution = HashMap<String, HashMap<String, usize>>;
+
+/// Whether facets can answer `aggs`: only `top` and `count_distinct` can,
+/// and only on a filterable field.
+fn facetable(filterable: bool, aggs: &[Agg]) -> bool {
+    filterable && aggs.iter().all(|a| a.facetable())
+}
+
+/// `aggs` from a field's facet counts, or `None` when the facet may have
+/// been cut off and a scan has to count instead.
+fn facet_stats(
+    aggs: &[Agg],
+    counts: HashMap<String, usize>,
+    limit: usize,
+) -> Option<Map<String, Value>> {
+    if counts.len() >= MAX_VALUES_PER_FACET {
+        return None;
+    }
+    let mut stats = Map::new();
+    for agg in aggs {
+        let value = match agg {
+            Agg::Top => json!(top(counts.clone(), limit)),
+            _ => json!(counts.len()),
+        };
+        stats.insert(agg.as_str().to_string(), value);
+    }
+    Some(stats)
+}
+
+/// Values of the field seen by a scan.
+#[derive(Default)]
+struct Tally {
+    counts: HashMap<String, usize>,
+    numbers: Vec<f64>,
+    scanned: usize,
+    missing: usize,
+    non_numeric: usize,
+}
+
+impl Tally {
+    fn add(&mut self, value: Option<&Value>) {
+        self.scanned += 1;
+        match value {
+            None | Some(Value::Null) => self.missing += 1,
+            Some(value) => {
+                match value.as_f64() {
+                    Some(n) => self.numbers.push(n),
+                    None => self.non_numeric += 1,
+                }
+                let key = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                *self.counts.entry(key).or_default() += 1;
+            }
+        }
+    }
+
+    fn stats(mut self, aggs: &[Agg], limit: usize) -> Map<String, Value> {
+        self.numbers.sort_by(f64::total_cmp);
+        let numbers = &self.numbers;
+        let mut stats = Map::new();
+        for agg in aggs {
+            let value = match agg {
+                Agg::Top => json!(top(self.counts.clone(), limit)),
+                Agg::CountDistinct => json!(self.counts.len()),
+                Agg::Min => json!(numbers.first()),
+                Agg::Max => json!(numbers.last()),
+                Agg::Avg => json!((!numbers.is_empty())
+                    .then(|| numbers.iter().sum::<f64>() / numbers.len() as f64)),
+                Agg::P50 => json!(percentile(numbers, 50.0)),
+                Agg::P95 => json!(percentile(numbers, 95.0)),
+                Agg::P99 => json!(percentile(numbers, 99.0)),
+            };
+            stats.insert(agg.as_str().to_string(), value);
+        }
+        stats
+    }
+}
+
+/// Matches counted from the `level` facet, which every entry has and which
+/// is exact where `estimatedTotalHits` isn't.
+fn level_total(distribution: &Distribution) -> usize {
+    distribution
+        .get("level")
+        .map_or(0, |levels| levels.values().sum())
+}
+
+/// The `limit` most frequent values, ties broken by value.
+fn top(counts: HashMap<String, usize>, limit: usize) -> Vec<TopValue> {
+    let mut values: Vec<TopValue> = counts
+        .into_iter()
+        .map(|(value, count)| TopValue { value, count })
+        .collect();
+    values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    values.truncate(limit);
+    values
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.saturating_sub(1)).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(&values, 0.0), Some(1.0));
+        assert_eq!(percentile(&values, 50.0), Some(50.0));
+        assert_eq!(percentile(&values, 95.0), Some(95.0));
+        assert_eq!(percentile(&values, 99.0), Some(99.0));
+        assert_eq!(percentile(&values, 100.0), Some(100.0));
+
+        let values = [10.0, 20.0, 30.0, 40.0];
+        assert_eq!(percentile(&values, 0.0), Some(10.0));
+        assert_eq!(percentile(&values, 25.0), Some(10.0));
+        assert_eq!(percentile(&values, 26.0), Some(20.0));
+        assert_eq!(percentile(&values, 50.0), Some(20.0));
+        assert_eq!(percentile(&values, 99.0), Some(40.0));
+        assert_eq!(percentile(&values, 100.0), Some(40.0));
+
+        for p in [0.0, 50.0, 99.0, 100.0] {
+            assert_eq!(percentile(&[7.5], p), Some(7.5));
+        }
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn facets_answer_only_top_and_count_distinct_on_filterable_fields() {
+        assert!(facetable(true, &[Agg::Top]));
+        assert!(facetable(true, &[Agg::Top, Agg::CountDistinct]));
+        assert!(!facetable(false, &[Agg::Top]));
+        assert!(!facetable(true, &[Agg::Top, Agg::P95]));
+        assert!(!facetable(true, &[Agg::Min]));
+    }
+
+    #[test]
+    fn full_facets_fall_back_to_a_scan() {
+        let counts: HashMap<String, usize> =
+            HashMap::from([("a".into(), 5), ("b".into(), 9), ("c".into(), 5)]);
+        let stats = facet_stats(&[Agg::Top, Agg::CountDistinct], counts, 2).unwrap();
+        assert_eq!(stats["count_distinct"], json!(3));
+        assert_eq!(
+            stats["top"],
+            json!([{ "value": "b", "count": 9 }, { "value": "a", "count": 5 }])
+        );
+
+        let full: HashMap<String, usize> = (0..MAX_VALUES_PER_FACET)
+            .map(|i| (i.to_string(), 1))
+            .collect();
+        assert!(facet_stats(&[Agg::CountDistinct], full, 10).is_none());
+    }
+
+    #[test]
+    fn scans_tally_numbers_and_missing_values() {
+        let mut tally = Tally::default();
+        for value in [
+            json!(3),
+            json!(1),
+            json!("slow"),
+            Value::Null,
+            json!(2.5),
+            json!(3),
+        ] {
+            tally.add(Some(&value));
+        }
+        tally.add(None);
+        assert_eq!((tally.scanned, tally.missing, tally.non_numeric), (7, 2, 1));
+
+        let aggs = [Agg::CountDistinct, Agg::Min, Agg::Max, Agg::Avg, Agg::P50];
+        let stats = tally.stats(&aggs, 10);
+        assert_eq!(stats["count_distinct"], json!(4));
+        assert_eq!(stats["min"], json!(1.0));
+        assert_eq!(stats["max"], json!(3.0));
+        assert_eq!(stats["avg"], json!(2.375));
+        assert_eq!(stats["p50"], json!(2.5));
+
+        let stats = Tally::default().stats(&[Agg::Avg, Agg::P99], 10);
+        assert_eq!(stats["avg"], Value::Null);
+        assert_eq!(stats["p99"], Value::Null);
+    }
+}
@@ -4,7 +4,9 @@ use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
-use crate::models::LogEntry;
+use crate::issues::Issue;
+use crate::models::{LogEntry, LogLevel};
+use crate::query::{self, Expr};
 
 /// A subscriber watching logs in real time via WebSocket.
 #[derive(Debug, Clone, Deserialize)]
@@ -12,9 +14,17 @@ pub struct SubscriberFilter {
     #[serde(default)]
     pub projects: Vec<String>,
     #[serde(default)]
-    pub levels: Vec<String>,
+    pub levels: Vec<LogLevel>,
+    /// Only entries at least this severe
+    #[serde(default)]
+    pub min_level: Option<LogLevel>,
     #[serde(default)]
     pub trace_id: Option<String>,
+    /// Query language expression as sent by the client
+    #[serde(default)]
+    pub query_text: Option<String>,
+    #[serde(skip)]
+    pub query: Option<Expr>,
 }
 
 impl SubscriberFilter {
@@ -22,8 +32,10 @@ impl SubscriberFilter {
         if !self.projects.is_empty() && !self.projects.contains(&entry.project) {
             return false;
         }
-        let level_str = entry.level.to_string();
-        if !self.levels.is_empty() && !self.levels.contains(&level_str) {
+        if !self.levels.is_empty() && !self.levels.contains(&entry.level) {
+            return false;
+        }
+        if self.min_level.as_ref().is_some_and(|m| entry.level < *m) {
             return false;
         }
         if let Some(ref tid) = self.trace_id {
@@ -31,16 +43,19 @@ impl SubscriberFilter {
                 return false;
             }
         }
+        if let Some(ref query) = self.query {
+            return query::matches(query, entry);
+        }
         true
     }
 }
 
 /// Message sent to a subscriber.
 #[derive(Serialize)]
-pub struct LogEvent {
+pub struct LogEvent<T: Serialize> {
     #[serde(rename = "type")]
     pub event_type: String,
-    pub data: LogEntry,
+    pub data: T,
 }
 
 /// Manages all live WebSocket subscribers.
@@ -78,9 +93,28 @@ impl SubscriberManager {
 
     /// Broadcast a log entry to all matching subscribers.
     pub fn broadcast(&self, entry: &LogEntry) {
+        self.broadcast_event("log", entry);
+    }
+
+    /// Broadcast an updated repeat count for a collapsed entry.
+    pub fn broadcast_repeat(&self, entry: &LogEntry) {
+        self.broadcast_event("repeat", entry);
+    }
+
+    /// Broadcast a regression of a resolved issue. Subscribers are matched
+    /// against the issue's latest entry.
+    pub fn broadcast_regression(&self, issue: &Issue) {
+        self.send_matching("regression", issue, &issue.sample);
+    }
+
+    fn broadcast_event(&self, event_type: &str, entry: &LogEntry) {
+        self.send_matching(event_type, entry, entry);
+    }
+
+    fn send_matching<T: Serialize>(&self, event_type: &str, data: &T, entry: &LogEntry) {
         let payload = serde_json::to_string(&LogEvent {
-            event_type: "log".into(),
-            data: entry.clone(),
+            event_type: event_type.into(),
+            data,
         })
         .unwrap_or_default();
 
@@ -88,10 +122,8 @@ impl SubscriberManager {
 
         for entry_ref in self.subs.iter() {
             let (id, (filter, tx)) = entry_ref.pair();
-            if filter.matches(entry) {
-                if tx.send(payload.clone()).is_err() {
-                    dead.push(*id);
-                }
+            if filter.matches(entry) && tx.send(payload.clone()).is_err() {
+                dead.push(*id);
             }
         }
 
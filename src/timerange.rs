@@ -0,0 +1,323 @@
+//! Time range parsing shared by the HTTP routes, the query language and MCP.
+//!
+//! Points in time (`from`, `to`) accept RFC 3339 timestamps, plain dates,
+//! epoch seconds (up to 10 digits) or milliseconds, `now`, `today`, `yesterday`, `now-2h` / `now+15m`, and
+//! bare durations meaning "that long ago". Durations chain units:
+//! `90s`, `1h30m`, `2d12h`, `1w`.
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::filter::{Filter, Op};
+
+const UNITS: &[(&str, i64)] = &[
+    ("ms", 1),
+    ("s", 1_000),
+    ("m", 60_000),
+    ("h", 3_600_000),
+    ("d", 86_400_000),
+    ("w", 604_800_000),
+];
+
+/// Parse a duration such as `5m`, `1h30m` or `2d` into milliseconds.
+pub fn parse_duration(s: &str) -> Option<i64> {
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut total: i64 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return None;
+        }
+        let n: i64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        // Longest matching unit, so `ms` wins over `m`
+        let (unit, ms) = UNITS
+            .iter()
+            .filter(|(u, _)| rest.starts_with(u))
+            .max_by_key(|(u, _)| u.len())?;
+        total = total.checked_add(n.checked_mul(*ms)?)?;
+        rest = &rest[unit.len()..];
+    }
+    Some(total)
+}
+
+/// Format milliseconds in the units `parse_duration` reads, e.g. `1h30m`.
+pub fn format_duration(ms: i64) -> String {
+    if ms <= 0 {
+        return "0ms".to_string();
+    }
+    let mut rest = ms;
+    let mut out = String::new();
+    for (unit, size) in UNITS.iter().rev() {
+        if rest >= *size {
+            out.push_str(&format!("{}{}", rest / size, unit));
+            rest %= size;
+        }
+    }
+    out
+}
+
+/// Parse a point in time into epoch milliseconds, relative to `now`.
+pub fn parse_time(s: &str, now: DateTime<Utc>) -> Result<i64, String> {
+    let s = s.trim();
+    parse_ms(s, now)
+        // Only times chrono can represent, so callers can do arithmetic on them
+        .filter(|&ms| DateTime::from_timestamp_millis(ms).is_some())
+        .ok_or_else(|| {
+            format!(
+                "invalid time {:?}: expected RFC 3339, epoch seconds or millis, now-2h, yesterday or a duration like 1h30m",
+                s
+            )
+        })
+}
+
+fn parse_ms(s: &str, now: DateTime<Utc>) -> Option<i64> {
+    let now_ms = now.timestamp_millis();
+    let start_of_day = now
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .map(|d| d.and_utc().timestamp_millis());
+
+    match s {
+        "now" => return Some(now_ms),
+        "today" => return start_of_day,
+        "yesterday" => return start_of_day.map(|d| d - 86_400_000),
+        _ => {}
+    }
+    if let Some(rel) = s.strip_prefix("now") {
+        return match rel.split_at_checked(1)? {
+            ("-", d) => now_ms.checked_sub(parse_duration(d)?),
+            ("+", d) => now_ms.checked_add(parse_duration(d)?),
+            _ => None,
+        };
+    }
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        let n: i64 = s.parse().ok()?;
+        // Epoch seconds until 2286; anything longer is milliseconds
+        return Some(if s.len() <= 10 { n * 1000 } else { n });
+    }
+    if let Some(ms) = parse_duration(s) {
+        return now_ms.checked_sub(ms);
+    }
+    if let Ok(dt) = s.parse::<DateTime<Utc>>() {
+        return Some(dt.timestamp_millis());
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis())
+}
+
+/// A time window in epoch milliseconds; either end may be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl TimeRange {
+    /// Build a range from the `since`, `from` and `to` parameters. `since`
+    /// is a duration and is an alternative to `from`.
+    pub fn parse(
+        since: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Self, String> {
+        let now = Utc::now();
+        let from = match (since, from) {
+            (Some(_), Some(_)) => return Err("use either since or from, not both".into()),
+            (Some(s), None) => Some(
+                parse_duration(s)
+                    .and_then(|ms| now.timestamp_millis().checked_sub(ms))
+                    .filter(|&ms| DateTime::from_timestamp_millis(ms).is_some())
+                    .ok_or_else(|| format!("invalid duration {:?}", s))?,
+            ),
+            (None, Some(f)) => Some(parse_time(f, now)?),
+            (None, None) => None,
+        };
+        let to = to.map(|t| parse_time(t, now)).transpose()?;
+        if let (Some(f), Some(t)) = (from, to) {
+            if f > t {
+                return Err("from must not be after to".into());
+            }
+        }
+        Ok(Self { from, to })
+    }
+
+    /// Parse a closed window written `from..to`, such as `now-2h..now-1h`.
+    /// An empty `to` means now.
+    pub fn parse_window(s: &str, now: DateTime<Utc>) -> Result<Self, String> {
+        let (from, to) = s
+            .split_once("..")
+            .ok_or_else(|| format!("invalid window {:?}: expected from..to", s))?;
+        let from = parse_time(from, now)?;
+        let to = match to.trim() {
+            "" => now.timestamp_millis(),
+            t => parse_time(t, now)?,
+        };
+        if from >= to {
+            return Err(format!("invalid window {:?}: from must be before to", s));
+        }
+        Ok(Self {
+            from: Some(from),
+            to: Some(to),
+        })
+    }
+
+    pub fn contains(&self, ms: i64) -> bool {
+        self.from.is_none_or(|f| ms >= f) && self.to.is_none_or(|t| ms <= t)
+    }
+
+    /// Clauses restricting `field` to the range.
+    pub fn filters(&self, field: &str) -> Vec<Filter> {
+        let mut clauses = Vec::new();
+        if let Some(f) = self.from {
+            clauses.push(Filter::cmp(field, Op::Gte, f));
+        }
+        if let Some(t) = self.to {
+            clauses.push(Filter::cmp(field, Op::Lte, t));
+        }
+        clauses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2023-11-14T22:13:20Z
+    const NOW_MS: i64 = 1_700_000_000_000;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(NOW_MS).unwrap()
+    }
+
+    #[test]
+    fn durations() {
+        for (raw, ms) in [
+            ("250ms", Some(250)),
+            ("90s", Some(90_000)),
+            ("5m", Some(300_000)),
+            ("1h30m", Some(5_400_000)),
+            ("2d12h", Some(216_000_000)),
+            ("1w", Some(604_800_000)),
+            (" 1m1ms ", Some(60_001)),
+            ("0s", Some(0)),
+            ("", None),
+            ("5", None),
+            ("m", None),
+            ("5x", None),
+            ("1h-5m", None),
+            ("-5m", None),
+            ("1.5h", None),
+            ("99999999999999999999s", None),
+            ("15250000000000w", None),
+        ] {
+            assert_eq!(parse_duration(raw), ms, "{raw:?}");
+        }
+        assert_eq!(format_duration(5_400_000), "1h30m");
+        assert_eq!(format_duration(0), "0ms");
+        assert_eq!(
+            parse_duration(&format_duration(694_861_001)),
+            Some(694_861_001)
+        );
+    }
+
+    #[test]
+    fn points_in_time() {
+        let midnight = 1_699_920_000_000;
+        for (raw, ms) in [
+            ("now", NOW_MS),
+            ("today", midnight),
+            ("yesterday", midnight - 86_400_000),
+            ("now-2h", NOW_MS - 7_200_000),
+            ("now+15m", NOW_MS + 900_000),
+            ("1h", NOW_MS - 3_600_000),
+            ("2023-11-14T22:13:20Z", NOW_MS),
+            ("2023-11-14T23:13:20+01:00", NOW_MS),
+            ("2023-11-14", midnight),
+            // Epoch seconds up to 10 digits, milliseconds beyond
+            ("1700000000", NOW_MS),
+            ("0", 0),
+            ("9999999999", 9_999_999_999_000),
+            ("10000000000", 10_000_000_000),
+            ("1700000000000", NOW_MS),
+        ] {
+            assert_eq!(parse_time(raw, now()), Ok(ms), "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_points_in_time() {
+        for raw in [
+            "",
+            "later",
+            "now-",
+            "now*2h",
+            "now-2x",
+            "now-15250000w",
+            "now+9223372036854775807ms",
+            "now-99999999999999999999w",
+            "-1000",
+            "2023-13-01",
+            "99999999999999999999",
+            "15250000w",
+        ] {
+            let err = parse_time(raw, now()).unwrap_err();
+            assert!(err.starts_with("invalid time"), "{raw:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn windows() {
+        for (raw, from, to) in [
+            ("now-2h..now-1h", NOW_MS - 7_200_000, NOW_MS - 3_600_000),
+            ("now-30m..", NOW_MS - 1_800_000, NOW_MS),
+            ("2h..1h", NOW_MS - 7_200_000, NOW_MS - 3_600_000),
+            ("1700000000..1700003600", NOW_MS, NOW_MS + 3_600_000),
+        ] {
+            let window = TimeRange::parse_window(raw, now()).unwrap();
+            assert_eq!(
+                window,
+                TimeRange {
+                    from: Some(from),
+                    to: Some(to)
+                },
+                "{raw:?}"
+            );
+        }
+        for raw in [
+            "now-1h",
+            "now..now-1h",
+            "now..now",
+            "..now",
+            "now-1h..later",
+        ] {
+            assert!(TimeRange::parse_window(raw, now()).is_err(), "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let range = TimeRange::parse(None, Some("1700000000"), Some("1700003600")).unwrap();
+        assert_eq!(range.from, Some(NOW_MS));
+        assert!(range.contains(NOW_MS) && range.contains(NOW_MS + 3_600_000));
+        assert!(!range.contains(NOW_MS - 1));
+        assert_eq!(TimeRange::parse(None, None, None), Ok(TimeRange::default()));
+        for (since, from, to) in [
+            (Some("1h"), Some("now-1h"), None),
+            (None, Some("now"), Some("now-1h")),
+            (Some("1x"), None, None),
+            (Some("15250000w"), None, None),
+            (Some("99999999999999999999w"), None, None),
+            (None, None, Some("soon")),
+        ] {
+            assert!(
+                TimeRange::parse(since, from, to).is_err(),
+                "{since:?} {from:?} {to:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Trace summaries for `/traces`.
+//!
+//! Every entry with a `traceId` updates its trace's summary at ingest: root
+//! operation, projects involved, span and entry counts, time bounds and
+//! errors. Summaries of active traces are kept in memory and persisted to
+//! the `traces` Meilisearch index, which `/traces` queries. A trace idle for
+//! [`IDLE_MS`] is dropped from memory once persisted; if more of its entries
+//! arrive later, the new summary is merged with the stored one; span ids are
+//! persisted with it so `spanCount` stays exact across the merge.
+
+use std::collections::BTreeSet;
+
+use chrono::Utc;
+use dashmap::{DashMap, DashSet};
+use meilisearch_sdk::client::Client;
+use meilisearch_sdk::documents::DocumentsQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::filter::Filter;
+use crate::meili::TRACES_INDEX;
+use crate::models::{LogEntry, LogLevel};
+
+/// Fields returned by `/traces`; `spanIds` is only kept for merging.
+pub const LISTED_FIELDS: &[&str] = &[
+    "traceId",
+    "root",
+    "rootProject",
+    "projects",
+    "spanCount",
+    "entryCount",
+    "errorCount",
+    "hasError",
+    "startMs",
+    "endMs",
+    "durationMs",
+];
+
+/// How long a trace stays in memory after its last entry.
+const IDLE_MS: i64 = 600_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceSummary {
+    pub trace_id: String,
+    /// `meta.operation` or message of the earliest entry without a parent span
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_project: Option<String>,
+    /// Projects with entries in the trace
+    pub projects: BTreeSet<String>,
+    /// Distinct span ids seen, persisted so later entries merge exactly
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub span_ids: BTreeSet<String>,
+    pub span_count: usize,
+    pub entry_count: u64,
+    pub error_count: u64,
+    pub has_error: bool,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub duration_ms: i64,
+}
+
+impl TraceSummary {
+    /// Fold in a summary persisted before this one started.
+    fn absorb(&mut self, stored: TraceSummary) {
+        if stored.root.is_some() {
+            self.root = stored.root;
+            self.root_project = stored.root_project;
+        }
+        self.projects.extend(stored.projects);
+        self.span_ids.extend(stored.span_ids);
+        self.span_count = self.span_ids.len();
+        self.entry_count += stored.entry_count;
+        self.error_count += stored.error_count;
+        self.has_error |= stored.has_error;
+        self.start_ms = self.start_ms.min(stored.start_ms);
+        self.end_ms = self.end_ms.max(stored.end_ms);
+        self.duration_ms = self.end_ms - self.start_ms;
+    }
+}
+
+struct Active {
+    summary: TraceSummary,
+    /// Timestamp of the entry `root` came from
+    root_ms: Option<i64>,
+    /// Not yet merged with a stored summary
+    fresh: bool,
+    /// Server time of the last entry
+    touched_ms: i64,
+}
+
+pub struct TraceTracker {
+    traces: DashMap<String, Active>,
+    /// Trace ids changed since the last flush
+    dirty: DashSet<String>,
+}
+
+impl TraceTracker {
+    pub fn new() -> Self {
+        Self {
+            traces: DashMap::new(),
+            dirty: DashSet::new(),
+        }
+    }
+
+    /// Fold an entry into its trace's summary.
+    pub fn record(&self, entry: &LogEntry) {
+        let Some(trace_id) = entry.trace_id.as_deref().filter(|t| !t.is_empty()) else {
+            return;
+        };
+        let ts = entry.timestamp_ms;
+        // A reported duration moves the start back, as in span trees
+        let start = entry
+            .meta
+            .as_ref()
+            .and_then(|m| m.get("durationMs"))
+            .and_then(Value::as_f64)
+            .map_or(ts, |ms| ts - ms.round() as i64);
+        let error =
+            matches!(entry.level, LogLevel::Error | LogLevel::Fatal) || entry.error.is_some();
+
+        let mut active = self
+            .traces
+            .entry(trace_id.to_string())
+            .or_insert_with(|| Active {
+                summary: TraceSummary {
+                    trace_id: trace_id.to_string(),
+                    root: None,
+                    root_project: None,
+                    projects: BTreeSet::new(),
+                    span_ids: BTreeSet::new(),
+                    span_count: 0,
+                    entry_count: 0,
+                    error_count: 0,
+                    has_error: false,
+                    start_ms: start,
+                    end_ms: ts,
+                    duration_ms: 0,
+                },
+                root_ms: None,
+                fresh: true,
+                touched_ms: 0,
+            });
+        let active = &mut *active;
+        active.touched_ms = Utc::now().timestamp_millis();
+        if entry.parent_span_id.is_none() && active.root_ms.is_none_or(|r| ts < r) {
+            active.root_ms = Some(ts);
+            active.summary.root = Some(
+                entry
+                    .meta
+                    .as_ref()
+                    .and_then(|m| m.get("operation"))
+                    .and_then(Value::as_str)
+                    .unwrap_or(&entry.message)
+                    .to_string(),
+            );
+            active.summary.root_project = Some(entry.project.clone());
+        }
+
+        let summary = &mut active.summary;
+        summary.projects.insert(entry.project.clone());
+        if let Some(span) = &entry.span_id {
+            summary.span_ids.insert(span.clone());
+        }
+        summary.span_count = summary.span_ids.len();
+        summary.entry_count += 1;
+        if error {
+            summary.error_count += 1;
+            summary.has_error = true;
+        }
+        summary.start_ms = summary.start_ms.min(start);
+        summary.end_ms = summary.end_ms.max(ts);
+        summary.duration_ms = summary.end_ms - summary.start_ms;
+        self.dirty.insert(trace_id.to_string());
+    }
+
+    /// Persist changed summaries, merging new ones with what's stored, and
+    /// drop idle traces from memory.
+    pub async fn flush(&self, client: &Client) {
+        let ids: Vec<String> = self.dirty.iter().map(|k| k.clone()).collect();
+        for id in &ids {
+            self.dirty.remove(id);
+        }
+
+        let fresh: Vec<String> = ids
+            .iter()
+            .filter(|id| self.traces.get(*id).is_some_and(|a| a.fresh))
+            .cloned()
+            .collect();
+        if !fresh.is_empty() {
+            match stored(client, &fresh).await {
+                Ok(stored) => {
+                    for summary in stored {
+                        if let Some(mut active) = self.traces.get_mut(&summary.trace_id) {
+                            active.summary.absorb(summary);
+                        }
+                    }
+                    for id in &fresh {
+                        if let Some(mut active) = self.traces.get_mut(id) {
+                            active.fresh = false;
+                        }
+                    }
+                }
+                // Try again on the next flush rather than overwrite them
+                Err(e) => {
+                    tracing::error!("Trace lookup error: {:?}", e);
+                    for id in fresh {
+                        self.dirty.insert(id);
+                    }
+                }
+            }
+        }
+
+        let summaries: Vec<TraceSummary> = ids
+            .iter()
+            .filter_map(|id| {
+                let active = self.traces.get(id)?;
+                (!active.fresh).then(|| active.summary.clone())
+            })
+            .collect();
+        if !summaries.is_empty() {
+            let index = client.index(TRACES_INDEX);
+            match index.add_documents(&summaries, Some("traceId")).await {
+                Ok(_task) => tracing::debug!("Persisted {} traces", summaries.len()),
+                Err(e) => {
+                    tracing::error!("Trace persist error: {:?}", e);
+                    for summary in &summaries {
+                        self.dirty.insert(summary.trace_id.clone());
+                    }
+                }
+            }
+        }
+
+        let cutoff = Utc::now().timestamp_millis() - IDLE_MS;
+        self.traces
+            .retain(|id, active| active.touched_ms >= cutoff || self.dirty.contains(id));
+    }
+}
+
+/// Persisted summaries of the given traces.
+async fn stored(client: &Client, ids: &[String]) -> anyhow::Result<Vec<TraceSummary>> {
+    let index = client.index(TRACES_INDEX);
+    let mut found = Vec::new();
+    for chunk in ids.chunks(1000) {
+        let filter = Filter::is_in("traceId", chunk).render()?;
+        let page = DocumentsQuery::new(&index)
+            .with_filter(&filter)
+            .with_limit(chunk.len())
+            .execute::<TraceSummary>()
+            .await?;
+        found.extend(page.results);
+    }
+    Ok(found)
+}